@@ -0,0 +1,40 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use eframe::egui::Color32;
+use raygui_fiddling::sketch::{NodeIndex, SceneObject, ShapeKind, find_object_by_id};
+use std::hint::black_box;
+
+/// 10,000 roots and nothing below them, the worst case for a linear search.
+fn build_flat() -> Vec<SceneObject> {
+    (1..=10_000)
+        .map(|id| SceneObject::new(id, "n", ShapeKind::Square, Color32::WHITE))
+        .collect()
+}
+
+/// Every hundredth ID, so hits are spread over the whole scene.
+fn probes() -> Vec<u32> {
+    (1..=10_000).step_by(100).collect()
+}
+
+fn bench_lookup(c: &mut Criterion) {
+    let tree = build_flat();
+    let index = NodeIndex::build(&tree);
+    let mut group = c.benchmark_group("lookup_10k_flat");
+    group.bench_function("recursive", |b| {
+        b.iter(|| {
+            for id in probes() {
+                black_box(find_object_by_id(&tree, black_box(id)));
+            }
+        })
+    });
+    group.bench_function("indexed", |b| {
+        b.iter(|| {
+            for id in probes() {
+                black_box(index.get(&tree, black_box(id)));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_lookup);
+criterion_main!(benches);
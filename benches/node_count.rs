@@ -0,0 +1,31 @@
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use eframe::egui::Color32;
+use raygui_fiddling::sketch::{SceneObject, ShapeKind, flatten, node_count};
+use std::hint::black_box;
+
+/// Full binary tree with leaves at depth 18: 2^19 - 1 = 524,287 nodes.
+fn build_tree() -> Vec<SceneObject> {
+    fn level(id: &mut u32, depth: u32) -> SceneObject {
+        *id += 1;
+        let mut o = SceneObject::new(*id, "n", ShapeKind::Square, Color32::WHITE);
+        if depth < 18 {
+            o.children = vec![level(id, depth + 1), level(id, depth + 1)];
+        }
+        o
+    }
+    vec![level(&mut 0, 0)]
+}
+
+fn bench_node_count(c: &mut Criterion) {
+    let tree = build_tree();
+    let nodes = node_count(&tree);
+    assert_eq!(nodes, 524_287);
+    let mut group = c.benchmark_group("binary_tree_depth_18");
+    group.throughput(Throughput::Elements(nodes as u64));
+    group.bench_function("node_count", |b| b.iter(|| node_count(black_box(&tree))));
+    group.bench_function("flatten", |b| b.iter(|| flatten(black_box(&tree)).len()));
+    group.finish();
+}
+
+criterion_group!(benches, bench_node_count);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use eframe::egui::Color32;
+use eframe::wgpu;
+use raygui_fiddling::sketch::{SceneObject, ShapeKind, animate, animate_on_gpu};
+use raygui_fiddling::vm::Env;
+use raygui_fiddling::wgpu_compute::RotationCompute;
+use std::hint::black_box;
+
+/// A hundred roots with a thousand children each: 100,100 nodes.
+fn build_tree() -> Vec<SceneObject> {
+    let mut id = 0;
+    let mut next = || {
+        id += 1;
+        SceneObject::new(id, "n", ShapeKind::Square, Color32::WHITE)
+    };
+    (0..100)
+        .map(|_| {
+            let mut root = next();
+            root.children = (0..1000).map(|_| next()).collect();
+            root
+        })
+        .collect()
+}
+
+/// Any adapter will do, software included; `None` skips the GPU case.
+fn compute() -> Option<RotationCompute> {
+    let instance = wgpu::Instance::default();
+    let adapter =
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))?;
+    if !RotationCompute::supported(&adapter) {
+        return None;
+    }
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None))
+            .ok()?;
+    Some(RotationCompute::new(&device, &queue))
+}
+
+fn bench_rotation(c: &mut Criterion) {
+    let mut tree = build_tree();
+    let env = Env {
+        t: 0.0,
+        dt: 1.0 / 60.0,
+        frame: 0,
+    };
+    let mut group = c.benchmark_group("rotation_100k");
+    group.bench_function("cpu", |b| {
+        b.iter(|| animate(&mut tree, 1.0, black_box(&env)))
+    });
+    match compute() {
+        // a frame's dispatch plus waiting for its readback to land in the tree
+        Some(mut compute) => {
+            group.bench_function("gpu", |b| {
+                b.iter(|| {
+                    animate_on_gpu(&mut tree, 1.0, black_box(&env), &mut compute);
+                    compute.sync(&mut tree);
+                })
+            });
+        }
+        None => eprintln!("no adapter with compute shaders, skipping rotation_100k/gpu"),
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_rotation);
+criterion_main!(benches);
@@ -0,0 +1,59 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use eframe::egui::Color32;
+use raygui_fiddling::sketch::{SceneObject, ShapeKind, animate};
+use raygui_fiddling::vm::Env;
+use std::hint::black_box;
+
+/// Ten roots with ten children with a hundred children each: 10,110 nodes.
+fn build_tree() -> Vec<SceneObject> {
+    let mut id = 0;
+    let mut next = || {
+        id += 1;
+        SceneObject::new(id, "n", ShapeKind::Square, Color32::WHITE)
+    };
+    (0..10)
+        .map(|_| {
+            let mut root = next();
+            root.children = (0..10)
+                .map(|_| {
+                    let mut mid = next();
+                    mid.children = (0..100).map(|_| next()).collect();
+                    mid
+                })
+                .collect();
+            root
+        })
+        .collect()
+}
+
+/// The hand-written recursion `animate` used before `visit_mut` existed.
+fn animate_recursive(o: &mut SceneObject, dt: f32) {
+    o.current_rotation += o.rotation_speed * dt;
+    for c in &mut o.children {
+        animate_recursive(c, dt);
+    }
+}
+
+fn bench_animate(c: &mut Criterion) {
+    let mut tree = build_tree();
+    let mut group = c.benchmark_group("animate_10k");
+    group.bench_function("recursive", |b| {
+        b.iter(|| {
+            for o in &mut tree {
+                animate_recursive(o, black_box(1.0 / 60.0));
+            }
+        })
+    });
+    group.bench_function("visit_mut", |b| {
+        let env = Env {
+            t: 0.0,
+            dt: 1.0 / 60.0,
+            frame: 0,
+        };
+        b.iter(|| animate(&mut tree, 1.0, black_box(&env)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_animate);
+criterion_main!(benches);
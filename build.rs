@@ -0,0 +1,24 @@
+//! Exposes the locked egui and eframe versions as `EGUI_VERSION` and
+//! `EFRAME_VERSION`, for Help > Renderer Info.
+
+use std::fs;
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+    for krate in ["egui", "eframe"] {
+        let version = locked_version(&lock, krate).unwrap_or("unknown");
+        println!("cargo:rustc-env={}_VERSION={version}", krate.to_uppercase());
+    }
+}
+
+/// The `version` line right after `name = "<krate>"` in a lockfile.
+fn locked_version<'a>(lock: &'a str, krate: &str) -> Option<&'a str> {
+    let name = format!("name = \"{krate}\"");
+    let mut lines = lock.lines();
+    lines.find(|l| *l == name)?;
+    lines
+        .next()?
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')
+}
@@ -0,0 +1,64 @@
+//! User settings that outlive a session, kept in `settings.toml` next to the binary's
+//! working directory.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::sketch::SceneObject;
+use crate::storage;
+
+pub const CONFIG_PATH: &str = "settings.toml";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Saved subtrees by name, inserted as copies through the inspector.
+    #[serde(default)]
+    pub prefabs: HashMap<String, Vec<SceneObject>>,
+    /// Where each node's inspector window was last left, by node ID.
+    #[serde(default)]
+    pub window_positions: HashMap<u32, egui::Pos2>,
+    /// Scene files last saved or opened, most recent first.
+    #[serde(default)]
+    pub recent_scenes: VecDeque<PathBuf>,
+    /// Named colors offered as swatches in the inspector.
+    #[serde(default = "default_palette")]
+    pub palette: Vec<(String, Color32)>,
+}
+
+fn default_palette() -> Vec<(String, Color32)> {
+    vec![
+        ("Primary".into(), Color32::RED),
+        ("Accent".into(), Color32::BLUE),
+    ]
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            prefabs: HashMap::new(),
+            window_positions: HashMap::new(),
+            recent_scenes: VecDeque::new(),
+            palette: default_palette(),
+        }
+    }
+}
+
+impl Config {
+    /// A missing file is a fresh install, not an error.
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        match storage::read(path) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AppError> {
+        storage::write(path, &toml::to_string(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+//! Per-node metrics as CSV, for poking at a scene's shape in a spreadsheet.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use eframe::egui;
+
+use crate::sketch::{SceneObject, count_descendants, visit};
+
+const HEADER: &str = "id,slug,name,depth,child_count,descendant_count,layer,priority,\
+                      pos_x,pos_y,rotation_speed,has_script,has_comment";
+
+/// Quotes a field when it holds a comma, quote or line break, doubling any quotes.
+fn field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+/// One row per node in pre-order, with a header. Roots are depth 0. Nodes
+/// without a position in `layout` leave `pos_x` and `pos_y` empty.
+pub fn export_metrics_csv(objects: &[SceneObject], layout: &HashMap<u32, egui::Vec2>) -> String {
+    let mut out = format!("{HEADER}\n");
+    visit(objects, 0, &mut |o, depth| {
+        let (x, y) = layout
+            .get(&o.id)
+            .map_or((String::new(), String::new()), |p| {
+                (p.x.to_string(), p.y.to_string())
+            });
+        let _ = writeln!(
+            out,
+            "{},{},{},{depth},{},{},{},{},{x},{y},{},{},{}",
+            o.id,
+            field(&o.slug),
+            field(&o.text),
+            o.children.len(),
+            count_descendants(o),
+            o.layer,
+            o.priority,
+            o.rotation_speed,
+            !o.script.trim().is_empty(),
+            !o.comment.trim().is_empty(),
+        );
+    });
+    out
+}
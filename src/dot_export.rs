@@ -0,0 +1,45 @@
+use std::fmt::Write;
+
+use crate::sketch::{NodeIndex, SceneObject, ShapeKind, resolve_appearance};
+
+fn shape_name(shape: ShapeKind) -> &'static str {
+    match shape {
+        ShapeKind::Square | ShapeKind::Reference { .. } => "box",
+        ShapeKind::Circle => "circle",
+        ShapeKind::Triangle => "triangle",
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// `scene` and `index` are the whole scene, for exporting references as their targets.
+fn write_node(out: &mut String, o: &SceneObject, scene: &[SceneObject], index: &NodeIndex) {
+    let (shape, color) = resolve_appearance(scene, index, o);
+    let [r, g, b, _] = color.to_array();
+    let _ = writeln!(
+        out,
+        "    n{} [label=\"{}\", shape={}, color=\"#{r:02x}{g:02x}{b:02x}\"];",
+        o.id,
+        escape(&o.text),
+        shape_name(shape),
+    );
+    for c in &o.children {
+        let _ = writeln!(out, "    n{} -> n{};", o.id, c.id);
+        write_node(out, c, scene, index);
+    }
+}
+
+/// Serializes the scene to a Graphviz `digraph`, emitting nodes and edges depth-first.
+pub fn export(objects: &[SceneObject]) -> String {
+    let mut out = String::from("digraph scene {\n");
+    let index = NodeIndex::build(objects);
+    for o in objects {
+        write_node(&mut out, o, objects, &index);
+    }
+    out.push_str("}\n");
+    out
+}
@@ -0,0 +1,323 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use eframe::egui::Color32;
+
+use crate::sketch::{SceneObject, ShapeKind};
+
+/* ---------------- errors ---------------- */
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    UnexpectedToken(String),
+    UnexpectedEnd,
+    UnknownAttribute(String),
+    MultipleParents(String),
+    Cycle(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedToken(t) => write!(f, "unexpected token `{t}`"),
+            Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+            Self::UnknownAttribute(a) => write!(f, "unknown attribute value `{a}`"),
+            Self::MultipleParents(n) => write!(f, "node `{n}` has more than one parent"),
+            Self::Cycle(n) => write!(f, "node `{n}` is part of a cycle"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/* ---------------- tokenizer ---------------- */
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Arrow,
+    Semi,
+    Comma,
+    Eq,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut out = vec![];
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '{' | '}' | '[' | ']' | ';' | ',' | '=' => {
+                chars.next();
+                out.push(match c {
+                    '{' => Token::LBrace,
+                    '}' => Token::RBrace,
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    ';' => Token::Semi,
+                    ',' => Token::Comma,
+                    _ => Token::Eq,
+                });
+            }
+            '-' => {
+                chars.next();
+                if chars.next() != Some('>') {
+                    return Err(ParseError::UnexpectedToken("-".into()));
+                }
+                out.push(Token::Arrow);
+            }
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => s.push('\n'),
+                            c => s.extend(c),
+                        },
+                        Some(c) => s.push(c),
+                        None => return Err(ParseError::UnexpectedEnd),
+                    }
+                }
+                out.push(Token::Ident(s));
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '#' || c == '.' => {
+                let mut s = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '#' || c == '.') {
+                        break;
+                    }
+                    s.push(c);
+                    chars.next();
+                }
+                out.push(Token::Ident(s));
+            }
+            c => return Err(ParseError::UnexpectedToken(c.into())),
+        }
+    }
+    Ok(out)
+}
+
+/* ---------------- parser ---------------- */
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, ParseError> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t.ok_or(ParseError::UnexpectedEnd)
+    }
+
+    fn expect(&mut self, want: Token) -> Result<(), ParseError> {
+        let t = self.next()?;
+        if t == want {
+            Ok(())
+        } else {
+            Err(ParseError::UnexpectedToken(describe(&t)))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        match self.next()? {
+            Token::Ident(s) => Ok(s),
+            t => Err(ParseError::UnexpectedToken(describe(&t))),
+        }
+    }
+
+    fn attrs(&mut self) -> Result<Vec<(String, String)>, ParseError> {
+        let mut out = vec![];
+        if self.peek() != Some(&Token::LBracket) {
+            return Ok(out);
+        }
+        self.next()?;
+        loop {
+            if self.peek() == Some(&Token::RBracket) {
+                self.next()?;
+                return Ok(out);
+            }
+            let key = self.ident()?;
+            self.expect(Token::Eq)?;
+            let value = self.ident()?;
+            out.push((key, value));
+            if self.peek() == Some(&Token::Comma) {
+                self.next()?;
+            }
+        }
+    }
+}
+
+fn describe(t: &Token) -> String {
+    match t {
+        Token::Ident(s) => s.clone(),
+        Token::Arrow => "->".into(),
+        Token::Semi => ";".into(),
+        Token::Comma => ",".into(),
+        Token::Eq => "=".into(),
+        Token::LBrace => "{".into(),
+        Token::RBrace => "}".into(),
+        Token::LBracket => "[".into(),
+        Token::RBracket => "]".into(),
+    }
+}
+
+fn parse_color(s: &str) -> Result<Color32, ParseError> {
+    let named = match s.to_ascii_lowercase().as_str() {
+        "red" => Some(Color32::RED),
+        "blue" => Some(Color32::BLUE),
+        "green" => Some(Color32::GREEN),
+        "yellow" => Some(Color32::YELLOW),
+        "white" => Some(Color32::WHITE),
+        "black" => Some(Color32::BLACK),
+        "gray" | "grey" => Some(Color32::GRAY),
+        "orange" => Some(Color32::from_rgb(255, 128, 0)),
+        "purple" => Some(Color32::from_rgb(128, 0, 255)),
+        _ => None,
+    };
+    if let Some(c) = named {
+        return Ok(c);
+    }
+    s.strip_prefix('#')
+        .filter(|hex| hex.len() == 6)
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .map(|v| Color32::from_rgb((v >> 16) as u8, (v >> 8) as u8, v as u8))
+        .ok_or_else(|| ParseError::UnknownAttribute(s.into()))
+}
+
+fn parse_shape(s: &str) -> Result<ShapeKind, ParseError> {
+    match s.to_ascii_lowercase().as_str() {
+        "box" | "square" | "rect" | "rectangle" => Ok(ShapeKind::Square),
+        "circle" | "ellipse" | "oval" | "point" => Ok(ShapeKind::Circle),
+        "triangle" => Ok(ShapeKind::Triangle),
+        _ => Err(ParseError::UnknownAttribute(s.into())),
+    }
+}
+
+/// Parses a minimal `digraph { A -> B; B [color=blue, shape=circle]; }` into a
+/// forest of scene objects. Node IDs are sequential placeholders; callers are
+/// expected to reassign them before inserting the result into a scene.
+pub fn parse(input: &str) -> Result<Vec<SceneObject>, ParseError> {
+    let mut p = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+    };
+
+    let kw = p.ident()?;
+    if kw != "digraph" {
+        return Err(ParseError::UnexpectedToken(kw));
+    }
+    if matches!(p.peek(), Some(Token::Ident(_))) {
+        p.next()?; // graph name, ignored
+    }
+    p.expect(Token::LBrace)?;
+
+    let mut nodes: Vec<SceneObject> = vec![];
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut parent: Vec<Option<usize>> = vec![];
+    let mut children: Vec<Vec<usize>> = vec![];
+
+    let mut node = |name: String, nodes: &mut Vec<SceneObject>| -> usize {
+        *index.entry(name.clone()).or_insert_with(|| {
+            let id = nodes.len() as u32 + 1;
+            nodes.push(SceneObject::new(
+                id,
+                &name,
+                ShapeKind::Square,
+                Color32::WHITE,
+            ));
+            parent.push(None);
+            children.push(vec![]);
+            nodes.len() - 1
+        })
+    };
+
+    let mut edges: Vec<(usize, usize)> = vec![];
+    loop {
+        match p.peek() {
+            Some(Token::RBrace) => {
+                p.next()?;
+                break;
+            }
+            Some(Token::Semi) => {
+                p.next()?;
+                continue;
+            }
+            None => return Err(ParseError::UnexpectedEnd),
+            _ => {}
+        }
+
+        let mut chain = vec![node(p.ident()?, &mut nodes)];
+        while p.peek() == Some(&Token::Arrow) {
+            p.next()?;
+            chain.push(node(p.ident()?, &mut nodes));
+        }
+        edges.extend(chain.windows(2).map(|w| (w[0], w[1])));
+
+        // attributes on an edge statement describe the edge, which we don't model
+        let attrs = p.attrs()?;
+        if let [i] = chain[..] {
+            for (key, value) in attrs {
+                match key.as_str() {
+                    "color" => nodes[i].color = parse_color(&value)?,
+                    "shape" => nodes[i].shape = parse_shape(&value)?,
+                    "label" => {
+                        nodes[i].text.clone_from(&value);
+                        nodes[i].text_buffer.clone_from(&value);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    for (from, to) in edges {
+        if parent[to].is_some_and(|p| p != from) {
+            return Err(ParseError::MultipleParents(nodes[to].text.clone()));
+        }
+        if parent[to].is_none() {
+            parent[to] = Some(from);
+            children[from].push(to);
+        }
+    }
+
+    fn build(i: usize, nodes: &[SceneObject], children: &[Vec<usize>]) -> SceneObject {
+        let mut o = nodes[i].clone();
+        o.children = children[i]
+            .iter()
+            .map(|&c| build(c, nodes, children))
+            .collect();
+        o
+    }
+
+    let roots: Vec<SceneObject> = (0..nodes.len())
+        .filter(|&i| parent[i].is_none())
+        .map(|i| build(i, &nodes, &children))
+        .collect();
+
+    // nodes on a cycle are never reached from a root
+    let mut reached = HashSet::new();
+    let mut stack: Vec<&SceneObject> = roots.iter().collect();
+    while let Some(o) = stack.pop() {
+        reached.insert(o.id);
+        stack.extend(&o.children);
+    }
+    if let Some(o) = nodes.iter().find(|o| !reached.contains(&o.id)) {
+        return Err(ParseError::Cycle(o.text.clone()));
+    }
+
+    Ok(roots)
+}
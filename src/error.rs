@@ -0,0 +1,87 @@
+use std::fmt;
+
+use crate::dot_import;
+
+/// Anything that can go wrong while bringing the editor up or loading data into it.
+#[derive(Debug)]
+pub enum AppError {
+    IoError(std::io::Error),
+    ParseError(String),
+    AudioError(String),
+    /// Headless rendering couldn't get a GPU or write the image.
+    RenderError(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IoError(e) => write!(f, "I/O error: {e}"),
+            Self::ParseError(e) => write!(f, "parse error: {e}"),
+            Self::AudioError(e) => write!(f, "audio error: {e}"),
+            Self::RenderError(e) => write!(f, "render error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        Self::IoError(e)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::ParseError(e.to_string())
+    }
+}
+
+impl From<toml::de::Error> for AppError {
+    fn from(e: toml::de::Error) -> Self {
+        Self::ParseError(e.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for AppError {
+    fn from(e: toml::ser::Error) -> Self {
+        Self::ParseError(e.to_string())
+    }
+}
+
+impl From<dot_import::ParseError> for AppError {
+    fn from(e: dot_import::ParseError) -> Self {
+        Self::ParseError(e.to_string())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<notify::Error> for AppError {
+    fn from(e: notify::Error) -> Self {
+        Self::IoError(std::io::Error::other(e))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<png::EncodingError> for AppError {
+    fn from(e: png::EncodingError) -> Self {
+        Self::RenderError(e.to_string())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<image::ImageError> for AppError {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            image::ImageError::IoError(e) => Self::IoError(e),
+            e => Self::ParseError(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Tiny arithmetic expressions over the scene clock `t`, used for node speeds:
+//! numbers, `t`, `+ - * /`, parentheses and `sin`, `cos`, `abs`, `floor`.
+
+use std::fmt;
+
+/* ---------------- errors ---------------- */
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprError {
+    UnexpectedChar(char),
+    UnexpectedEnd,
+    UnknownName(String),
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedChar(c) => write!(f, "unexpected `{c}`"),
+            Self::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            Self::UnknownName(n) => write!(f, "unknown name `{n}`"),
+        }
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+/* ---------------- ast ---------------- */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Func {
+    Sin,
+    Cos,
+    Abs,
+    Floor,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Num(f32),
+    T,
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    Call(Func, Box<Expr>),
+}
+
+impl Expr {
+    pub fn eval(&self, t: f32) -> f32 {
+        match self {
+            Self::Num(n) => *n,
+            Self::T => t,
+            Self::Neg(e) => -e.eval(t),
+            Self::Add(a, b) => a.eval(t) + b.eval(t),
+            Self::Sub(a, b) => a.eval(t) - b.eval(t),
+            Self::Mul(a, b) => a.eval(t) * b.eval(t),
+            Self::Div(a, b) => a.eval(t) / b.eval(t),
+            Self::Call(f, e) => {
+                let x = e.eval(t);
+                match f {
+                    Func::Sin => x.sin(),
+                    Func::Cos => x.cos(),
+                    Func::Abs => x.abs(),
+                    Func::Floor => x.floor(),
+                }
+            }
+        }
+    }
+}
+
+/* ---------------- parser ---------------- */
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ExprError> {
+        match self.peek() {
+            Some(p) if p == c => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(p) => Err(ExprError::UnexpectedChar(p)),
+            None => Err(ExprError::UnexpectedEnd),
+        }
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.product()?;
+        loop {
+            if self.eat('+') {
+                lhs = Expr::Add(Box::new(lhs), Box::new(self.product()?));
+            } else if self.eat('-') {
+                lhs = Expr::Sub(Box::new(lhs), Box::new(self.product()?));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    // product := unary (('*' | '/') unary)*
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.unary()?;
+        loop {
+            if self.eat('*') {
+                lhs = Expr::Mul(Box::new(lhs), Box::new(self.unary()?));
+            } else if self.eat('/') {
+                lhs = Expr::Div(Box::new(lhs), Box::new(self.unary()?));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    // unary := '-' unary | atom
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        if self.eat('-') {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    // atom := number | 't' | func '(' sum ')' | '(' sum ')'
+    fn atom(&mut self) -> Result<Expr, ExprError> {
+        match self.peek() {
+            None => Err(ExprError::UnexpectedEnd),
+            Some('(') => {
+                self.pos += 1;
+                let e = self.sum()?;
+                self.expect(')')?;
+                Ok(e)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.pos;
+                while self
+                    .chars
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+                {
+                    self.pos += 1;
+                }
+                let text: String = self.chars[start..self.pos].iter().collect();
+                text.parse()
+                    .map(Expr::Num)
+                    .map_err(|_| ExprError::UnknownName(text))
+            }
+            Some(c) if c.is_alphabetic() => {
+                let start = self.pos;
+                while self
+                    .chars
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_alphanumeric())
+                {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                let func = match name.as_str() {
+                    "t" => return Ok(Expr::T),
+                    "sin" => Func::Sin,
+                    "cos" => Func::Cos,
+                    "abs" => Func::Abs,
+                    "floor" => Func::Floor,
+                    _ => return Err(ExprError::UnknownName(name)),
+                };
+                self.expect('(')?;
+                let arg = self.sum()?;
+                self.expect(')')?;
+                Ok(Expr::Call(func, Box::new(arg)))
+            }
+            Some(c) => Err(ExprError::UnexpectedChar(c)),
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Expr, ExprError> {
+    let mut p = Parser {
+        chars: input.chars().collect(),
+        pos: 0,
+    };
+    let e = p.sum()?;
+    match p.peek() {
+        Some(c) => Err(ExprError::UnexpectedChar(c)),
+        None => Ok(e),
+    }
+}
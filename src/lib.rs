@@ -0,0 +1,23 @@
+pub mod config;
+pub mod csv_export;
+pub mod dot_export;
+pub mod dot_import;
+pub mod error;
+pub mod expr;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod offline;
+pub mod scene_diff;
+pub mod scene_file;
+pub mod scene_watch;
+pub mod sketch;
+pub mod sound;
+pub mod storage;
+pub mod svg_export;
+pub mod templates;
+pub mod tween;
+pub mod validate;
+pub mod vm;
+#[cfg(target_arch = "wasm32")]
+mod web;
+pub mod wgpu_compute;
+pub mod wgpu_glow;
@@ -1,8 +1,41 @@
-pub mod sketch;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
 
+#[cfg(not(target_arch = "wasm32"))]
+use clap::Parser;
+#[cfg(not(target_arch = "wasm32"))]
 use eframe::{NativeOptions, Renderer, Result};
+#[cfg(not(target_arch = "wasm32"))]
+use raygui_fiddling::{offline, sketch};
 
+/// Scene tree editor. With `--render-output` and `--scene` it draws the scene
+/// to a PNG and exits instead of opening a window.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Parser)]
+struct Args {
+    /// PNG to render the scene into.
+    #[arg(long, requires = "scene")]
+    render_output: Option<PathBuf>,
+    /// Scene JSON to render.
+    #[arg(long, requires = "render_output")]
+    scene: Option<PathBuf>,
+}
+
+/// The web build is driven by the library's `start` export instead.
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+#[cfg(not(target_arch = "wasm32"))]
 fn main() -> Result<()> {
+    let args = Args::parse();
+    if let (Some(output), Some(scene)) = (&args.render_output, &args.scene) {
+        if let Err(e) = offline::render_to_png(scene, output) {
+            eprintln!("couldn't render {}: {e}", scene.display());
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let opts = NativeOptions {
         renderer: Renderer::Wgpu,
         ..Default::default()
@@ -12,6 +45,6 @@ fn main() -> Result<()> {
     eframe::run_native(
         "Scene Tree (egui + wgpu)",
         opts,
-        Box::new(|cc| Ok(Box::new(sketch::AppState::new(cc)))),
+        Box::new(|cc| Ok(Box::new(sketch::AppState::new(cc)?))),
     )
 }
@@ -0,0 +1,185 @@
+//! Renders a scene file to a PNG without opening a window, for
+//! `--render-output`. One egui frame is run against a fixed-size screen and its
+//! shapes are drawn by egui's own wgpu renderer into an offscreen texture.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::mpsc;
+
+use eframe::egui::{self, Pos2, Rect};
+use eframe::egui_wgpu::{self, ScreenDescriptor};
+use eframe::wgpu;
+
+use crate::error::AppError;
+use crate::scene_file;
+use crate::sketch::{self, NODE_SIZE};
+
+/// Output size in pixels.
+pub const WIDTH: u32 = 1280;
+pub const HEIGHT: u32 = 800;
+/// Space kept clear around the scene, in pixels.
+const MARGIN: f32 = 60.0;
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Lays out the scene at `scene` as a tree, fits it to the image and writes the
+/// picture to `output`.
+pub fn render_to_png(scene: &Path, output: &Path) -> Result<(), AppError> {
+    let scene = scene_file::load(scene)?;
+    let layout = sketch::tree_layout(&scene.objects);
+    let screen = Rect::from_min_size(Pos2::ZERO, egui::vec2(WIDTH as f32, HEIGHT as f32));
+    let (camera, zoom) = fit(&layout, screen);
+
+    let ctx = egui::Context::default();
+    let input = egui::RawInput {
+        screen_rect: Some(screen),
+        ..Default::default()
+    };
+    let frame = ctx.run(input, |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let to_screen = |p: egui::Vec2| screen.center() + (p - camera) * zoom;
+            sketch::paint_scene(
+                ui.painter(),
+                &scene.objects,
+                &scene.links,
+                &layout,
+                &to_screen,
+                zoom,
+            );
+        });
+    });
+    let primitives = ctx.tessellate(frame.shapes, frame.pixels_per_point);
+    let pixels = rasterize(&primitives, &frame.textures_delta)?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(output)?), WIDTH, HEIGHT);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    Ok(())
+}
+
+/// Camera target and zoom that fit every laid-out node inside `screen`. Never
+/// zooms in past 1:1, since nodes are drawn at a fixed pixel size anyway.
+fn fit(layout: &HashMap<u32, egui::Vec2>, screen: Rect) -> (egui::Vec2, f32) {
+    let Some(bounds) = layout
+        .values()
+        .map(|&p| Rect::from_center_size(p.to_pos2(), egui::Vec2::splat(NODE_SIZE)))
+        .reduce(|a, b| a.union(b))
+    else {
+        return (egui::Vec2::ZERO, 1.0);
+    };
+    let room = screen.size() - egui::Vec2::splat(MARGIN * 2.0);
+    let zoom = (room.x / bounds.width())
+        .min(room.y / bounds.height())
+        .clamp(0.1, 1.0);
+    (bounds.center().to_vec2(), zoom)
+}
+
+/// Draws `primitives` into a `WIDTH` × `HEIGHT` texture and reads it back as
+/// tightly packed RGBA rows.
+fn rasterize(
+    primitives: &[egui::ClippedPrimitive],
+    textures: &egui::TexturesDelta,
+) -> Result<Vec<u8>, AppError> {
+    let render_err = |e: &dyn std::fmt::Display| AppError::RenderError(e.to_string());
+    let instance = wgpu::Instance::default();
+    let adapter =
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+            .ok_or_else(|| AppError::RenderError("no GPU adapter available".into()))?;
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None))
+            .map_err(|e| render_err(&e))?;
+
+    let size = wgpu::Extent3d {
+        width: WIDTH,
+        height: HEIGHT,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("offline target"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let mut renderer = egui_wgpu::Renderer::new(&device, FORMAT, None, 1, false);
+    for (id, delta) in &textures.set {
+        renderer.update_texture(&device, &queue, *id, delta);
+    }
+    let screen = ScreenDescriptor {
+        size_in_pixels: [WIDTH, HEIGHT],
+        pixels_per_point: 1.0,
+    };
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    let mut commands = renderer.update_buffers(&device, &queue, &mut encoder, primitives, &screen);
+    {
+        let pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("offline"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        renderer.render(&mut pass.forget_lifetime(), primitives, &screen);
+    }
+
+    // texture-to-buffer copies need each row padded out to the copy alignment
+    let row = WIDTH * 4;
+    let padded_row =
+        row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("offline readback"),
+        size: (padded_row * HEIGHT) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &readback,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: Some(HEIGHT),
+            },
+        },
+        size,
+    );
+    commands.push(encoder.finish());
+    queue.submit(commands);
+
+    let slice = readback.slice(..);
+    let (tx, rx) = mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = tx.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    rx.recv()
+        .map_err(|e| render_err(&e))?
+        .map_err(|e| render_err(&e))?;
+    let pixels = slice
+        .get_mapped_range()
+        .chunks(padded_row as usize)
+        .flat_map(|r| &r[..row as usize])
+        .copied()
+        .collect();
+    Ok(pixels)
+}
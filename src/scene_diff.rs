@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use eframe::egui::Color32;
+use serde::{Deserialize, Serialize};
+
+use crate::sketch::{SceneObject, ShapeKind, detach_node, find_object_by_id_mut, flatten};
+
+/// Node IDs that differ between two scenes. Lists follow depth-first order of the
+/// scene they come from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SceneDiff {
+    /// In `b` only.
+    pub added: Vec<u32>,
+    /// In `a` only.
+    pub removed: Vec<u32>,
+    /// In both, with a different name, color, shape or rotation speed.
+    pub changed: Vec<u32>,
+}
+
+/// Whether the properties compared by [`diff_scenes`] differ.
+pub fn node_changed(a: &SceneObject, b: &SceneObject) -> bool {
+    a.text != b.text
+        || a.color != b.color
+        || a.shape != b.shape
+        || a.rotation_speed != b.rotation_speed
+}
+
+/// Matches nodes by ID; where a node sits in the tree is not compared.
+pub fn diff_scenes(a: &[SceneObject], b: &[SceneObject]) -> SceneDiff {
+    let before: HashMap<u32, &SceneObject> = flatten(a).into_iter().map(|o| (o.id, o)).collect();
+    let after: HashMap<u32, &SceneObject> = flatten(b).into_iter().map(|o| (o.id, o)).collect();
+
+    let mut diff = SceneDiff::default();
+    for o in flatten(b) {
+        match before.get(&o.id) {
+            None => diff.added.push(o.id),
+            Some(old) if node_changed(old, o) => diff.changed.push(o.id),
+            Some(_) => {}
+        }
+    }
+    diff.removed = flatten(a)
+        .into_iter()
+        .filter(|o| !after.contains_key(&o.id))
+        .map(|o| o.id)
+        .collect();
+    diff
+}
+
+/// A node property carried by [`ScenePatch::UpdateField`], with its new value.
+/// These are the properties [`node_changed`] compares.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum UpdatedField {
+    Text(String),
+    Color(Color32),
+    Shape(ShapeKind),
+    RotationSpeed(f32),
+}
+
+impl UpdatedField {
+    /// Every listed property on which `b` differs from `a`, as `b`'s values.
+    fn between(a: &SceneObject, b: &SceneObject) -> Vec<Self> {
+        let mut fields = vec![];
+        if a.text != b.text {
+            fields.push(Self::Text(b.text.clone()));
+        }
+        if a.color != b.color {
+            fields.push(Self::Color(b.color));
+        }
+        if a.shape != b.shape {
+            fields.push(Self::Shape(b.shape));
+        }
+        if a.rotation_speed != b.rotation_speed {
+            fields.push(Self::RotationSpeed(b.rotation_speed));
+        }
+        fields
+    }
+
+    fn apply(self, o: &mut SceneObject) {
+        match self {
+            Self::Text(text) => o.text = text,
+            Self::Color(color) => o.color = color,
+            Self::Shape(shape) => o.shape = shape,
+            Self::RotationSpeed(speed) => o.rotation_speed = speed,
+        }
+    }
+}
+
+/// One step of turning a scene into another, see [`compute_diff`]. Serializable
+/// so a scene can be kept in sync by sending these instead of the whole scene.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ScenePatch {
+    /// Appends `node`, with its whole subtree, under `parent_id` or as a root.
+    AddNode {
+        parent_id: Option<u32>,
+        node: Box<SceneObject>,
+    },
+    /// Removes the node and its subtree.
+    DeleteNode {
+        id: u32,
+    },
+    UpdateField {
+        id: u32,
+        field: UpdatedField,
+    },
+}
+
+/// Patches that turn `from` into `to` when applied in order with [`apply_diff`]:
+/// deletions first, then additions, then field updates.
+///
+/// Nodes are matched by ID. A node that changed parents is deleted and added
+/// again with its subtree, so anything below it comes along. Nodes that stay
+/// put only get `UpdateField`s, and only for the properties `UpdatedField`
+/// lists. Sibling order isn't carried: added nodes go after their siblings.
+pub fn compute_diff(from: &[SceneObject], to: &[SceneObject]) -> Vec<ScenePatch> {
+    let old_parents = parents(from);
+    let new_parents = parents(to);
+    let old: HashMap<u32, &SceneObject> = flatten(from).into_iter().map(|o| (o.id, o)).collect();
+
+    let mut deletes = vec![];
+    let mut adds = vec![];
+    let mut updates = vec![];
+    // the topmost node that's gone, so its subtree goes with it
+    for o in flatten(from) {
+        let parent_kept = old_parents[&o.id].is_none_or(|p| new_parents.contains_key(&p));
+        if !new_parents.contains_key(&o.id) && parent_kept {
+            deletes.push(ScenePatch::DeleteNode { id: o.id });
+        }
+    }
+    fn walk(
+        v: &[SceneObject],
+        parent_id: Option<u32>,
+        old: &HashMap<u32, &SceneObject>,
+        old_parents: &HashMap<u32, Option<u32>>,
+        deletes: &mut Vec<ScenePatch>,
+        adds: &mut Vec<ScenePatch>,
+        updates: &mut Vec<ScenePatch>,
+    ) {
+        for o in v {
+            match old.get(&o.id) {
+                Some(before) if old_parents[&o.id] == parent_id => {
+                    updates.extend(
+                        UpdatedField::between(before, o)
+                            .into_iter()
+                            .map(|field| ScenePatch::UpdateField { id: o.id, field }),
+                    );
+                    walk(
+                        &o.children,
+                        Some(o.id),
+                        old,
+                        old_parents,
+                        deletes,
+                        adds,
+                        updates,
+                    );
+                }
+                // new or moved: whatever of the subtree exists elsewhere is
+                // cleared out first, so the copy below doesn't duplicate it
+                _ => {
+                    for c in flatten(std::slice::from_ref(o)) {
+                        if old.contains_key(&c.id) {
+                            deletes.push(ScenePatch::DeleteNode { id: c.id });
+                        }
+                    }
+                    adds.push(ScenePatch::AddNode {
+                        parent_id,
+                        node: Box::new(o.clone()),
+                    });
+                }
+            }
+        }
+    }
+    walk(
+        to,
+        None,
+        &old,
+        &old_parents,
+        &mut deletes,
+        &mut adds,
+        &mut updates,
+    );
+    deletes.extend(adds);
+    deletes.extend(updates);
+    deletes
+}
+
+/// Applies `patches` in order. A patch naming a node that isn't there (one
+/// already deleted with an ancestor, say) is skipped, and an `AddNode` whose
+/// parent is missing adds a root. `next_id` is raised past any added ID.
+pub fn apply_diff(objects: &mut Vec<SceneObject>, patches: Vec<ScenePatch>, next_id: &mut u32) {
+    for patch in patches {
+        match patch {
+            ScenePatch::AddNode { parent_id, node } => {
+                for o in flatten(std::slice::from_ref(&node)) {
+                    *next_id = (*next_id).max(o.id);
+                }
+                match parent_id.and_then(|p| find_object_by_id_mut(objects, p)) {
+                    Some(parent) => parent.children.push(*node),
+                    None => objects.push(*node),
+                }
+            }
+            ScenePatch::DeleteNode { id } => {
+                detach_node(objects, id);
+            }
+            ScenePatch::UpdateField { id, field } => {
+                if let Some(o) = find_object_by_id_mut(objects, id) {
+                    field.apply(o);
+                }
+            }
+        }
+    }
+}
+
+/// Each node's parent, `None` for roots.
+fn parents(v: &[SceneObject]) -> HashMap<u32, Option<u32>> {
+    let mut out: HashMap<u32, Option<u32>> = v.iter().map(|o| (o.id, None)).collect();
+    for o in flatten(v) {
+        out.extend(o.children.iter().map(|c| (c.id, Some(o.id))));
+    }
+    out
+}
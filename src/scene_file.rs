@@ -0,0 +1,32 @@
+//! Scenes on disk: pretty-printed JSON holding the node forest, its extra links
+//! and its node types.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::sketch::{NodeTypeSpec, SceneObject};
+use crate::storage;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SceneFile {
+    pub objects: Vec<SceneObject>,
+    /// Source → target pairs outside the parent-child tree.
+    #[serde(default)]
+    pub links: Vec<(u32, u32)>,
+    /// What `SceneObject::node_type` names refer to.
+    #[serde(default)]
+    pub node_types: HashMap<String, NodeTypeSpec>,
+}
+
+pub fn load(path: &Path) -> Result<SceneFile, AppError> {
+    let text = storage::read(path)?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+pub fn save(path: &Path, scene: &SceneFile) -> Result<(), AppError> {
+    storage::write(path, &serde_json::to_string_pretty(scene)?)?;
+    Ok(())
+}
@@ -0,0 +1,69 @@
+//! Notices when a loaded scene file is edited by another program. The web build
+//! has no filesystem to watch, so there `SceneWatcher` never reports a change.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::storage;
+
+pub struct SceneWatcher {
+    path: PathBuf,
+    /// File contents as of the last load or save, so our own writes don't count.
+    last_text: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    events: std::sync::mpsc::Receiver<notify::Result<notify::Event>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl SceneWatcher {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(path: &Path) -> Result<Self, AppError> {
+        use notify::Watcher;
+
+        let (tx, events) = std::sync::mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        // editors often save by replacing the file, so watch the folder it lives in
+        let dir = match path.parent() {
+            Some(d) if !d.as_os_str().is_empty() => d,
+            _ => Path::new("."),
+        };
+        watcher.watch(dir, notify::RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            path: path.to_owned(),
+            last_text: storage::read(path).ok(),
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn new(path: &Path) -> Result<Self, AppError> {
+        Ok(Self {
+            path: path.to_owned(),
+            last_text: storage::read(path).ok(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// True once per outside edit that left the file with new contents.
+    pub fn poll(&mut self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.events.try_iter().count() > 0 {
+            let text = storage::read(&self.path).ok();
+            if text.is_some() && text != self.last_text {
+                self.last_text = text;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Takes the file's current contents as known, e.g. right after saving to it.
+    pub fn sync(&mut self) {
+        self.last_text = storage::read(&self.path).ok();
+    }
+}
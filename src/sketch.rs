@@ -1,59 +1,1167 @@
-use std::collections::HashMap;
-use std::time::{Duration, Instant};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use eframe::egui;
 use eframe::egui::{Color32, FontId, Pos2, Rect, Shape, Slider, Stroke, output::OutputCommand};
-use egui_plot::{HLine, Line, LineStyle, MarkerShape, Plot, PlotPoint, Points, Text, VLine};
+use egui_extras::{Column, TableBuilder};
+use egui_plot::{HLine, Line, LineStyle, MarkerShape, Plot, PlotPoint, Points, Text};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
 
-pub const FRAMES_PER_SECOND: u32 = 60;
+use crate::config::{CONFIG_PATH, Config};
+use crate::error::AppError;
+use crate::scene_diff::{SceneDiff, diff_scenes};
+use crate::scene_file::{self, SceneFile};
+use crate::scene_watch::SceneWatcher;
+use crate::sound::SoundPlayer;
+use crate::storage;
+use crate::templates::{TEMPLATES, build_blank_scene};
+use crate::tween::{EasingKind, Tween, TweenProperty};
+use crate::validate::{ValidationError, validate_scene};
+use crate::wgpu_compute::RotationCompute;
+use crate::{csv_export, dot_export, dot_import, expr, svg_export, vm, wgpu_compute, wgpu_glow};
+
+const DEFAULT_FPS: u32 = 60;
 const MAX_SAMPLES: usize = 400;
 const SAMPLE_DT: Duration = Duration::from_millis(100);
 const LO: f64 = 90.0;
 const HI: f64 = 110.0;
+/// Default on-screen size of a node shape, in pixels at zoom 1.
+pub(crate) const NODE_SIZE: f32 = 40.0;
+/// Pulses per second of the active node's outline.
+const PULSE_HZ: f32 = 1.5;
+/// Seconds a node must be hovered before its info tooltip pops up.
+const TOOLTIP_DELAY: f32 = 0.5;
+/// Screen distance from a node's center that still counts as hovering it.
+const HOVER_RADIUS: f32 = 20.0;
+/// Seconds a toast message stays up.
+const TOAST_SECS: f64 = 2.0;
+/// Seconds the status bar highlights what an undo or redo just did.
+const STATUS_FLASH_SECS: f32 = 1.0;
+/// Frames between two recorded keyframes, and how many a node keeps: 100 s
+/// of samples at 60 fps.
+const RECORD_EVERY: u64 = 10;
+const MAX_KEYFRAMES: usize = 600;
+/// Copied subtrees kept for Ctrl+Shift+V.
+const CLIPBOARD_HISTORY: usize = 10;
+/// Trips per second a flow dot makes along an edge of the selected node.
+const FLOW_SPEED: f32 = 0.5;
+/// Seconds a freshly added child's connector takes to grow to full length.
+const EDGE_SPAWN_SECS: f32 = 0.4;
+/// Seconds a deleted node takes to shrink away before it leaves the scene.
+const DELETE_SECS: f32 = 0.3;
+/// Phase offsets of the dots travelling along each edge at once.
+const FLOW_DOTS: [f32; 3] = [0.0, 0.33, 0.66];
+/// How quickly a collapsing subtree folds away, per second.
+const COLLAPSE_EASE: f32 = 10.0;
+/// Friction on physics-mode nodes so they settle instead of orbiting forever.
+const SPRING_DAMPING: f32 = 4.0;
+/// Force layout: the distance nodes settle at, in world units.
+const FD_SPACING: f32 = 150.0;
+/// Force layout: farthest a node moves in one frame at full heat.
+const FD_MAX_STEP: f32 = 40.0;
+/// Force layout: how quickly it cools, per second; see `AppState::fd_cooling`.
+const FD_COOLING_RATE: f32 = 0.5;
+/// Timing samples kept per node, and how many the sparkline shows.
+const MAX_TIMING_SAMPLES: usize = 60;
+const SPARKLINE_BARS: usize = 10;
+/// One frame at 60 FPS; slower samples draw red.
+const FRAME_BUDGET_MS: f32 = 16.0;
+/// 3D preview: shrink per unit of depth, where depth is `(x + z_offset) * 0.01`.
+const PERSPECTIVE: f32 = 0.1;
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 2.0;
+/// Zoom a double-clicked node is brought to.
+const FOCUS_ZOOM: f32 = 1.5;
+/// How quickly a smooth camera move closes the gap, per second.
+const CAMERA_EASE: f32 = 8.0;
+/// Share of its speed a released pan keeps each frame.
+const PAN_FRICTION: f32 = 0.9;
+/// Scene snapshots kept for Edit → Undo.
+const UNDO_LIMIT: usize = 100;
+/// Entries kept in File → Recent Scenes.
+const MAX_RECENT_SCENES: usize = 10;
+/// World-space distance between grid lines.
+const GRID_SPACING: f32 = 50.0;
+/// Screen size of the overview in the canvas corner, and how many nodes it draws.
+const MINIMAP_SIZE: egui::Vec2 = egui::Vec2::new(180.0, 120.0);
+const MINIMAP_MAX_NODES: usize = 500;
+/// Side of the square grip on an orthogonal connector's vertical segment.
+const ELBOW_HANDLE: f32 = 8.0;
+const LINK_COLOR: Color32 = Color32::from_rgb(170, 80, 255);
+const STEP_CHILD_COLOR: Color32 = Color32::from_rgb(255, 150, 0);
+
+/// Shown in the F1 help window.
+const SHORTCUTS: &[(&str, &str)] = &[
+    ("Click", "Select node"),
+    ("Shift+Click", "Toggle node in selection"),
+    ("Ctrl+A", "Select all nodes"),
+    ("Ctrl+D", "Deselect all"),
+    ("Escape", "Clear selection and close inspector"),
+    ("F2", "Rename selected node"),
+    ("Space", "Collapse or expand the selected node"),
+    ("L", "Toggle lasso selection"),
+    ("Ctrl+Shift+R", "Reset all rotations"),
+    ("Ctrl+Z", "Undo"),
+    ("Ctrl+Y / Ctrl+Shift+Z", "Redo"),
+    ("Ctrl+C", "Copy selected subtree"),
+    ("Ctrl+V", "Paste subtree under selected node"),
+    ("Ctrl+Shift+V", "Pick a recent copy to paste at the cursor"),
+    ("Ctrl+H", "Find and replace in node names"),
+    ("Drag node", "Move node (snaps to the grid when shown)"),
+    ("Right drag", "Pan"),
+    ("Scroll", "Zoom"),
+    ("Double-click node", "Zoom in on node"),
+    ("Double-click empty space", "Fit scene to view"),
+    ("C", "Center on selected node (or the scene), keeping zoom"),
+    ("F1", "Toggle this window"),
+];
+
+/// Inspector sections, indexed by `AppState::inspector_tab`.
+const INSPECTOR_TABS: [&str; 4] = ["Properties", "Animation", "Appearance", "Links"];
+/// Icons offered by the inspector's picker.
+const ICONS: [&str; 30] = [
+    "🔥", "📦", "⚙", "⭐", "❤", "⚡", "🔒", "🔑", "📁", "📄", "🔔", "🔧", "💡", "🎯", "🚀", "⚠",
+    "✔", "❌", "❓", "💬", "📌", "🏠", "🌐", "🐛", "🎵", "📷", "⏰", "☁", "💾", "👤",
+];
 
 /* ---------------- data types ---------------- */
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ShapeKind {
     Square,
     Circle,
     Triangle,
+    /// Drawn with another node's shape and color; see `resolve_appearance`.
+    Reference {
+        target_id: u32,
+    },
 }
 
-#[derive(Debug, Clone)]
+/// Style of the connector drawn from a node up to its parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum EdgeStyle {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+/// A small marker drawn on a node's corner; a node carries up to `MAX_BADGES`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BadgeKind {
+    Warning,
+    Error,
+    Success,
+    Info,
+    Lock,
+    Star,
+}
+
+const MAX_BADGES: usize = 3;
+
+impl BadgeKind {
+    pub const ALL: [Self; 6] = [
+        Self::Warning,
+        Self::Error,
+        Self::Success,
+        Self::Info,
+        Self::Lock,
+        Self::Star,
+    ];
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Warning => "⚠",
+            Self::Error => "✗",
+            Self::Success => "✓",
+            Self::Info => "ℹ",
+            Self::Lock => "🔒",
+            Self::Star => "★",
+        }
+    }
+
+    fn color(self) -> Color32 {
+        match self {
+            Self::Warning => Color32::from_rgb(230, 160, 0),
+            Self::Error => Color32::from_rgb(210, 40, 40),
+            Self::Success => Color32::from_rgb(40, 170, 70),
+            Self::Info => Color32::from_rgb(40, 120, 220),
+            Self::Lock => Color32::from_gray(110),
+            Self::Star => Color32::from_rgb(200, 160, 20),
+        }
+    }
+}
+
+/// What a `NodeRule` checks for. A node matching it gets a warning badge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuleCondition {
+    NameLongerThan(usize),
+    ChildCountGreaterThan(usize),
+    DepthGreaterThan(u32),
+    HasNoTags,
+    RotationSpeedAbove(f32),
+}
+
+impl RuleCondition {
+    /// One of each kind with a starting limit, for the Rules panel's picker.
+    pub const ALL: [Self; 5] = [
+        Self::NameLongerThan(32),
+        Self::ChildCountGreaterThan(8),
+        Self::DepthGreaterThan(5),
+        Self::HasNoTags,
+        Self::RotationSpeedAbove(180.0),
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::NameLongerThan(_) => "Name longer than",
+            Self::ChildCountGreaterThan(_) => "Children more than",
+            Self::DepthGreaterThan(_) => "Depth greater than",
+            Self::HasNoTags => "Has no tags",
+            Self::RotationSpeedAbove(_) => "Speed above",
+        }
+    }
+
+    /// Whether `o`, sitting `depth` levels below a root, breaks the rule.
+    /// Speed is compared by magnitude, so it catches both directions.
+    pub fn violated_by(self, o: &SceneObject, depth: u32) -> bool {
+        match self {
+            Self::NameLongerThan(n) => o.text.chars().count() > n,
+            Self::ChildCountGreaterThan(n) => o.children.len() > n,
+            Self::DepthGreaterThan(n) => depth > n,
+            Self::HasNoTags => o.tags.is_empty(),
+            Self::RotationSpeedAbove(v) => o.rotation_speed.abs() > v,
+        }
+    }
+}
+
+/// A check run against every node each frame (View > Rules).
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeRule {
+    pub condition: RuleCondition,
+    pub message: String,
+}
+
+/// Something a click on a node does to each node it links to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NodeEvent {
+    SetColor(Color32),
+    ToggleDisabled,
+    SetRotationSpeed(f32),
+}
+
+impl NodeEvent {
+    pub fn apply(self, o: &mut SceneObject) {
+        match self {
+            Self::SetColor(c) => o.color = c,
+            Self::ToggleDisabled => o.disabled = !o.disabled,
+            Self::SetRotationSpeed(v) => o.rotation_speed = v,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutMode {
+    /// Left-to-right tidy tree (`layout_recursive`).
+    Tree,
+    /// Nested rectangles filling the canvas, area proportional to subtree size.
+    Treemap,
+    /// Children fanned out on arcs around their parent (`layout_circular`).
+    Circular,
+    /// Nodes pushed apart and pulled together by edges until they settle
+    /// (`step_force_layout`).
+    ForceDirected,
+    /// One row per depth, each spread evenly across the canvas regardless of
+    /// who the parents are (`layout_folded_rows`), for comparing levels.
+    FoldedRows,
+}
+
+/// What a click on the canvas does besides selecting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkMode {
+    Off,
+    /// First click picks the source, second click the target.
+    Add,
+    /// Click near a link to delete it.
+    Remove,
+}
+
+/// How the canvas is shared between the main camera and a second one
+/// (View > Split View).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitMode {
+    Single,
+    /// Two views side by side.
+    Horizontal,
+    /// Two views stacked, the main one on top.
+    Vertical,
+}
+
+impl SplitMode {
+    pub const ALL: [Self; 3] = [Self::Single, Self::Horizontal, Self::Vertical];
+
+    /// The main view's rect, and the second view's if the canvas is split.
+    fn split(self, rect: Rect) -> (Rect, Option<Rect>) {
+        let c = rect.center();
+        match self {
+            Self::Single => (rect, None),
+            Self::Horizontal => (
+                Rect::from_x_y_ranges(rect.left()..=c.x, rect.y_range()),
+                Some(Rect::from_x_y_ranges(c.x..=rect.right(), rect.y_range())),
+            ),
+            Self::Vertical => (
+                Rect::from_x_y_ranges(rect.x_range(), rect.top()..=c.y),
+                Some(Rect::from_x_y_ranges(rect.x_range(), c.y..=rect.bottom())),
+            ),
+        }
+    }
+}
+
+/// Canned settings for a node's rotation and pulse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationPreset {
+    /// Slow rotation.
+    Idle,
+    /// Fast spin with a quick scale pulse.
+    Alert,
+    /// Rotation that swings back and forth.
+    Wave,
+    /// Constant fast rotation.
+    Spin,
+}
+
+impl AnimationPreset {
+    pub const ALL: [Self; 4] = [Self::Idle, Self::Alert, Self::Wave, Self::Spin];
+}
+
+/// A node property driven by an `AnimationTrack`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnimatableProperty {
+    /// `current_rotation` in degrees.
+    Rotation,
+    Scale,
+    Opacity,
+    /// Hue, saturation or value of the fill, each 0–1.
+    ColorH,
+    ColorS,
+    ColorV,
+}
+
+impl AnimatableProperty {
+    pub const ALL: [Self; 6] = [
+        Self::Rotation,
+        Self::Scale,
+        Self::Opacity,
+        Self::ColorH,
+        Self::ColorS,
+        Self::ColorV,
+    ];
+
+    /// Sets the property to `value` for this frame. Everything but the
+    /// rotation goes into `o.animated`, leaving the node's own values alone.
+    pub fn apply(self, o: &mut SceneObject, value: f32) {
+        let mut hsva = egui::ecolor::Hsva::from(o.shown_color());
+        match self {
+            Self::Rotation => o.current_rotation = value,
+            Self::Scale => o.animated.scale = Some(value.max(0.0)),
+            Self::Opacity => o.animated.opacity = Some(value.clamp(0.0, 1.0)),
+            Self::ColorH => hsva.h = value.rem_euclid(1.0),
+            Self::ColorS => hsva.s = value.clamp(0.0, 1.0),
+            Self::ColorV => hsva.v = value.clamp(0.0, 1.0),
+        }
+        if matches!(self, Self::ColorH | Self::ColorS | Self::ColorV) {
+            o.animated.color = Some(hsva.into());
+        }
+    }
+}
+
+/// What a track does past its last keyframe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LoopMode {
+    /// Holds the last value.
+    #[default]
+    Once,
+    /// Starts over from the first keyframe.
+    Loop,
+    /// Plays backwards to the first keyframe, then forwards again.
+    PingPong,
+}
+
+impl LoopMode {
+    pub const ALL: [Self; 3] = [Self::Once, Self::Loop, Self::PingPong];
+}
+
+/// What a node's tracks make of its scale, opacity and fill this frame; `None`
+/// where no track drives them, so the node's own value shows.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AnimatedValues {
+    pub scale: Option<f32>,
+    pub opacity: Option<f32>,
+    pub color: Option<Color32>,
+}
+
+/// Keyframed values for one property, played against the scene clock.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationTrack {
+    pub property: AnimatableProperty,
+    /// `(seconds, value)`, sorted by time.
+    pub keyframes: Vec<(f32, f32)>,
+    pub loop_mode: LoopMode,
+}
+
+impl AnimationTrack {
+    /// The track's value at scene time `t`; `None` without keyframes.
+    pub fn value_at(&self, t: f32) -> Option<f32> {
+        let first = self.keyframes.first()?.0;
+        let span = self.keyframes.last()?.0 - first;
+        let t = if span <= 0.0 {
+            first
+        } else {
+            match self.loop_mode {
+                LoopMode::Once => t,
+                LoopMode::Loop => first + (t - first).rem_euclid(span),
+                LoopMode::PingPong => {
+                    let p = (t - first).rem_euclid(2.0 * span);
+                    first + if p > span { 2.0 * span - p } else { p }
+                }
+            }
+        };
+        sample_keyframes(&self.keyframes, t)
+    }
+}
+
+/// Sortable columns of the outline table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutlineColumn {
+    Id,
+    Name,
+    Shape,
+    Layer,
+    Depth,
+    Children,
+}
+
+impl OutlineColumn {
+    const ALL: [(Self, &str); 6] = [
+        (Self::Id, "ID"),
+        (Self::Name, "Name"),
+        (Self::Shape, "Shape"),
+        (Self::Layer, "Layer"),
+        (Self::Depth, "Depth"),
+        (Self::Children, "Children"),
+    ];
+}
+
+/// One node as listed in the outline table.
+struct OutlineRow {
+    id: u32,
+    name: String,
+    shape: ShapeKind,
+    layer: u8,
+    depth: u32,
+    children: usize,
+    /// Parent and index among its siblings; `None` for roots.
+    slot: Option<(u32, usize)>,
+}
+
+/// Where on an outline row a dragged node is let go: the top and bottom
+/// quarters place it beside the row's node, the middle puts it under it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutlineDrop {
+    Before,
+    Onto,
+    After,
+}
+
+impl OutlineDrop {
+    fn at(row: Rect, y: f32) -> Self {
+        let t = (y - row.top()) / row.height();
+        if t < 0.25 {
+            Self::Before
+        } else if t > 0.75 {
+            Self::After
+        } else {
+            Self::Onto
+        }
+    }
+}
+
+fn sort_outline(rows: &mut [OutlineRow], column: OutlineColumn, ascending: bool) {
+    match column {
+        OutlineColumn::Id => rows.sort_by_key(|r| r.id),
+        OutlineColumn::Name => rows.sort_by(|a, b| a.name.cmp(&b.name)),
+        OutlineColumn::Shape => rows.sort_by_key(|r| format!("{:?}", r.shape)),
+        OutlineColumn::Layer => rows.sort_by_key(|r| r.layer),
+        OutlineColumn::Depth => rows.sort_by_key(|r| r.depth),
+        OutlineColumn::Children => rows.sort_by_key(|r| r.children),
+    }
+    if !ascending {
+        rows.reverse();
+    }
+}
+
+/// A named kind of node (Tools → Node Types): the look a node takes when it's
+/// given the type, and which types its children may have.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeTypeSpec {
+    pub allowed_child_types: Vec<String>,
+    pub shape: ShapeKind,
+    pub default_color: Color32,
+}
+
+/// Everything the Add Child wizard asks for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewNodeConfig {
+    pub name: String,
+    pub shape: ShapeKind,
+    pub color: Color32,
+    pub rotation_speed: f32,
+    pub tags: Vec<String>,
+}
+
+impl Default for NewNodeConfig {
+    fn default() -> Self {
+        Self {
+            name: "New Node".to_owned(),
+            shape: ShapeKind::Square,
+            color: Color32::WHITE,
+            rotation_speed: 20.0,
+            tags: vec![],
+        }
+    }
+}
+
+/// Color-blindness simulation applied to node colors at draw time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessibilityMode {
+    None,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+impl AccessibilityMode {
+    pub const ALL: [Self; 4] = [
+        Self::None,
+        Self::Deuteranopia,
+        Self::Protanopia,
+        Self::Tritanopia,
+    ];
+
+    /// Rows map linear (r, g, b) to the simulated (r, g, b).
+    fn matrix(self) -> Option<[[f32; 3]; 3]> {
+        match self {
+            Self::None => None,
+            Self::Deuteranopia => Some([[0.625, 0.375, 0.0], [0.7, 0.3, 0.0], [0.0, 0.3, 0.7]]),
+            Self::Protanopia => Some([
+                [0.567, 0.433, 0.0],
+                [0.558, 0.442, 0.0],
+                [0.0, 0.242, 0.758],
+            ]),
+            Self::Tritanopia => Some([[0.95, 0.05, 0.0], [0.0, 0.433, 0.567], [0.0, 0.475, 0.525]]),
+        }
+    }
+
+    pub fn simulate(self, c: Color32) -> Color32 {
+        let Some(m) = self.matrix() else {
+            return c;
+        };
+        let rgba = egui::Rgba::from(c);
+        let [r, g, b] = [rgba.r(), rgba.g(), rgba.b()];
+        let row = |i: usize| m[i][0] * r + m[i][1] * g + m[i][2] * b;
+        egui::Rgba::from_rgba_premultiplied(row(0), row(1), row(2), rgba.a()).into()
+    }
+}
+
+/// How connectors between a parent and its children are routed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectorStyle {
+    Diagonal,
+    Orthogonal,
+    Bezier,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FillMode {
+    #[default]
+    Solid,
+    /// Top-to-bottom blend from the node's `color` to `to`.
+    LinearGradient { to: Color32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    Dark,
+    Light,
+    Custom,
+}
+
+/// User-picked colors for `Theme::Custom`, layered over the dark visuals.
+#[derive(Debug, Clone, Copy)]
+pub struct CustomTheme {
+    pub background: Color32,
+    pub panel: Color32,
+    pub text: Color32,
+}
+
+impl Default for CustomTheme {
+    fn default() -> Self {
+        Self {
+            background: Color32::from_rgb(20, 24, 36),
+            panel: Color32::from_rgb(36, 40, 56),
+            text: Color32::from_rgb(230, 230, 210),
+        }
+    }
+}
+
+impl CustomTheme {
+    fn visuals(&self) -> egui::Visuals {
+        let mut v = egui::Visuals::dark();
+        v.panel_fill = self.panel;
+        v.window_fill = self.panel;
+        v.extreme_bg_color = self.background;
+        v.override_text_color = Some(self.text);
+        v
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SceneObject {
     pub id: u32,
     pub text: String,
     pub text_buffer: String,
+    /// Human-readable unique identifier, `[a-z0-9_]` only. Generated from the
+    /// name at creation; see `slugify`.
+    #[serde(default)]
+    pub slug: String,
+    #[serde(default)]
+    pub comment: String,
+    /// Notes with `**bold**`, `_italic_` and `` `code` `` markup; see `memo_layout`.
+    #[serde(default)]
+    pub memo: String,
     pub shape: ShapeKind,
     pub color: Color32,
+    #[serde(default)]
+    pub fill_mode: FillMode,
+    #[serde(default)]
+    pub edge_style: EdgeStyle,
     pub rotation_speed: f32,
+    /// Speed as a function of `t` (see `expr`); empty means use `rotation_speed`.
+    #[serde(default)]
+    pub speed_expr: String,
+    /// `speed_expr` as last parsed, by `parsed_speed`; cleared whenever it's edited.
+    #[serde(skip)]
+    pub speed_parsed: Option<Result<expr::Expr, expr::ExprError>>,
+    /// Size oscillation as (amplitude, frequency in Hz).
+    #[serde(default)]
+    pub scale_anim: Option<(f32, f32)>,
+    /// Keyframed tracks, applied after the rotation speed. They set
+    /// `current_rotation` directly and everything else through `animated`.
+    #[serde(default)]
+    pub animations: Vec<AnimationTrack>,
+    /// Worked out from `animations` every frame, never saved.
+    #[serde(skip)]
+    pub animated: AnimatedValues,
     pub current_rotation: f32,
+    #[serde(default)]
+    pub pinned_to_screen: bool,
+    #[serde(default)]
+    pub screen_pos: Pos2,
+    /// Name of the prefab this subtree was inserted from, if any.
+    pub is_prefab_instance: Option<String>,
+    /// 0 = none, 1 = low, 2 = medium, 3 = high.
+    #[serde(default)]
+    pub priority: u8,
+    /// Emoji drawn inside the shape.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Corner markers: top-left, top-right, then bottom-left. Only the first
+    /// `MAX_BADGES` are drawn. Separate from the `priority` dot, which sits on the top edge.
+    #[serde(default)]
+    pub badges: Vec<BadgeKind>,
+    /// World position set by dragging; replaces the node's tree layout slot.
+    #[serde(default)]
+    pub position_override: Option<egui::Vec2>,
+    /// Weight of the edge up to the parent; drawn as line thickness.
+    #[serde(default = "default_one")]
+    pub edge_weight: f32,
+    /// 0–7; hidden along with its subtree when the layer is toggled off.
+    #[serde(default)]
+    pub layer: u8,
+    /// Children are folded away.
+    #[serde(default)]
+    pub collapsed: bool,
+    /// Levels of descendants shown below this node; `None` shows them all and
+    /// `Some(0)` hides the children like `collapsed`.
+    #[serde(default)]
+    pub expand_depth: Option<u32>,
+    /// Can't be clicked, dragged, selected in bulk or edited until unlocked.
+    #[serde(default)]
+    pub locked: bool,
+    /// Extra depth for the 3D preview, in world units.
+    #[serde(default)]
+    pub z_offset: f32,
+    /// Size multiplier; frame scripts can animate it.
+    #[serde(default = "default_one")]
+    pub scale: f32,
+    /// 0..1, multiplied into the node's alpha when drawn.
+    #[serde(default = "default_one")]
+    pub opacity: f32,
+    /// Rounding of a square's corners, 0–20 points.
+    #[serde(default)]
+    pub corner_radius: f32,
+    /// Shape the node is turning into. `morph_progress` runs 0..1 and the node
+    /// takes the new shape when it gets there.
+    #[serde(default)]
+    pub morph_target: Option<ShapeKind>,
+    #[serde(default)]
+    pub morph_progress: f32,
+    /// 0..1 progress; below 1 only that share of the shape is filled in.
+    #[serde(default = "default_one")]
+    pub fill_ratio: f32,
+    /// Label color; `None` follows the theme's text color.
+    #[serde(default)]
+    pub text_color: Option<Color32>,
+    /// Label font size in points, 8–32.
+    #[serde(default = "default_text_size")]
+    pub text_size: f32,
+    /// Per-frame script, see `vm`.
+    #[serde(default)]
+    pub script: String,
+    /// `script` as last compiled by the Run button (or on first use).
+    #[serde(skip)]
+    pub program: Option<vm::Program>,
+    /// URL or file path opened from the inspector; `scene://<id>` jumps to a node.
+    #[serde(default)]
+    pub link: String,
+    /// IDs of nodes shown as also hanging off this one: a dashed orange
+    /// connector runs to where they sit as real children somewhere else.
+    /// They're only for show: `animate` and every other walk go by `children`.
+    #[serde(default)]
+    pub step_children: Vec<u32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Disabled nodes stop animating and are drawn faded.
+    #[serde(default)]
+    pub disabled: bool,
+    /// Applied to every link target when this node is clicked.
+    #[serde(default)]
+    pub on_click_events: Vec<NodeEvent>,
+    /// Free-form key/value metadata.
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+    /// Recent timings in ms fed through `AppState::record_timing`; not saved.
+    #[serde(skip)]
+    pub timing_samples: VecDeque<f32>,
+    /// Name in `AppState::node_types`. A node of a known type only takes
+    /// children whose type it allows; untyped nodes and unknown types take any.
+    #[serde(default)]
+    pub node_type: Option<String>,
+    /// `(time, current_rotation)` samples taken while recording, oldest first.
+    #[serde(default)]
+    pub keyframes: Vec<(f32, f32)>,
+    /// PNG or JPEG drawn inside a square or circle, over its fill.
+    #[serde(default)]
+    pub texture_path: Option<PathBuf>,
+    /// `texture_path` once decoded; the handle itself lives in
+    /// `AppState::textures`. Not saved, and filled back in from the path.
+    #[serde(skip)]
+    pub texture: Option<egui::TextureId>,
     pub children: Vec<SceneObject>,
 }
 
+/// Equality means "same node", not "same content": two objects are equal when
+/// their IDs match, whatever their other fields say. IDs are unique within a
+/// scene, so this is what sets of nodes and selections want. Compare fields
+/// directly (or use `scene_diff`) to find out whether a node was edited.
+impl PartialEq for SceneObject {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for SceneObject {}
+
+/// Hashes the ID only, to agree with `PartialEq`.
+impl Hash for SceneObject {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+/// Snake-cased `name` with the ID appended so it's unique from the start,
+/// e.g. "Render Node" with ID 3 becomes `render_node_3`.
+pub fn slugify(name: &str, id: u32) -> String {
+    let mut slug = String::new();
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    if slug.is_empty() {
+        slug.push_str("node_");
+    } else if !slug.ends_with('_') {
+        slug.push('_');
+    }
+    format!("{slug}{id}")
+}
+
+/// Checks a user-entered slug; `taken` holds every other node's slug.
+fn validate_slug(slug: &str, taken: &HashSet<&str>) -> Result<(), String> {
+    if slug.is_empty() {
+        Err("ID can't be empty".to_owned())
+    } else if !slug
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        Err("Only a-z, 0-9 and _ are allowed".to_owned())
+    } else if taken.contains(slug) {
+        Err(format!("\"{slug}\" is already used"))
+    } else {
+        Ok(())
+    }
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+fn default_text_size() -> f32 {
+    16.0
+}
+
 impl SceneObject {
-    fn new(id: u32, name: &str, shape: ShapeKind, color: Color32) -> Self {
+    pub fn new(id: u32, name: &str, shape: ShapeKind, color: Color32) -> Self {
         Self {
             id,
             text: name.into(),
             text_buffer: name.into(),
+            slug: slugify(name, id),
+            comment: String::new(),
+            memo: String::new(),
             shape,
             color,
+            fill_mode: FillMode::Solid,
+            edge_style: EdgeStyle::Solid,
             rotation_speed: 20.0,
+            speed_expr: String::new(),
+            speed_parsed: None,
+            scale_anim: None,
+            animations: vec![],
+            animated: AnimatedValues::default(),
             current_rotation: 0.0,
+            pinned_to_screen: false,
+            screen_pos: Pos2::new(100.0, 100.0),
+            is_prefab_instance: None,
+            priority: 0,
+            icon: None,
+            badges: vec![],
+            position_override: None,
+            edge_weight: 1.0,
+            layer: 0,
+            collapsed: false,
+            expand_depth: None,
+            locked: false,
+            z_offset: 0.0,
+            scale: 1.0,
+            opacity: 1.0,
+            corner_radius: 0.0,
+            morph_target: None,
+            morph_progress: 0.0,
+            fill_ratio: 1.0,
+            text_color: None,
+            text_size: default_text_size(),
+            script: String::new(),
+            program: None,
+            link: String::new(),
+            step_children: vec![],
+            tags: vec![],
+            disabled: false,
+            on_click_events: vec![],
+            properties: BTreeMap::new(),
+            timing_samples: VecDeque::new(),
+            node_type: None,
+            keyframes: vec![],
+            texture_path: None,
+            texture: None,
             children: vec![],
         }
     }
+
+    /// Overwrites the animation fields with the preset's values.
+    pub fn apply_preset(&mut self, preset: AnimationPreset) {
+        self.clear_animation();
+        match preset {
+            AnimationPreset::Idle => self.rotation_speed = 10.0,
+            AnimationPreset::Alert => {
+                self.rotation_speed = 360.0;
+                self.scale_anim = Some((0.3, 3.0));
+            }
+            AnimationPreset::Wave => self.speed_expr = "sin(t * 2) * 90".into(),
+            AnimationPreset::Spin => self.rotation_speed = 180.0,
+        }
+    }
+
+    /// `scale` as drawn this frame, with any Scale track in place of it.
+    pub fn shown_scale(&self) -> f32 {
+        self.animated.scale.unwrap_or(self.scale)
+    }
+
+    pub fn shown_opacity(&self) -> f32 {
+        self.animated.opacity.unwrap_or(self.opacity)
+    }
+
+    pub fn shown_color(&self) -> Color32 {
+        self.animated.color.unwrap_or(self.color)
+    }
+
+    /// `speed_expr` parsed, or why it can't be; `None` while it's empty. Parsed
+    /// on first use after each edit rather than every frame.
+    pub fn parsed_speed(&mut self) -> Option<&Result<expr::Expr, expr::ExprError>> {
+        if self.speed_expr.trim().is_empty() {
+            return None;
+        }
+        Some(
+            self.speed_parsed
+                .get_or_insert_with(|| expr::parse(&self.speed_expr)),
+        )
+    }
+
+    /// Puts every animation field back to what a new node has.
+    pub fn clear_animation(&mut self) {
+        self.rotation_speed = 20.0;
+        self.speed_expr.clear();
+        self.speed_parsed = None;
+        self.scale_anim = None;
+        self.animations.clear();
+    }
+}
+
+/// A labelled box drawn around a set of nodes. Nodes may sit in several groups.
+#[derive(Debug, Clone)]
+pub struct NodeGroup {
+    pub id: u32,
+    pub label: String,
+    pub node_ids: HashSet<u32>,
+    pub color: Color32,
+}
+
+/// A named edge between two groups, drawn as a thick arrow between their boxes
+/// on top of the node graph.
+#[derive(Debug, Clone)]
+pub struct GroupLink {
+    pub from_group: u32,
+    pub to_group: u32,
+    pub label: String,
+    pub color: Color32,
 }
 
 #[derive(Debug, Clone)]
 enum EditorRequest {
-    AddChild { parent_id: u32 },
-    DeleteNode { node_id: u32 },
+    AddChild {
+        parent_id: u32,
+    },
+    DeleteNode {
+        node_id: u32,
+    },
+    /// Appends already re-ID'd subtrees under `parent_id`; `None` adds them as roots.
+    InsertChildren {
+        parent_id: Option<u32>,
+        nodes: Vec<SceneObject>,
+    },
+    /// A re-ID'd subtree from the clipboard, inserted like `InsertChildren`
+    /// but named after the paste in the undo history.
+    Paste {
+        parent_id: Option<u32>,
+        node: Box<SceneObject>,
+    },
+    /// Zeroes `current_rotation` on one node, or on every node when `None`.
+    ResetRotation {
+        node_id: Option<u32>,
+    },
+    /// Flips `collapsed`, hiding or showing the node's children.
+    ToggleCollapsed {
+        node_id: u32,
+    },
+    /// Moves a child to `new_index` among its siblings, clamped to the end.
+    MoveChild {
+        parent_id: u32,
+        child_id: u32,
+        new_index: usize,
+    },
+    /// Moves a node and its subtree; `None` makes it a root.
+    Reparent {
+        node_id: u32,
+        new_parent_id: Option<u32>,
+    },
+    /// Recolors every descendant of `node_id`, or mixes 50/50 with their own
+    /// colors when `blend` is set.
+    PaintSubtree {
+        node_id: u32,
+        color: Color32,
+        blend: bool,
+    },
+    /// Batch edits from the multi-selection inspector; locked nodes are skipped.
+    BatchSetRotationSpeed {
+        ids: Vec<u32>,
+        value: f32,
+    },
+    BatchSetColor {
+        ids: Vec<u32>,
+        color: Color32,
+    },
+    BatchSetShape {
+        ids: Vec<u32>,
+        shape: ShapeKind,
+    },
+    /// A click on `node_id` running its `on_click_events` on each linked
+    /// target; locked targets are skipped.
+    FireClickEvents {
+        node_id: u32,
+        targets: Vec<u32>,
+        events: Vec<NodeEvent>,
+    },
+    /// Folds a node into its parent `keep_id`: children, tags and properties
+    /// move over, then `discard_id` is deleted.
+    MergeNodes {
+        keep_id: u32,
+        discard_id: u32,
+    },
+    /// `AddChild` with the wizard's choices instead of a default white square.
+    AddChildConfigured {
+        parent_id: u32,
+        config: NewNodeConfig,
+    },
+    /// Replaces `find` in every unlocked node's name, either every occurrence
+    /// or only the first one in each name.
+    ReplaceText {
+        find: String,
+        replace: String,
+        case_sensitive: bool,
+        all: bool,
+    },
+}
+
+impl EditorRequest {
+    /// How the edit reads in the undo history, e.g. "Add Child to 'Root'".
+    fn description(&self, objects: &[SceneObject]) -> String {
+        let name = |id| quoted_name(objects, id);
+        let count = |ids: &[u32]| format!("{} nodes", ids.len());
+        match self {
+            Self::AddChild { parent_id } | Self::AddChildConfigured { parent_id, .. } => {
+                format!("Add Child to {}", name(*parent_id))
+            }
+            Self::DeleteNode { node_id } => format!("Delete {}", name(*node_id)),
+            Self::InsertChildren {
+                parent_id: Some(parent_id),
+                ..
+            } => format!("Insert into {}", name(*parent_id)),
+            Self::InsertChildren {
+                parent_id: None, ..
+            } => "Insert Root".into(),
+            Self::Paste {
+                parent_id: Some(parent_id),
+                node,
+            } => format!(
+                "Paste '{}' into {}",
+                node.text.lines().next().unwrap_or_default(),
+                name(*parent_id)
+            ),
+            Self::Paste {
+                parent_id: None,
+                node,
+            } => format!("Paste '{}'", node.text.lines().next().unwrap_or_default()),
+            Self::ResetRotation { node_id: None } => "Reset All Rotations".into(),
+            Self::ResetRotation {
+                node_id: Some(node_id),
+            } => format!("Reset Rotation of {}", name(*node_id)),
+            Self::ToggleCollapsed { node_id } => {
+                let collapsed = find_object_by_id(objects, *node_id).is_some_and(|o| o.collapsed);
+                let verb = if collapsed { "Expand" } else { "Collapse" };
+                format!("{verb} {}", name(*node_id))
+            }
+            Self::MoveChild { child_id, .. } => format!("Reorder {}", name(*child_id)),
+            Self::Reparent {
+                node_id,
+                new_parent_id: Some(parent_id),
+            } => format!("Move {} under {}", name(*node_id), name(*parent_id)),
+            Self::Reparent {
+                node_id,
+                new_parent_id: None,
+            } => format!("Make {} a Root", name(*node_id)),
+            Self::PaintSubtree { node_id, .. } => format!("Paint Subtree of {}", name(*node_id)),
+            Self::BatchSetRotationSpeed { ids, .. } => format!("Set Speed of {}", count(ids)),
+            Self::BatchSetColor { ids, .. } => format!("Set Color of {}", count(ids)),
+            Self::BatchSetShape { ids, .. } => format!("Set Shape of {}", count(ids)),
+            Self::FireClickEvents { node_id, .. } => format!("Click {}", name(*node_id)),
+            Self::MergeNodes {
+                keep_id,
+                discard_id,
+            } => format!("Merge {} into {}", name(*discard_id), name(*keep_id)),
+            Self::ReplaceText { find, replace, .. } => {
+                format!("Replace \"{find}\" with \"{replace}\"")
+            }
+        }
+    }
+}
+
+/// The scene data of the active tab, without any editor state. Used for undo.
+#[derive(Debug, Clone)]
+pub struct SceneSnapshot {
+    objects: Vec<SceneObject>,
+    links: Vec<(u32, u32)>,
+    groups: Vec<NodeGroup>,
+    group_links: Vec<GroupLink>,
+    next_id: u32,
+}
+
+/// A scene parked in the tab bar. The active tab's contents live directly on
+/// `AppState`; this only holds the state of the tabs in the background.
+#[derive(Clone)]
+struct SceneTab {
+    name: String,
+    objects: Vec<SceneObject>,
+    camera_target: egui::Vec2,
+    zoom: f32,
+    next_id: u32,
+    active_settings_id: Option<u32>,
+    links: Vec<(u32, u32)>,
+    groups: Vec<NodeGroup>,
+    group_links: Vec<GroupLink>,
+}
+
+impl SceneTab {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            objects: vec![],
+            camera_target: egui::Vec2::new(400.0, 450.0),
+            zoom: 1.0,
+            next_id: 0,
+            active_settings_id: None,
+            links: vec![],
+            groups: vec![],
+            group_links: vec![],
+        }
+    }
+}
+
+/// Two scene files loaded side by side by the Compare Scenes dialog.
+#[derive(Clone)]
+struct SceneComparison {
+    before: Vec<SceneObject>,
+    after: Vec<SceneObject>,
+    diff: SceneDiff,
 }
 
+#[derive(Clone)]
 struct GraphDemo {
     data: Vec<f64>,
     last: f64,
@@ -76,8 +1184,8 @@ impl GraphDemo {
         self.t_prev = Instant::now();
 
         // random step ±0.5
-        let mut rng = rand::thread_rng();
-        let step: f64 = rng.gen_range(-0.5..=0.5);
+        let mut rng = rand::rng();
+        let step: f64 = rng.random_range(-0.5..=0.5);
         self.last = (self.last + step).clamp(LO, HI);
 
         if self.data.len() == MAX_SAMPLES {
@@ -139,366 +1247,8276 @@ impl GraphDemo {
 
 pub struct AppState {
     time_since_last_update: f32,
+    frame_count: u64,
+    /// Frames per second to repaint at; also the fixed animation step.
+    target_fps: u32,
     scene_objects: Vec<SceneObject>,
     camera_target: egui::Vec2,
     zoom: f32,
+    split_mode: SplitMode,
+    /// The second view's camera. It only pans and zooms; clicks, drags and
+    /// camera moves like Fit all go to the main view.
+    camera_target_b: egui::Vec2,
+    zoom_b: f32,
+    /// Where the second view was drawn last frame, if the canvas is split.
+    viewport_b: Option<Rect>,
+    /// The pan in progress was started over the second view.
+    panning_b: bool,
     active_settings_id: Option<u32>,
+    selected_ids: HashSet<u32>,
     requests: Vec<EditorRequest>,
     next_id: u32,
     dragging: bool,
     last_pointer: Pos2,
+    /// World units per second the camera was last panned at; it keeps gliding
+    /// at this speed after the pan is released, slowing by `PAN_FRICTION`.
+    pan_velocity: egui::Vec2,
     graph: GraphDemo,
+    max_visible_depth: Option<u32>,
+    show_help: bool,
+    show_stats: bool,
+    layout_mode: LayoutMode,
+    renaming_node: Option<u32>,
+    proportional_size: bool,
+    connector_style: ConnectorStyle,
+    accessibility_mode: AccessibilityMode,
+    /// Copies a "spoken:" description of each newly selected node to the
+    /// clipboard for a screen reader to pick up, since egui can't speak.
+    announce_selection: bool,
+    /// The `active_settings_id` last announced.
+    announced_id: Option<u32>,
+    dot_import_open: bool,
+    /// Tools → Export Metrics CSV dialog, and the path it writes to.
+    metrics_export_open: bool,
+    metrics_path: String,
+    /// Edit → Find & Replace dialog and its inputs.
+    find_replace_open: bool,
+    find_text: String,
+    replace_text: String,
+    find_case_sensitive: bool,
+    /// ID typed into the inspector's Add Step-Child field.
+    new_step_child: u32,
+    /// Asking before Reset All Overrides throws away every dragged position.
+    confirm_reset_overrides: bool,
+    /// JSON of the subtrees copied this session, newest first, and where the
+    /// Ctrl+Shift+V picker was opened (in world space) while it's up.
+    clipboard_history: VecDeque<String>,
+    clipboard_picker: Option<egui::Vec2>,
+    /// Node types by name, saved with the scene, and the Node Types editor.
+    node_types: HashMap<String, NodeTypeSpec>,
+    node_types_open: bool,
+    new_type_name: String,
+    /// Decoded node images by path, `None` for ones that failed to load, and
+    /// the inspector's image path field.
+    textures: HashMap<PathBuf, Option<egui::TextureHandle>>,
+    image_path: String,
+    /// Rotations are sampled into each node's `keyframes` (toolbar ⏺).
+    record_mode: bool,
+    /// Seconds into the recording being played back; rotations come from the
+    /// keyframes instead of `animate` meanwhile.
+    playback: Option<f32>,
+    /// The New from Template gallery is open, with each template built once so
+    /// its thumbnail (and the scene a click loads) stays put between frames.
+    template_gallery: Option<Vec<Vec<SceneObject>>>,
+    dot_input: String,
+    dot_error: Option<String>,
+    dot_export: Option<String>,
+    dot_export_path: String,
+    dot_export_status: Option<String>,
+    scenes: Vec<SceneTab>,
+    active_scene: usize,
+    renaming_scene: Option<usize>,
+    closing_scene: Option<usize>,
+    /// Parent the Add Child wizard is open for, with the choices made so far
+    /// and the tag list as typed.
+    wizard: Option<(u32, NewNodeConfig, String)>,
+    last_wizard_config: NewNodeConfig,
+    /// `None` when no audio device could be opened.
+    sound: Option<SoundPlayer>,
+    mute: bool,
+    /// 0..1, wraps once per selection pulse.
+    pulse_phase: f32,
+    /// Seconds the pointer has stayed near each node; only the hovered one is kept.
+    hover_timers: HashMap<u32, f32>,
+    theme: Theme,
+    custom_theme: CustomTheme,
+    theme_editor_open: bool,
+    /// Scales every node's rotation speed. A view setting, not part of the scene.
+    global_speed_multiplier: f32,
+    /// Inspector "Parent ID" text, tagged with the node it was filled in for.
+    reparent_buffer: Option<(u32, String)>,
+    /// Inspector slug text, tagged with the node it was filled in for.
+    slug_buffer: Option<(u32, String)>,
+    slug_error: Option<String>,
+    reparent_error: Option<String>,
+    /// 0..1 progress of the flow dots along each (parent, child) edge of the
+    /// selected nodes.
+    flow_phase: HashMap<(u32, u32), f32>,
+    /// 0..1 growth of the connector to each newly added child, by (parent, child).
+    edge_spawn_progress: HashMap<(u32, u32), f32>,
+    /// Saved subtrees by name; mirrored into the config file on every change.
+    prefabs: HashMap<String, Vec<SceneObject>>,
+    prefab_name: String,
+    prefab_status: Option<String>,
+    /// Inspector window positions by node ID; mirrored into the config file.
+    window_positions: HashMap<u32, Pos2>,
+    /// Shared named colors, mirrored into the config file, and the name
+    /// typed for the next one (inspector > Set from Node).
+    palette: Vec<(String, Color32)>,
+    palette_name: String,
+    show_palette: bool,
+    rules: Vec<NodeRule>,
+    /// Nodes breaking at least one rule as of this frame, badged on the canvas.
+    warning_nodes: HashSet<u32>,
+    show_rules: bool,
+    /// Scene files last saved or opened, most recent first; mirrored into the config file.
+    recent_scenes: VecDeque<PathBuf>,
+    /// Open inspector tab per node, into `INSPECTOR_TABS`.
+    inspector_tab: HashMap<u32, usize>,
+    /// Inspector widget the user was last typing into or dragging; kept in view
+    /// on the next frame in case the edit moved it.
+    last_changed_widget: Option<egui::Id>,
+    /// Scene from before the batch edit in progress, with its undo description;
+    /// recorded once the pointer is let go, see `batch_edit`.
+    batch_undo: Option<(SceneSnapshot, String)>,
+    /// Extra source → target edges outside the parent-child tree, by node ID.
+    links: Vec<(u32, u32)>,
+    link_mode: LinkMode,
+    link_source: Option<u32>,
+    groups: Vec<NodeGroup>,
+    group_links: Vec<GroupLink>,
+    next_group_id: u32,
+    show_groups: bool,
+    search_query: String,
+    scene_path: String,
+    scene_status: Option<String>,
+    compare_open: bool,
+    /// Before / after file paths typed into the Compare Scenes dialog.
+    compare_paths: [String; 2],
+    comparison: Option<SceneComparison>,
+    compare_error: Option<String>,
+    /// Changed node whose property table is shown.
+    compare_picked: Option<u32>,
+    /// Scene states to go back to, each with the edit that left it.
+    undo_stack: Vec<(SceneSnapshot, String)>,
+    redo_stack: Vec<(SceneSnapshot, String)>,
+    /// What the last undo or redo did, and seconds left to highlight it.
+    status_flash: Option<(String, f32)>,
+    /// Tree-mode node positions from the last time the scene or layout settings
+    /// changed. Set `layout_dirty` after any such change to rebuild it.
+    layout_cache: Option<HashMap<u32, egui::Vec2>>,
+    layout_dirty: bool,
+    /// Where an in-flight camera move (double-click, Fit) is heading.
+    camera_goal: Option<egui::Vec2>,
+    target_zoom: Option<f32>,
+    smooth_camera: bool,
+    /// Set by the Fit button; the canvas frames the scene next time it's laid out.
+    fit_requested: bool,
+    /// C was pressed: center on the active node, or on the whole scene, at the
+    /// current zoom.
+    center_requested: bool,
+    /// Set by Zoom to Subtree; the canvas frames that node's subtree next frame.
+    zoom_to_subtree: Option<u32>,
+    /// While set, primary-drag draws a lasso instead of clicking nodes.
+    lasso_mode: bool,
+    /// Node the canvas context menu was opened on.
+    context_node: Option<u32>,
+    /// Screen-space path of the lasso being drawn.
+    lasso_points: Vec<Pos2>,
+    show_grid: bool,
+    show_minimap: bool,
+    show_outline: bool,
+    /// Outline table sort column, and whether it's ascending.
+    outline_sort: (OutlineColumn, bool),
+    /// Nodes listed live in the Watch panel, in the order they were added.
+    watch_list: Vec<u32>,
+    /// Dropped nodes land on the nearest grid crossing while the grid is shown.
+    snap_to_grid: bool,
+    /// Node being dragged to a manual position.
+    dragging_node: Option<u32>,
+    /// World-space sideways shift of each orthogonal connector's vertical
+    /// segment, by (parent, child). Missing means the default elbow.
+    edge_elbow_offset: HashMap<(u32, u32), f32>,
+    /// Connector whose elbow is being dragged, and whether it has moved yet.
+    dragging_elbow: Option<((u32, u32), bool)>,
+    /// Canvas area from the last frame, for mapping the pointer outside the canvas.
+    canvas_rect: Rect,
+    /// Bit `n` shows layer `n`.
+    visible_layers: u8,
+    /// Per parent: 0 = children folded away, 1 = fully open. Eases toward
+    /// `collapsed` each frame.
+    collapse_anim: HashMap<u32, f32>,
+    /// Property animations started with `tween`, dropped once they finish.
+    active_tweens: Vec<Tween>,
+    /// ID lookups into `scene_objects`; rebuilt while `layout_dirty` is set.
+    node_index: NodeIndex,
+    /// Tools → Fold Duplicates: repeated branches the layout folds away (see
+    /// `fold_duplicates`), each with how many copies of it the scene has.
+    folded_duplicates: HashMap<u32, usize>,
+    /// Nodes shrinking away after a delete, with the seconds left until
+    /// they're actually removed.
+    deleting_nodes: HashMap<u32, f32>,
+    /// Paint Subtree mixes with the existing colors instead of replacing them.
+    paint_blend: bool,
+    /// The node whose memo the inspector shows as an editor rather than rendered.
+    editing_memo: Option<u32>,
+    /// Problems from the last validation; the warning panel shows while non-empty.
+    validation_errors: Vec<ValidationError>,
+    /// Draws nodes as billboards with a fake perspective on their depth.
+    preview_3d: bool,
+    /// Compile error from the last Run, for the node it was run on.
+    script_error: Option<(u32, String)>,
+    /// The opened scene file; edits made elsewhere are loaded automatically.
+    scene_watch: Option<SceneWatcher>,
+    /// Short-lived message and the time it went up.
+    toast: Option<(String, f64)>,
+    /// The wgpu glow pipeline is set up; false on other renderers.
+    glow_available: bool,
+    /// The wgpu adapter as "Key: value" lines; `None` on other renderers.
+    renderer_info: Option<String>,
+    renderer_info_open: bool,
+    /// High-priority nodes glow (View > Glow).
+    show_glow: bool,
+    /// Connectors end in an arrowhead at the child (View > Arrowheads).
+    show_arrows: bool,
+    /// "↑ parent" above every non-root node (View > Show Parent Labels).
+    show_parent_labels: bool,
+    /// Each node is ringed by an arc as long as its `health_score` (View > Health).
+    show_health: bool,
+    /// Triangles are drawn turned to the nearest multiple of this many degrees;
+    /// `current_rotation` itself keeps spinning freely underneath.
+    snap_rotation: Option<f32>,
+    /// Nodes spring toward their layout slot instead of snapping to it.
+    physics_mode: bool,
+    spring_k: f32,
+    /// Where each node currently is in physics mode, and how fast it's moving.
+    physics_pos: HashMap<u32, egui::Vec2>,
+    velocities: HashMap<u32, egui::Vec2>,
+    /// Node positions in the force-directed layout, moved a little every frame.
+    fd_positions: HashMap<u32, egui::Vec2>,
+    /// 1 when the force layout is hot, decaying toward 0 as it settles; scales
+    /// how far nodes may move. Structure changes heat it back up.
+    fd_cooling: f32,
+    /// Rotations are advanced by a compute shader (View → GPU Rotation);
+    /// `rotation_compute` is `None` when the adapter can't run one.
+    gpu_rotation: bool,
+    rotation_compute: Option<RotationCompute>,
+}
+
+/// Deep copy of the editor for branching a scene. In-flight interaction (drags,
+/// renames, queued requests, the inspector) starts fresh, and the copy has no
+/// sound, file watcher or GPU rotation of its own.
+impl Clone for AppState {
+    fn clone(&self) -> Self {
+        Self {
+            time_since_last_update: self.time_since_last_update,
+            frame_count: self.frame_count,
+            target_fps: self.target_fps,
+            scene_objects: self.scene_objects.clone(),
+            camera_target: self.camera_target,
+            zoom: self.zoom,
+            split_mode: self.split_mode,
+            camera_target_b: self.camera_target_b,
+            zoom_b: self.zoom_b,
+            viewport_b: None,
+            panning_b: false,
+            active_settings_id: None,
+            selected_ids: self.selected_ids.clone(),
+            requests: vec![],
+            next_id: self.next_id,
+            dragging: false,
+            pan_velocity: egui::Vec2::ZERO,
+            last_pointer: self.last_pointer,
+            graph: self.graph.clone(),
+            max_visible_depth: self.max_visible_depth,
+            show_help: self.show_help,
+            show_stats: self.show_stats,
+            layout_mode: self.layout_mode,
+            renaming_node: None,
+            proportional_size: self.proportional_size,
+            connector_style: self.connector_style,
+            accessibility_mode: self.accessibility_mode,
+            announce_selection: self.announce_selection,
+            announced_id: self.announced_id,
+            metrics_export_open: false,
+            metrics_path: self.metrics_path.clone(),
+            find_replace_open: false,
+            find_text: self.find_text.clone(),
+            replace_text: self.replace_text.clone(),
+            find_case_sensitive: self.find_case_sensitive,
+            new_step_child: 0,
+            confirm_reset_overrides: false,
+            clipboard_history: self.clipboard_history.clone(),
+            clipboard_picker: None,
+            node_types: self.node_types.clone(),
+            node_types_open: false,
+            new_type_name: self.new_type_name.clone(),
+            textures: self.textures.clone(),
+            image_path: self.image_path.clone(),
+            record_mode: false,
+            playback: None,
+            dot_import_open: self.dot_import_open,
+            template_gallery: None,
+            dot_input: self.dot_input.clone(),
+            dot_error: self.dot_error.clone(),
+            dot_export: self.dot_export.clone(),
+            dot_export_path: self.dot_export_path.clone(),
+            dot_export_status: self.dot_export_status.clone(),
+            scenes: self.scenes.clone(),
+            active_scene: self.active_scene,
+            renaming_scene: None,
+            closing_scene: None,
+            wizard: None,
+            last_wizard_config: self.last_wizard_config.clone(),
+            sound: None,
+            mute: self.mute,
+            pulse_phase: self.pulse_phase,
+            hover_timers: HashMap::new(),
+            theme: self.theme,
+            custom_theme: self.custom_theme,
+            theme_editor_open: self.theme_editor_open,
+            global_speed_multiplier: self.global_speed_multiplier,
+            reparent_buffer: None,
+            slug_buffer: None,
+            slug_error: None,
+            reparent_error: None,
+            flow_phase: self.flow_phase.clone(),
+            edge_spawn_progress: HashMap::new(),
+            prefabs: self.prefabs.clone(),
+            prefab_name: self.prefab_name.clone(),
+            palette: self.palette.clone(),
+            palette_name: self.palette_name.clone(),
+            show_palette: self.show_palette,
+            rules: self.rules.clone(),
+            warning_nodes: self.warning_nodes.clone(),
+            show_rules: self.show_rules,
+            prefab_status: self.prefab_status.clone(),
+            window_positions: self.window_positions.clone(),
+            recent_scenes: self.recent_scenes.clone(),
+            inspector_tab: self.inspector_tab.clone(),
+            last_changed_widget: None,
+            batch_undo: None,
+            links: self.links.clone(),
+            link_mode: self.link_mode,
+            link_source: None,
+            groups: self.groups.clone(),
+            group_links: self.group_links.clone(),
+            next_group_id: self.next_group_id,
+            show_groups: self.show_groups,
+            search_query: self.search_query.clone(),
+            scene_path: self.scene_path.clone(),
+            scene_status: self.scene_status.clone(),
+            compare_open: self.compare_open,
+            compare_paths: self.compare_paths.clone(),
+            comparison: self.comparison.clone(),
+            compare_error: self.compare_error.clone(),
+            compare_picked: self.compare_picked,
+            undo_stack: self.undo_stack.clone(),
+            redo_stack: self.redo_stack.clone(),
+            status_flash: None,
+            layout_cache: None,
+            layout_dirty: true,
+            camera_goal: None,
+            target_zoom: None,
+            smooth_camera: self.smooth_camera,
+            fit_requested: false,
+            center_requested: false,
+            zoom_to_subtree: None,
+            lasso_mode: self.lasso_mode,
+            context_node: None,
+            lasso_points: vec![],
+            show_grid: self.show_grid,
+            show_minimap: self.show_minimap,
+            show_outline: self.show_outline,
+            outline_sort: self.outline_sort,
+            watch_list: self.watch_list.clone(),
+            snap_to_grid: self.snap_to_grid,
+            dragging_node: None,
+            edge_elbow_offset: self.edge_elbow_offset.clone(),
+            dragging_elbow: None,
+            canvas_rect: self.canvas_rect,
+            visible_layers: self.visible_layers,
+            collapse_anim: self.collapse_anim.clone(),
+            active_tweens: vec![],
+            node_index: self.node_index.clone(),
+            folded_duplicates: self.folded_duplicates.clone(),
+            deleting_nodes: HashMap::new(),
+            paint_blend: self.paint_blend,
+            editing_memo: self.editing_memo,
+            validation_errors: self.validation_errors.clone(),
+            preview_3d: self.preview_3d,
+            script_error: self.script_error.clone(),
+            scene_watch: None,
+            toast: None,
+            glow_available: self.glow_available,
+            renderer_info: self.renderer_info.clone(),
+            renderer_info_open: false,
+            show_glow: self.show_glow,
+            show_arrows: self.show_arrows,
+            show_parent_labels: self.show_parent_labels,
+            show_health: self.show_health,
+            snap_rotation: self.snap_rotation,
+            physics_mode: self.physics_mode,
+            spring_k: self.spring_k,
+            physics_pos: HashMap::new(),
+            velocities: HashMap::new(),
+            fd_positions: HashMap::new(),
+            fd_cooling: 1.0,
+            gpu_rotation: false,
+            rotation_compute: None,
+        }
+    }
 }
 
 impl AppState {
-    pub fn new(_: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Result<Self, AppError> {
+        // a broken settings file shouldn't keep the editor from starting
+        let config = Config::load(Path::new(CONFIG_PATH)).unwrap_or_else(|e| {
+            eprintln!("couldn't load {CONFIG_PATH}, using defaults: {e}");
+            Config::default()
+        });
+        if let Some(render_state) = &cc.wgpu_render_state {
+            wgpu_glow::init(render_state);
+        }
         let mut s = Self {
             time_since_last_update: 0.0,
+            frame_count: 0,
+            target_fps: DEFAULT_FPS,
             scene_objects: vec![],
             camera_target: egui::Vec2::new(400.0, 450.0),
             zoom: 1.0,
+            split_mode: SplitMode::Single,
+            camera_target_b: egui::Vec2::new(400.0, 450.0),
+            zoom_b: 1.0,
+            viewport_b: None,
+            panning_b: false,
             active_settings_id: None,
+            selected_ids: HashSet::new(),
             requests: vec![],
             next_id: 0,
             dragging: false,
+            pan_velocity: egui::Vec2::ZERO,
             last_pointer: Pos2::ZERO,
             graph: GraphDemo::new(),
+            max_visible_depth: None,
+            show_help: false,
+            show_stats: false,
+            layout_mode: LayoutMode::Tree,
+            renaming_node: None,
+            proportional_size: false,
+            connector_style: ConnectorStyle::Diagonal,
+            accessibility_mode: AccessibilityMode::None,
+            announce_selection: false,
+            announced_id: None,
+            dot_import_open: false,
+            metrics_export_open: false,
+            metrics_path: "metrics.csv".to_owned(),
+            find_replace_open: false,
+            find_text: String::new(),
+            replace_text: String::new(),
+            find_case_sensitive: false,
+            new_step_child: 0,
+            confirm_reset_overrides: false,
+            clipboard_history: VecDeque::new(),
+            clipboard_picker: None,
+            node_types: HashMap::new(),
+            node_types_open: false,
+            new_type_name: String::new(),
+            textures: HashMap::new(),
+            image_path: String::new(),
+            record_mode: false,
+            playback: None,
+            template_gallery: None,
+            dot_input: String::new(),
+            dot_error: None,
+            dot_export: None,
+            dot_export_path: "scene.dot".into(),
+            dot_export_status: None,
+            scenes: vec![SceneTab::new("Scene 1")],
+            active_scene: 0,
+            renaming_scene: None,
+            closing_scene: None,
+            wizard: None,
+            last_wizard_config: NewNodeConfig::default(),
+            // a missing audio device shouldn't keep the editor from starting
+            sound: SoundPlayer::new()
+                .inspect_err(|e| eprintln!("sound disabled: {e}"))
+                .ok(),
+            mute: false,
+            pulse_phase: 0.0,
+            hover_timers: HashMap::new(),
+            theme: Theme::Dark,
+            custom_theme: CustomTheme::default(),
+            theme_editor_open: false,
+            global_speed_multiplier: 1.0,
+            reparent_buffer: None,
+            slug_buffer: None,
+            slug_error: None,
+            reparent_error: None,
+            flow_phase: HashMap::new(),
+            edge_spawn_progress: HashMap::new(),
+            prefabs: config.prefabs,
+            prefab_name: String::new(),
+            palette: config.palette,
+            palette_name: String::new(),
+            show_palette: false,
+            rules: vec![],
+            warning_nodes: HashSet::new(),
+            show_rules: false,
+            prefab_status: None,
+            window_positions: config.window_positions,
+            recent_scenes: config.recent_scenes,
+            inspector_tab: HashMap::new(),
+            last_changed_widget: None,
+            batch_undo: None,
+            links: vec![],
+            link_mode: LinkMode::Off,
+            link_source: None,
+            groups: vec![],
+            group_links: vec![],
+            next_group_id: 0,
+            show_groups: false,
+            search_query: String::new(),
+            scene_path: "scene.json".into(),
+            scene_status: None,
+            compare_open: false,
+            compare_paths: [String::new(), String::new()],
+            comparison: None,
+            compare_error: None,
+            compare_picked: None,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            status_flash: None,
+            layout_cache: None,
+            layout_dirty: true,
+            camera_goal: None,
+            target_zoom: None,
+            smooth_camera: true,
+            fit_requested: false,
+            center_requested: false,
+            zoom_to_subtree: None,
+            lasso_mode: false,
+            context_node: None,
+            lasso_points: Vec::new(),
+            show_grid: false,
+            show_minimap: true,
+            show_outline: false,
+            outline_sort: (OutlineColumn::Id, true),
+            watch_list: vec![],
+            snap_to_grid: true,
+            dragging_node: None,
+            edge_elbow_offset: HashMap::new(),
+            dragging_elbow: None,
+            canvas_rect: Rect::ZERO,
+            visible_layers: u8::MAX,
+            collapse_anim: HashMap::new(),
+            active_tweens: vec![],
+            node_index: NodeIndex::default(),
+            folded_duplicates: HashMap::new(),
+            deleting_nodes: HashMap::new(),
+            paint_blend: false,
+            editing_memo: None,
+            validation_errors: vec![],
+            preview_3d: false,
+            script_error: None,
+            scene_watch: None,
+            toast: None,
+            glow_available: cc.wgpu_render_state.is_some(),
+            renderer_info: cc.wgpu_render_state.as_ref().map(|rs| {
+                let info = rs.adapter.get_info();
+                format!(
+                    "Adapter: {}\nBackend: {:?}\nDevice type: {:?}\nDriver: {} {}",
+                    info.name, info.backend, info.device_type, info.driver, info.driver_info,
+                )
+            }),
+            renderer_info_open: false,
+            show_glow: true,
+            show_arrows: false,
+            show_parent_labels: false,
+            show_health: false,
+            snap_rotation: None,
+            physics_mode: false,
+            spring_k: 20.0,
+            physics_pos: HashMap::new(),
+            velocities: HashMap::new(),
+            fd_positions: HashMap::new(),
+            fd_cooling: 1.0,
+            gpu_rotation: false,
+            rotation_compute: cc
+                .wgpu_render_state
+                .as_ref()
+                .filter(|rs| RotationCompute::supported(&rs.adapter))
+                .map(|rs| RotationCompute::new(&rs.device, &rs.queue)),
         };
 
-        /* sample tree */
-        let mut root = SceneObject::new(s.new_id(), "Root", ShapeKind::Square, Color32::RED);
-        let mut a = SceneObject::new(s.new_id(), "Data", ShapeKind::Circle, Color32::BLUE);
-        let mut b = SceneObject::new(s.new_id(), "Render", ShapeKind::Triangle, Color32::GREEN);
-
-        a.children.push(SceneObject::new(
-            s.new_id(),
-            "Mesh",
-            ShapeKind::Square,
-            Color32::YELLOW,
-        ));
-        a.children.push(SceneObject::new(
-            s.new_id(),
-            "Texture",
-            ShapeKind::Triangle,
-            Color32::from_rgb(255, 128, 0),
-        ));
-        b.children.push(SceneObject::new(
-            s.new_id(),
-            "Shader",
-            ShapeKind::Circle,
-            Color32::from_rgb(128, 0, 255),
-        ));
-
-        root.children.push(a);
-        root.children.push(b);
-        s.scene_objects.push(root);
-        s
+        s.scene_objects = sample_scene(&mut s.next_id);
+        Ok(s)
     }
     fn new_id(&mut self) -> u32 {
         self.next_id += 1;
         self.next_id
     }
-}
-
-/* ---------------- eframe::App impl ---------------- */
-
-impl eframe::App for AppState {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let dt = 1.0 / FRAMES_PER_SECOND as f32;
-        self.time_since_last_update += dt;
 
-        self.graph.maybe_tick();
+    /// Parks the active scene in its tab and loads tab `i` in its place.
+    fn switch_scene(&mut self, i: usize) {
+        if i == self.active_scene || i >= self.scenes.len() {
+            return;
+        }
+        // finish any shrinking deletes before the scene is parked
+        for (id, _) in self.deleting_nodes.drain() {
+            detach_node(&mut self.scene_objects, id);
+        }
+        let cur = &mut self.scenes[self.active_scene];
+        cur.objects = std::mem::take(&mut self.scene_objects);
+        cur.camera_target = self.camera_target;
+        cur.zoom = self.zoom;
+        cur.next_id = self.next_id;
+        cur.active_settings_id = self.active_settings_id;
+        cur.links = std::mem::take(&mut self.links);
+        cur.groups = std::mem::take(&mut self.groups);
+        cur.group_links = std::mem::take(&mut self.group_links);
 
-        /* ----- pan & zoom ----- */
-        let input = ctx.input(|i| i.clone());
-        self.zoom =
-            (self.zoom + input.raw_scroll_delta.y * 0.001 * ctx.pixels_per_point()).clamp(0.1, 2.0);
+        let next = &mut self.scenes[i];
+        self.scene_objects = std::mem::take(&mut next.objects);
+        self.camera_target = next.camera_target;
+        self.zoom = next.zoom;
+        self.next_id = next.next_id;
+        self.active_settings_id = next.active_settings_id;
+        self.links = std::mem::take(&mut next.links);
+        self.groups = std::mem::take(&mut next.groups);
+        self.group_links = std::mem::take(&mut next.group_links);
+        self.active_scene = i;
+        self.link_source = None;
+        // the watched file belongs to the scene being parked
+        self.scene_watch = None;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.layout_dirty = true;
+        self.selected_ids.clear();
+        self.requests.clear();
+    }
 
-        if input.pointer.secondary_down() && !self.dragging {
-            self.dragging = true;
-            self.last_pointer = input.pointer.hover_pos().unwrap_or(self.last_pointer);
+    fn close_scene(&mut self, i: usize) {
+        if self.scenes.len() <= 1 || i >= self.scenes.len() {
+            return;
         }
-        if self.dragging {
-            if let Some(p) = input.pointer.hover_pos() {
-                let delta = (p - self.last_pointer) / self.zoom;
-                self.camera_target -= egui::Vec2::new(delta.x, delta.y);
-                self.last_pointer = p;
-            }
-            if !input.pointer.secondary_down() {
-                self.dragging = false;
-            }
+        if i == self.active_scene {
+            self.switch_scene(if i == 0 { 1 } else { i - 1 });
         }
+        self.scenes.remove(i);
+        if self.active_scene > i {
+            self.active_scene -= 1;
+        }
+    }
 
-        for o in &mut self.scene_objects {
-            animate(o, dt);
+    fn scene_is_empty(&self, i: usize) -> bool {
+        if i == self.active_scene {
+            self.scene_objects.is_empty()
+        } else {
+            self.scenes[i].objects.is_empty()
         }
+    }
 
-        /* ----- drawing canvas ----- */
-        egui::CentralPanel::default().show(ctx, |ui| {
-            let resp = ui.allocate_rect(ui.max_rect(), egui::Sense::click_and_drag());
-            let painter = ui.painter();
-            let mut layout: HashMap<u32, egui::Vec2> = HashMap::new();
-            let mut cy = 100.0;
-            for o in &self.scene_objects {
-                layout_recursive(o, 200.0, cy, &mut cy, &mut layout);
+    /// Every node in the active tab's scene.
+    pub fn total_node_count(&self) -> usize {
+        node_count(&self.scene_objects)
+    }
+
+    /// Adds a timing sample to a node's sparkline, dropping the oldest past
+    /// `MAX_TIMING_SAMPLES`.
+    pub fn record_timing(&mut self, id: u32, ms: f32) {
+        if let Some(o) = self.node_index.get_mut(&mut self.scene_objects, id) {
+            o.timing_samples.push_back(ms);
+            if o.timing_samples.len() > MAX_TIMING_SAMPLES {
+                o.timing_samples.pop_front();
             }
+        }
+    }
 
-            let to_screen = |p: egui::Vec2| {
-                let offset = ui.max_rect().min.to_vec2() + ui.max_rect().size() / 2.0;
-                let v = offset + (p - self.camera_target) * self.zoom;
-                Pos2::new(v.x, v.y)
-            };
-            for (&id, &pos) in &layout {
-                if let Some(obj) = find_object_by_id(&self.scene_objects, id) {
-                    draw_world(painter, obj, pos, &layout, &to_screen);
+    /// Animates `property` of `node_id` from its current value to `to` over
+    /// `duration` seconds, replacing any tween already running on it.
+    pub fn tween(
+        &mut self,
+        node_id: u32,
+        property: TweenProperty,
+        to: f32,
+        duration: f32,
+        easing: EasingKind,
+    ) {
+        let Some(o) = self.node_index.get(&self.scene_objects, node_id) else {
+            return;
+        };
+        self.active_tweens
+            .retain(|t| t.node_id != node_id || t.property != property);
+        self.active_tweens.push(Tween {
+            node_id,
+            property,
+            from: property.get(o),
+            to,
+            duration,
+            elapsed: 0.0,
+            easing,
+        });
+    }
+
+    /// Selection, undo/redo, clipboard and window keys. Keys that would type
+    /// into a focused text field are left alone.
+    fn handle_keyboard_shortcuts(&mut self, ctx: &egui::Context, input: &egui::InputState) {
+        if !ctx.wants_keyboard_input() {
+            if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Z)) {
+                if input.modifiers.shift {
+                    self.redo();
+                } else {
+                    self.undo();
                 }
             }
+            if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Y)) {
+                self.redo();
+            }
+            if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::A)) {
+                let selected = &mut self.selected_ids;
+                visit(&self.scene_objects, 0, &mut |o, _| {
+                    if !o.locked {
+                        selected.insert(o.id);
+                    }
+                });
+            }
+            if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::D)) {
+                self.selected_ids.clear();
+            }
+            if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::H)) {
+                self.find_replace_open = !self.find_replace_open;
+            }
+            if ctx.input(|i| i.modifiers.is_none() && i.key_pressed(egui::Key::C)) {
+                self.center_requested = true;
+            }
+            if ctx.input(|i| i.modifiers.is_none() && i.key_pressed(egui::Key::L)) {
+                self.lasso_mode = !self.lasso_mode;
+                self.lasso_points.clear();
+            }
+            if ctx.input(|i| i.modifiers.ctrl && i.modifiers.shift && i.key_pressed(egui::Key::R)) {
+                self.requests
+                    .push(EditorRequest::ResetRotation { node_id: None });
+            }
+            if ctx.input(|i| i.modifiers.is_none() && i.key_pressed(egui::Key::Space))
+                && let Some(node_id) = self.active_settings_id
+            {
+                self.requests
+                    .push(EditorRequest::ToggleCollapsed { node_id });
+            }
 
-            if resp.clicked() && input.pointer.primary_released() {
-                if let Some(pos) = input.pointer.interact_pos() {
-                    let world =
-                        (pos.to_vec2() - ui.max_rect().min.to_vec2() - ui.max_rect().size() / 2.0)
-                            / self.zoom
-                            + self.camera_target;
-                    self.active_settings_id = self
-                        .scene_objects
-                        .iter()
-                        .filter_map(|o| find_clicked_object(o, world, &layout))
-                        .next();
+            /* copy & paste go through the system clipboard as a node's JSON */
+            for event in &input.events {
+                match event {
+                    egui::Event::Copy => {
+                        if let Some(obj) = self
+                            .active_settings_id
+                            .and_then(|id| self.node_index.get(&self.scene_objects, id))
+                        {
+                            match serde_json::to_string_pretty(obj) {
+                                Ok(json) => {
+                                    ctx.copy_text(json.clone());
+                                    self.clipboard_history.retain(|j| *j != json);
+                                    self.clipboard_history.push_front(json);
+                                    self.clipboard_history.truncate(CLIPBOARD_HISTORY);
+                                }
+                                Err(e) => {
+                                    self.toast = Some((format!("Couldn't copy: {e}"), input.time));
+                                }
+                            }
+                        }
+                    }
+                    // Shift picks from this session's copies instead, if there are any
+                    egui::Event::Paste(text) => {
+                        let cursor = input.pointer.hover_pos().map(|p| self.screen_to_world(p));
+                        if !input.modifiers.shift {
+                            self.paste_subtree(text, None, input);
+                        } else if self.clipboard_history.is_empty() {
+                            self.paste_subtree(text, cursor, input);
+                        } else {
+                            self.clipboard_picker = cursor.or(Some(self.camera_target));
+                        }
+                    }
+                    _ => {}
                 }
             }
-        });
+        }
+        if !ctx.wants_keyboard_input()
+            && ctx.input(|i| i.key_pressed(egui::Key::F2))
+            && let Some(id) = self.active_settings_id
+            && let Some(obj) = self.node_index.get_mut(&mut self.scene_objects, id)
+        {
+            obj.text_buffer.clone_from(&obj.text);
+            self.renaming_node = Some(id);
+        }
+        if self.renaming_node.is_none() && ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.selected_ids.clear();
+            self.active_settings_id = None;
+            self.lasso_mode = false;
+            self.lasso_points.clear();
+        }
+        if ctx.input(|i| i.key_pressed(egui::Key::F1)) {
+            self.show_help = !self.show_help;
+        }
+    }
 
-        egui::Window::new("Price graph").show(ctx, |ui| {
-            self.graph.ui(ui);
+    /// Inserts a copied subtree under the active node, or as a root without one,
+    /// and selects it. With `at` it's pinned there instead of placed by the layout.
+    fn paste_subtree(&mut self, text: &str, at: Option<egui::Vec2>, input: &egui::InputState) {
+        let Ok(mut node) = serde_json::from_str::<SceneObject>(text) else {
+            self.toast = Some(("The clipboard doesn't hold a node".into(), input.time));
+            return;
+        };
+        self.assign_new_ids(std::slice::from_mut(&mut node));
+        node.position_override = at;
+        let parent_id = self
+            .active_settings_id
+            .filter(|&id| self.node_index.get(&self.scene_objects, id).is_some());
+        self.active_settings_id = Some(node.id);
+        self.selected_ids = HashSet::from([node.id]);
+        self.requests.push(EditorRequest::Paste {
+            parent_id,
+            node: Box::new(node),
         });
+    }
 
-        /* ----- inspector ----- */
-        if let Some(id) = self.active_settings_id {
-            if let Some(obj) = find_object_by_id_mut(&mut self.scene_objects, id) {
-                egui::Window::new(format!("Settings: {}", obj.text))
-                    .resizable(false)
-                    .show(ctx, |ui| {
-                        ui.label("Name:");
-                        let resp = ui.text_edit_singleline(&mut obj.text_buffer);
-                        if resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                            obj.text.clone_from(&obj.text_buffer);
-                        }
-                        if ui.button("Apply Name").clicked() {
-                            obj.text.clone_from(&obj.text_buffer);
-                        }
+    /// Shows `depth` levels below `id` (`None` for all of them) and lifts any
+    /// depth limit further down, so the chosen depth is the only one in effect.
+    fn expand_to_depth(&mut self, id: u32, depth: Option<u32>) {
+        let name = quoted_name(&self.scene_objects, id);
+        self.push_undo(format!("Expand {name}"));
+        if let Some(o) = self.node_index.get_mut(&mut self.scene_objects, id) {
+            o.collapsed = false;
+            o.expand_depth = depth;
+            visit_mut(&mut o.children, 0, &mut |c, _| c.expand_depth = None);
+        }
+        self.layout_dirty = true;
+    }
 
-                        ui.separator();
-                        ui.label("Shape:");
-                        ui.radio_value(&mut obj.shape, ShapeKind::Square, "Square");
-                        ui.radio_value(&mut obj.shape, ShapeKind::Circle, "Circle");
-                        ui.radio_value(&mut obj.shape, ShapeKind::Triangle, "Triangle");
+    /// Folds away every branch that repeats an earlier one, see
+    /// `duplicate_subtrees`. Only the layout is affected; the nodes' own
+    /// `collapsed` stays as the user left it.
+    fn fold_duplicates(&mut self) {
+        self.folded_duplicates = duplicate_subtrees(&self.scene_objects);
+        self.layout_dirty = true;
+    }
 
-                        ui.separator();
-                        ui.label("Rotation Speed:");
-                        ui.add(Slider::new(&mut obj.rotation_speed, -180.0..=180.0));
+    fn unfold_all(&mut self) {
+        self.folded_duplicates.clear();
+        self.layout_dirty = true;
+    }
 
-                        ui.separator();
-                        ui.label("Color:");
-                        let rgba = obj.color.to_array();
-                        let mut col = [
-                            rgba[0] as f32 / 255.0,
-                            rgba[1] as f32 / 255.0,
-                            rgba[2] as f32 / 255.0,
-                            rgba[3] as f32 / 255.0,
-                        ];
-                        if ui.color_edit_button_rgba_unmultiplied(&mut col).changed() {
-                            obj.color = Color32::from_rgba_unmultiplied(
-                                (col[0] * 255.0) as u8,
-                                (col[1] * 255.0) as u8,
-                                (col[2] * 255.0) as u8,
-                                (col[3] * 255.0) as u8,
-                            );
-                        }
+    fn screen_to_world(&self, p: Pos2) -> egui::Vec2 {
+        (p - self.canvas_rect.center()) / self.zoom + self.camera_target
+    }
 
-                        ui.horizontal(|ui| {
-                            if ui.button("Add Child").clicked() {
-                                self.requests
-                                    .push(EditorRequest::AddChild { parent_id: id });
-                            }
-                            if ui.button("Delete Node").clicked() {
-                                self.requests
-                                    .push(EditorRequest::DeleteNode { node_id: id });
-                                self.active_settings_id = None;
-                            }
-                        });
-                    });
-            }
+    fn world_to_screen(&self, p: egui::Vec2) -> Pos2 {
+        self.canvas_rect.center() + (p - self.camera_target) * self.zoom
+    }
+
+    pub fn snapshot(&self) -> SceneSnapshot {
+        SceneSnapshot {
+            objects: self.scene_objects.clone(),
+            links: self.links.clone(),
+            groups: self.groups.clone(),
+            group_links: self.group_links.clone(),
+            next_id: self.next_id,
         }
+    }
+
+    pub fn restore_snapshot(&mut self, snap: SceneSnapshot) {
+        self.scene_objects = snap.objects;
+        self.links = snap.links;
+        self.groups = snap.groups;
+        self.group_links = snap.group_links;
+        self.next_id = snap.next_id;
+        // a shrink still playing out would otherwise delete from the restored scene
+        self.deleting_nodes.clear();
+        // undoing a paste or an add takes away nodes the selection may still name
+        let ids = ids_in(&self.scene_objects);
+        self.selected_ids.retain(|id| ids.contains(id));
+        self.active_settings_id = self.active_settings_id.filter(|id| ids.contains(id));
+        self.layout_dirty = true;
+    }
 
+    fn push_undo(&mut self, description: String) {
+        self.push_undo_snapshot(self.snapshot(), description);
+    }
+
+    /// Applies a Batch Edit change right away, without an undo entry of its own:
+    /// the scene from before the first change is kept until the pointer is
+    /// released, so a whole slider drag undoes in one step.
+    fn batch_edit(&mut self, req: EditorRequest) {
+        if self.batch_undo.is_none() {
+            self.batch_undo = Some((self.snapshot(), req.description(&self.scene_objects)));
+        }
         process_requests(
             &mut self.scene_objects,
-            &mut self.requests,
+            &self.node_index,
+            &mut vec![req],
             &mut self.next_id,
+            &self.node_types,
+            None,
         );
+        self.layout_dirty = true;
+    }
 
-        /* ----- clipboard ----- */
-        for cmd in ctx.output(|o| o.commands.clone()) {
-            if let OutputCommand::CopyText(_text) = cmd {}
+    /// Records a scene state captured before an edit that has already started.
+    fn push_undo_snapshot(&mut self, snapshot: SceneSnapshot, description: String) {
+        self.undo_stack.push((snapshot, description));
+        if self.undo_stack.len() > UNDO_LIMIT {
+            self.undo_stack.remove(0);
         }
+        self.redo_stack.clear();
+    }
 
-        ctx.request_repaint_after(std::time::Duration::from_secs_f32(
-            1.0 / FRAMES_PER_SECOND as f32,
-        ));
+    fn undo(&mut self) {
+        // a delete that is still shrinking has no undo entry yet, so undo calls it off
+        if let Some(&node_id) = self.deleting_nodes.keys().min() {
+            let mut description =
+                EditorRequest::DeleteNode { node_id }.description(&self.scene_objects);
+            if self.deleting_nodes.len() > 1 {
+                description = format!("{description} (+{} more)", self.deleting_nodes.len() - 1);
+            }
+            self.deleting_nodes.clear();
+            self.status_flash = Some((format!("Undid {description}"), STATUS_FLASH_SECS));
+            return;
+        }
+        if let Some((prev, description)) = self.undo_stack.pop() {
+            self.redo_stack.push((self.snapshot(), description.clone()));
+            self.restore_snapshot(prev);
+            self.status_flash = Some((format!("Undid {description}"), STATUS_FLASH_SECS));
+        }
     }
-}
 
-/* ---------------- helpers ---------------- */
+    fn redo(&mut self) {
+        if let Some((next, description)) = self.redo_stack.pop() {
+            self.undo_stack.push((self.snapshot(), description.clone()));
+            self.restore_snapshot(next);
+            self.status_flash = Some((format!("Redid {description}"), STATUS_FLASH_SECS));
+        }
+    }
 
-fn animate(o: &mut SceneObject, dt: f32) {
-    o.current_rotation += o.rotation_speed * dt;
-    for c in &mut o.children {
-        animate(c, dt);
+    fn save_scene(&self, path: &Path) -> Result<(), AppError> {
+        let scene = SceneFile {
+            objects: self.scene_objects.clone(),
+            links: self.links.clone(),
+            node_types: self.node_types.clone(),
+        };
+        scene_file::save(path, &scene)
     }
-}
 
-fn layout_recursive(
-    o: &SceneObject,
-    x: f32,
-    y: f32,
-    cur: &mut f32,
-    m: &mut HashMap<u32, egui::Vec2>,
-) -> f32 {
-    const XS: f32 = 250.0;
-    const YS: f32 = 120.0;
-    let mut th = 0.0;
-    let mut cy = y;
-    for c in &o.children {
-        th += layout_recursive(c, x + XS, cy, &mut cy, m);
+    /// Replaces the active scene with the file's contents, keeping its IDs.
+    fn load_scene(&mut self, path: &Path) -> Result<(), AppError> {
+        let scene = scene_file::load(path)?;
+        self.next_id = ids_in(&scene.objects).into_iter().max().unwrap_or(0);
+        self.scene_objects = scene.objects;
+        // scenes saved before slugs existed
+        visit_mut(&mut self.scene_objects, 0, &mut |o, _| {
+            if o.slug.is_empty() {
+                o.slug = slugify(&o.text, o.id);
+            }
+        });
+        self.links = scene.links;
+        self.node_types = scene.node_types;
+        self.groups.clear();
+        self.group_links.clear();
+        self.selected_ids.clear();
+        self.active_settings_id = None;
+        self.link_source = None;
+        self.requests.clear();
+        self.deleting_nodes.clear();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.layout_dirty = true;
+        self.validation_errors = validate_scene(&self.scene_objects, &self.links);
+        Ok(())
     }
-    let p = if !o.children.is_empty() {
-        egui::Vec2::new(x, y + th / 2.0 - YS / 2.0)
-    } else {
-        egui::Vec2::new(x, *cur)
-    };
-    m.insert(o.id, p);
-    let h = th.max(YS);
-    *cur = y + h;
-    h
-}
 
-fn draw_world<F>(
-    painter: &egui::Painter,
-    o: &SceneObject,
-    p: egui::Vec2,
-    m: &HashMap<u32, egui::Vec2>,
-    to_screen: &F,
-) where
-    F: Fn(egui::Vec2) -> Pos2,
-{
-    for c in &o.children {
-        if let Some(&cp) = m.get(&c.id) {
-            painter.line_segment(
-                [to_screen(p), to_screen(cp)],
-                Stroke::new(1.0, Color32::GRAY),
-            );
+    fn save_config(&self) -> Result<(), AppError> {
+        Config {
+            prefabs: self.prefabs.clone(),
+            window_positions: self.window_positions.clone(),
+            recent_scenes: self.recent_scenes.clone(),
+            palette: self.palette.clone(),
         }
+        .save(Path::new(CONFIG_PATH))
     }
 
-    let center = to_screen(p);
-    let sz = 40.0;
-    match o.shape {
-        ShapeKind::Square => {
-            let rect = Rect::from_center_size(center, egui::Vec2::splat(sz));
-            // **THE FIX**: This is the correct signature for painter.rect
-            // It takes the rect, rounding, fill color, and stroke.
-            // The fifth 'StrokeKind' argument was incorrect.
-            painter.rect(rect, 0.0, o.color, Stroke::NONE, egui::StrokeKind::Middle);
+    /// Moves `path` to the front of File → Recent Scenes and saves the config.
+    fn remember_scene(&mut self, path: &Path) -> Result<(), AppError> {
+        self.recent_scenes.retain(|p| p != path);
+        self.recent_scenes.push_front(path.to_path_buf());
+        self.recent_scenes.truncate(MAX_RECENT_SCENES);
+        self.save_config()
+    }
+
+    /// Opens the scene at `path`, watches it for outside edits and records it as
+    /// recent. Returns the File menu's status line.
+    fn open_scene(&mut self, path: &Path) -> String {
+        match self.load_scene(path) {
+            Ok(()) => {
+                self.scene_watch = SceneWatcher::new(path)
+                    .inspect_err(|e| eprintln!("not watching {}: {e}", path.display()))
+                    .ok();
+                if let Err(e) = self.remember_scene(path) {
+                    eprintln!("couldn't save recent scenes: {e}");
+                }
+                format!("Opened {}", path.display())
+            }
+            Err(e) => format!("Couldn't open: {e}"),
         }
-        ShapeKind::Circle => {
-            painter.circle(center, sz * 0.5, o.color, Stroke::NONE);
+    }
+
+    /// `scene://<id>` selects and centers that node; anything else goes to the OS.
+    fn follow_link(&mut self, ctx: &egui::Context, link: &str) -> Result<(), String> {
+        if let Some(rest) = link.strip_prefix("scene://") {
+            let id = rest
+                .parse::<u32>()
+                .ok()
+                .filter(|&id| self.node_index.get(&self.scene_objects, id).is_some())
+                .ok_or_else(|| format!("No node {rest} in this scene"))?;
+            self.active_settings_id = Some(id);
+            if let Some(&p) = self.layout_cache.as_ref().and_then(|m| m.get(&id)) {
+                self.camera_goal = Some(p);
+            }
+            return Ok(());
         }
-        ShapeKind::Triangle => {
-            let a = o.current_rotation.to_radians();
-            let rot = |v: egui::Vec2| {
-                egui::Vec2::new(v.x * a.cos() - v.y * a.sin(), v.x * a.sin() + v.y * a.cos())
-                    + center.to_vec2()
-            };
-            let v = |v: egui::Vec2| Pos2::new(v.x, v.y);
-            painter.add(Shape::convex_polygon(
-                vec![
-                    v(rot(egui::Vec2::new(0.0, -sz / 2.0))),
-                    v(rot(egui::Vec2::new(-sz / 2.0, sz / 2.0))),
-                    v(rot(egui::Vec2::new(sz / 2.0, sz / 2.0))),
-                ],
-                o.color,
-                Stroke::NONE,
-            ));
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let _ = ctx;
+            open::that(link).map_err(|e| format!("Couldn't open {link}: {e}"))
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            ctx.open_url(egui::OpenUrl::new_tab(link));
+            Ok(())
         }
     }
-    painter.text(
-        Pos2::new(center.x, center.y + sz * 0.65),
-        egui::Align2::CENTER_CENTER,
-        &o.text,
-        FontId::proportional(16.0),
-        Color32::WHITE,
-    );
-}
 
-fn process_requests(v: &mut Vec<SceneObject>, reqs: &mut Vec<EditorRequest>, next_id: &mut u32) {
-    for r in reqs.drain(..) {
-        match r {
-            EditorRequest::AddChild { parent_id } => {
-                if let Some(p) = find_object_by_id_mut(v, parent_id) {
-                    *next_id += 1;
-                    let id = *next_id;
-                    p.children.push(SceneObject::new(
-                        id,
-                        "New Node",
-                        ShapeKind::Square,
-                        Color32::WHITE,
-                    ));
+    /// Builds a preview of every template for File → New from Template.
+    fn open_template_gallery(&mut self) {
+        let mut scratch_id = 0;
+        let previews = TEMPLATES
+            .iter()
+            .map(|t| (t.build)(&mut scratch_id))
+            .collect();
+        self.template_gallery = Some(previews);
+    }
+
+    /// Swaps the active scene for `objects`, renumbered from this scene's counter.
+    fn replace_scene(&mut self, mut objects: Vec<SceneObject>, description: String) {
+        self.push_undo(description);
+        self.assign_new_ids(&mut objects);
+        self.scene_objects = objects;
+        self.links.clear();
+        self.groups.clear();
+        self.group_links.clear();
+        self.selected_ids.clear();
+        self.active_settings_id = None;
+        self.link_source = None;
+        self.requests.clear();
+        self.deleting_nodes.clear();
+        self.layout_dirty = true;
+    }
+
+    /// Where node `id` sits in world space as of the last drawn frame; `None`
+    /// for treemap tiles, which aren't laid out in world space.
+    fn world_position(&self, id: u32) -> Option<egui::Vec2> {
+        let layout = if self.physics_mode && self.layout_mode != LayoutMode::Treemap {
+            Some(&self.physics_pos)
+        } else {
+            match self.layout_mode {
+                LayoutMode::Tree | LayoutMode::Circular | LayoutMode::FoldedRows => {
+                    self.layout_cache.as_ref()
                 }
+                LayoutMode::ForceDirected => Some(&self.fd_positions),
+                LayoutMode::Treemap => None,
             }
-            EditorRequest::DeleteNode { node_id } => {
-                find_and_delete_node(v, node_id);
-            }
+        };
+        layout?.get(&id).copied()
+    }
+
+    /// Homogeneous 2D matrix taking the node's local shape coordinates to
+    /// screen points: its scale (with any pulse) and `current_rotation`, then
+    /// its layout position, then the camera's pan and zoom. Pinned nodes skip
+    /// the camera and sit at `screen_pos`.
+    fn node_transform(&self, id: u32) -> Option<[[f32; 3]; 3]> {
+        let o = self.node_index.get(&self.scene_objects, id)?;
+        let (translation, zoom) = if o.pinned_to_screen {
+            (o.screen_pos.to_vec2(), 1.0)
+        } else {
+            let p = self.world_position(id)?;
+            let t = self.canvas_rect.center().to_vec2() + (p - self.camera_target) * self.zoom;
+            (t, self.zoom)
+        };
+        let k = zoom * o.shown_scale() * scale_factor(o, self.time_since_last_update);
+        let (sin, cos) = o.current_rotation.to_radians().sin_cos();
+        Some([
+            [k * cos, -k * sin, translation.x],
+            [k * sin, k * cos, translation.y],
+            [0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Scatters the force layout's nodes at random and heats it back up.
+    fn reset_force_layout(&mut self) {
+        let half = (self.fd_positions.len() as f32).sqrt() * FD_SPACING / 2.0;
+        let mut rng = rand::rng();
+        for p in self.fd_positions.values_mut() {
+            *p = egui::Vec2::new(
+                rng.random_range(-half..=half),
+                rng.random_range(-half..=half),
+            );
         }
+        self.fd_cooling = 1.0;
     }
-}
 
-fn find_and_delete_node(v: &mut Vec<SceneObject>, id: u32) -> bool {
-    if let Some(i) = v.iter().position(|o| o.id == id) {
-        v.remove(i);
-        return true;
+    /// Drops every node's dragged position so the layout places them all again.
+    fn reset_position_overrides(&mut self) {
+        self.push_undo("Reset All Overrides".to_owned());
+        visit_mut(&mut self.scene_objects, 0, &mut |o, _| {
+            o.position_override = None;
+        });
+        if self.layout_mode == LayoutMode::ForceDirected {
+            self.reset_force_layout();
+        }
+        self.layout_dirty = true;
+    }
+
+    /// Gives every node in `v` a fresh ID from this scene's counter.
+    fn assign_new_ids(&mut self, v: &mut [SceneObject]) {
+        for o in v {
+            o.id = self.new_id();
+            o.slug = slugify(&o.text, o.id);
+            self.assign_new_ids(&mut o.children);
+        }
     }
-    v.iter_mut()
-        .any(|o| find_and_delete_node(&mut o.children, id))
 }
 
-fn find_object_by_id(v: &[SceneObject], id: u32) -> Option<&SceneObject> {
-    v.iter().find_map(|o| {
-        if o.id == id {
-            Some(o)
+/* ---------------- eframe::App impl ---------------- */
+
+impl eframe::App for AppState {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let dt = 1.0 / self.target_fps as f32;
+        self.time_since_last_update += dt;
+        self.frame_count += 1;
+        self.pulse_phase = (self.pulse_phase + dt * PULSE_HZ).fract();
+        if self.layout_dirty {
+            self.node_index = NodeIndex::build(&self.scene_objects);
+        }
+        if let Some((_, left)) = &mut self.status_flash {
+            *left -= dt;
+            if *left <= 0.0 {
+                self.status_flash = None;
+            }
+        }
+
+        ctx.set_visuals(match self.theme {
+            Theme::Dark => egui::Visuals::dark(),
+            Theme::Light => egui::Visuals::light(),
+            Theme::Custom => self.custom_theme.visuals(),
+        });
+
+        self.graph.maybe_tick();
+
+        /* ----- pan & zoom ----- */
+        let input = ctx.input(|i| i.clone());
+        // whichever view the pointer is over takes the wheel and the pan
+        let over_b = self
+            .viewport_b
+            .zip(input.pointer.hover_pos())
+            .is_some_and(|(r, p)| r.contains(p));
+        let scroll = input.raw_scroll_delta.y * 0.001 * ctx.pixels_per_point();
+        if over_b {
+            self.zoom_b = (self.zoom_b + scroll).clamp(MIN_ZOOM, MAX_ZOOM);
         } else {
-            find_object_by_id(&o.children, id)
+            if scroll != 0.0 {
+                self.target_zoom = None;
+            }
+            self.zoom = (self.zoom + scroll).clamp(MIN_ZOOM, MAX_ZOOM);
         }
-    })
-}
-fn find_object_by_id_mut(v: &mut [SceneObject], id: u32) -> Option<&mut SceneObject> {
-    v.iter_mut().find_map(|o| {
-        if o.id == id {
-            Some(o)
+
+        if input.pointer.secondary_down() && !self.dragging {
+            self.dragging = true;
+            self.panning_b = over_b;
+            if !over_b {
+                self.camera_goal = None;
+            }
+            // grabbing again stops a glide dead
+            self.pan_velocity = egui::Vec2::ZERO;
+            self.last_pointer = input.pointer.hover_pos().unwrap_or(self.last_pointer);
+        }
+        if self.dragging {
+            let mut delta = egui::Vec2::ZERO;
+            if let Some(p) = input.pointer.hover_pos() {
+                // the second view doesn't glide, so its pan leaves the velocity at zero
+                if self.panning_b {
+                    self.camera_target_b -= (p - self.last_pointer) / self.zoom_b;
+                } else {
+                    delta = (p - self.last_pointer) / self.zoom;
+                    self.camera_target -= delta;
+                }
+                self.last_pointer = p;
+            }
+            // averaged over the last few frames so one jittery frame doesn't set the throw
+            self.pan_velocity = self.pan_velocity * 0.5 + delta / dt * 0.5;
+            if !input.pointer.secondary_down() {
+                self.dragging = false;
+            }
+        } else if self.camera_goal.is_some() {
+            self.pan_velocity = egui::Vec2::ZERO;
+        } else if self.pan_velocity.length() >= 0.1 {
+            self.camera_target -= self.pan_velocity * dt;
+            self.pan_velocity *= PAN_FRICTION;
         } else {
-            find_object_by_id_mut(&mut o.children, id)
+            self.pan_velocity = egui::Vec2::ZERO;
         }
-    })
-}
 
-fn find_clicked_object(
-    o: &SceneObject,
-    w: egui::Vec2,
-    m: &HashMap<u32, egui::Vec2>,
-) -> Option<u32> {
-    if let Some(&p) = m.get(&o.id) {
-        if (w - p).length() < 20.0 {
-            return Some(o.id);
+        // ease toward the camera goal, or jump straight there
+        let k = if self.smooth_camera {
+            1.0 - (-CAMERA_EASE * dt).exp()
+        } else {
+            1.0
+        };
+        if let Some(goal) = self.camera_goal {
+            self.camera_target += (goal - self.camera_target) * k;
+            if (goal - self.camera_target).length() < 0.5 {
+                self.camera_target = goal;
+                self.camera_goal = None;
+            }
+        }
+        if let Some(zoom) = self.target_zoom {
+            self.zoom += (zoom - self.zoom) * k;
+            if (zoom - self.zoom).abs() < 0.001 {
+                self.zoom = zoom;
+                self.target_zoom = None;
+            }
+        }
+
+        let env = vm::Env {
+            t: self.time_since_last_update,
+            dt,
+            frame: self.frame_count,
+        };
+        if let Some(elapsed) = &mut self.playback {
+            *elapsed += dt;
+            if !play_keyframes(&mut self.scene_objects, *elapsed) {
+                self.playback = None;
+            }
+        } else if let Some(compute) = &mut self.rotation_compute
+            && self.gpu_rotation
+        {
+            animate_on_gpu(
+                &mut self.scene_objects,
+                self.global_speed_multiplier,
+                &env,
+                compute,
+            );
+        } else {
+            animate(&mut self.scene_objects, self.global_speed_multiplier, &env);
+        }
+        if self.record_mode
+            && self.playback.is_none()
+            && self.frame_count.is_multiple_of(RECORD_EVERY)
+        {
+            let t = self.time_since_last_update;
+            visit_mut(&mut self.scene_objects, 0, &mut |o, _| {
+                if o.keyframes.len() == MAX_KEYFRAMES {
+                    o.keyframes.remove(0);
+                }
+                o.keyframes.push((t, o.current_rotation));
+            });
+        }
+        let objects = &mut self.scene_objects;
+        self.active_tweens.retain_mut(|tween| {
+            find_object_by_id_mut(objects, tween.node_id).is_some_and(|o| !tween.step(o, dt))
+        });
+
+        let k = 1.0 - (-COLLAPSE_EASE * dt).exp();
+        let anim = &mut self.collapse_anim;
+        let folded = &self.folded_duplicates;
+        let mut folding = false;
+        visit(&self.scene_objects, 0, &mut |o, _| {
+            if o.children.is_empty() {
+                return;
+            }
+            let shut = o.collapsed || folded.contains_key(&o.id);
+            let target = if shut { 0.0 } else { 1.0 };
+            let open = anim.entry(o.id).or_insert(target);
+            if *open != target {
+                *open += (target - *open) * k;
+                if (target - *open).abs() < 0.01 {
+                    *open = target;
+                }
+                folding = true;
+            }
+        });
+        self.layout_dirty |= folding;
+
+        let flowing: Vec<(u32, u32)> = self
+            .selected_ids
+            .iter()
+            .filter_map(|&id| self.node_index.get(&self.scene_objects, id))
+            .flat_map(|o| o.children.iter().map(|c| (o.id, c.id)))
+            .collect();
+        self.flow_phase.retain(|edge, _| flowing.contains(edge));
+        for edge in flowing {
+            let phase = self.flow_phase.entry(edge).or_insert(0.0);
+            *phase = (*phase + dt * FLOW_SPEED).fract();
         }
+        for progress in self.edge_spawn_progress.values_mut() {
+            *progress = (*progress + dt / EDGE_SPAWN_SECS).min(1.0);
+        }
+        self.edge_spawn_progress.retain(|_, p| *p < 1.0);
+
+        /* ----- reload the scene file when it changes on disk ----- */
+        let changed = self.scene_watch.as_mut().is_some_and(|w| w.poll());
+        if changed && let Some(path) = self.scene_watch.as_ref().map(|w| w.path().to_owned()) {
+            let msg = match self.load_scene(&path) {
+                Ok(()) => "File reloaded".to_owned(),
+                Err(e) => format!("Reload failed: {e}"),
+            };
+            self.toast = Some((msg, input.time));
+        }
+
+        self.handle_keyboard_shortcuts(ctx, &input);
+
+        /* ----- scene tabs ----- */
+        let mut switch_to = None;
+        let mut close = None;
+        egui::TopBottomPanel::top("scene_tabs").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let closable = self.scenes.len() > 1;
+                for (i, tab) in self.scenes.iter_mut().enumerate() {
+                    if self.renaming_scene == Some(i) {
+                        let resp = ui.text_edit_singleline(&mut tab.name);
+                        if resp.lost_focus() {
+                            self.renaming_scene = None;
+                        } else {
+                            resp.request_focus();
+                        }
+                    } else {
+                        let resp = ui.selectable_label(i == self.active_scene, &tab.name);
+                        if resp.double_clicked() {
+                            self.renaming_scene = Some(i);
+                        } else if resp.clicked() {
+                            switch_to = Some(i);
+                        }
+                    }
+                    if closable && ui.small_button("×").clicked() {
+                        close = Some(i);
+                    }
+                    ui.separator();
+                }
+                if ui.button("+").clicked() {
+                    self.scenes
+                        .push(SceneTab::new(&format!("Scene {}", self.scenes.len() + 1)));
+                    switch_to = Some(self.scenes.len() - 1);
+                    // a blank tab has nothing in it yet, so offer something to start from
+                    self.open_template_gallery();
+                }
+            });
+        });
+        if let Some(i) = switch_to {
+            self.switch_scene(i);
+        }
+        if let Some(i) = close {
+            if self.scene_is_empty(i) {
+                self.close_scene(i);
+            } else {
+                self.closing_scene = Some(i);
+            }
+        }
+        if !self.validation_errors.is_empty() {
+            let mut dismiss = false;
+            egui::Window::new("Scene Problems")
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    for e in &self.validation_errors {
+                        ui.colored_label(Color32::YELLOW, format!("⚠ {e}"));
+                    }
+                    dismiss = ui.button("Dismiss").clicked();
+                });
+            if dismiss {
+                self.validation_errors.clear();
+            }
+        }
+        if let Some(i) = self.closing_scene {
+            egui::Window::new("Close scene?")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "\"{}\" is not empty. Close it anyway?",
+                        self.scenes[i].name
+                    ));
+                    ui.horizontal(|ui| {
+                        if ui.button("Close").clicked() {
+                            self.close_scene(i);
+                            self.closing_scene = None;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.closing_scene = None;
+                        }
+                    });
+                });
+        }
+
+        /* ----- toolbar ----- */
+        let layout_settings = (
+            self.max_visible_depth,
+            self.proportional_size,
+            self.layout_mode,
+            self.visible_layers,
+        );
+        let mut search_matches = HashSet::new();
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Path:");
+                        ui.text_edit_singleline(&mut self.scene_path);
+                    });
+                    let path = self.scene_path.clone();
+                    if ui.button("Save Scene").clicked() {
+                        self.scene_status = Some(match self.save_scene(Path::new(&path)) {
+                            Ok(()) => {
+                                if let Err(e) = self.remember_scene(Path::new(&path)) {
+                                    eprintln!("couldn't save recent scenes: {e}");
+                                }
+                                format!("Saved to {path}")
+                            }
+                            Err(e) => format!("Couldn't save: {e}"),
+                        });
+                        if let Some(w) = &mut self.scene_watch
+                            && w.path() == Path::new(&path)
+                        {
+                            w.sync();
+                        }
+                    }
+                    if ui.button("Open Scene").clicked() {
+                        self.scene_status = Some(self.open_scene(Path::new(&path)));
+                    }
+                    let mut open_recent = None;
+                    ui.add_enabled_ui(!self.recent_scenes.is_empty(), |ui| {
+                        ui.menu_button("Recent Scenes", |ui| {
+                            for recent in &self.recent_scenes {
+                                let label = recent.display().to_string();
+                                if ui
+                                    .add_enabled(storage::exists(recent), egui::Button::new(label))
+                                    .on_disabled_hover_text("File not found")
+                                    .clicked()
+                                {
+                                    open_recent = Some(recent.clone());
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+                    if let Some(recent) = open_recent {
+                        self.scene_path = recent.display().to_string();
+                        self.scene_status = Some(self.open_scene(&recent));
+                    }
+                    if let Some(status) = &self.scene_status {
+                        ui.label(status);
+                    }
+                    ui.separator();
+                    if ui.button("New from Template…").clicked() {
+                        self.open_template_gallery();
+                        ui.close_menu();
+                    }
+                    if ui.button("Compare Scenes…").clicked() {
+                        self.compare_open = true;
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Edit", |ui| {
+                    if ui
+                        .add_enabled(!self.undo_stack.is_empty(), egui::Button::new("Undo"))
+                        .clicked()
+                    {
+                        self.undo();
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(!self.redo_stack.is_empty(), egui::Button::new("Redo"))
+                        .clicked()
+                    {
+                        self.redo();
+                        ui.close_menu();
+                    }
+                    if ui.button("Find & Replace…").clicked() {
+                        self.find_replace_open = true;
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    for (label, locked) in [("Lock All", true), ("Unlock All", false)] {
+                        if ui.button(label).clicked() {
+                            self.push_undo(label.to_owned());
+                            visit_mut(&mut self.scene_objects, 0, &mut |o, _| o.locked = locked);
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.menu_button("View", |ui| {
+                    ui.add_enabled(
+                        self.sound.is_some(),
+                        egui::Checkbox::new(&mut self.mute, "Mute sounds"),
+                    );
+                    ui.checkbox(&mut self.show_groups, "Groups");
+                    ui.checkbox(&mut self.smooth_camera, "Smooth camera");
+                    ui.checkbox(&mut self.show_grid, "Grid");
+                    ui.checkbox(&mut self.show_minimap, "Minimap");
+                    ui.add_enabled(
+                        self.glow_available,
+                        egui::Checkbox::new(&mut self.show_glow, "Glow"),
+                    );
+                    ui.checkbox(&mut self.show_arrows, "Arrowheads");
+                    ui.checkbox(&mut self.show_parent_labels, "Show Parent Labels");
+                    ui.checkbox(&mut self.show_health, "Health")
+                        .on_hover_text("Ring each node by how fleshed out it is");
+                    ui.add_enabled(
+                        self.rotation_compute.is_some(),
+                        egui::Checkbox::new(&mut self.gpu_rotation, "GPU Rotation"),
+                    )
+                    .on_disabled_hover_text("Needs an adapter with compute shaders");
+                    ui.menu_button("Split View", |ui| {
+                        for mode in SplitMode::ALL {
+                            ui.radio_value(&mut self.split_mode, mode, format!("{mode:?}"));
+                        }
+                    });
+                    ui.checkbox(&mut self.show_outline, "Outline");
+                    ui.checkbox(&mut self.show_palette, "Palette");
+                    ui.checkbox(&mut self.show_rules, "Rules");
+                    ui.checkbox(&mut self.show_stats, "Statistics");
+                    ui.checkbox(&mut self.preview_3d, "3D Preview");
+                    ui.horizontal(|ui| {
+                        ui.label("FPS");
+                        ui.add(egui::DragValue::new(&mut self.target_fps).range(1..=240));
+                    });
+                    ui.checkbox(&mut self.physics_mode, "Physics");
+                    ui.add_enabled(
+                        self.physics_mode,
+                        Slider::new(&mut self.spring_k, 1.0..=100.0).text("Spring Constant"),
+                    );
+                    ui.menu_button("Color Vision", |ui| {
+                        for mode in AccessibilityMode::ALL {
+                            ui.radio_value(&mut self.accessibility_mode, mode, format!("{mode:?}"));
+                        }
+                    });
+                    ui.checkbox(&mut self.announce_selection, "Announce Selection")
+                        .on_hover_text("Copies the selected node's description to the clipboard");
+                });
+                ui.menu_button("Help", |ui| {
+                    if ui.button("Keyboard Shortcuts").clicked() {
+                        self.show_help = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Renderer Info").clicked() {
+                        self.renderer_info_open = true;
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Tools", |ui| {
+                    if ui.button("Export Metrics CSV…").clicked() {
+                        self.metrics_export_open = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Node Types…").clicked() {
+                        self.node_types_open = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Validate").clicked() {
+                        self.validation_errors = validate_scene(&self.scene_objects, &self.links);
+                        if self.validation_errors.is_empty() {
+                            self.toast = Some(("No problems found".to_owned(), input.time));
+                        }
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    let mut fold = !self.folded_duplicates.is_empty();
+                    if ui.checkbox(&mut fold, "Fold Duplicates").changed() {
+                        if fold {
+                            self.fold_duplicates();
+                            if self.folded_duplicates.is_empty() {
+                                self.toast =
+                                    Some(("No duplicate branches found".to_owned(), input.time));
+                            }
+                        } else {
+                            self.unfold_all();
+                        }
+                    }
+                    if ui
+                        .add_enabled(
+                            !self.folded_duplicates.is_empty(),
+                            egui::Button::new("Unfold All"),
+                        )
+                        .clicked()
+                    {
+                        self.unfold_all();
+                        ui.close_menu();
+                    }
+                });
+                ui.add_enabled(
+                    self.show_grid,
+                    egui::Checkbox::new(&mut self.snap_to_grid, "Snap"),
+                );
+                if ui.button("Fit").clicked() {
+                    self.fit_requested = true;
+                }
+
+                ui.separator();
+                let mut limited = self.max_visible_depth.is_some();
+                let mut depth = self.max_visible_depth.unwrap_or(20);
+                ui.checkbox(&mut limited, "Depth limit");
+                ui.add_enabled(limited, Slider::new(&mut depth, 0..=20));
+                self.max_visible_depth = limited.then_some(depth);
+
+                ui.checkbox(&mut self.proportional_size, "Proportional Size");
+
+                ui.separator();
+                for layer in 0..8 {
+                    let bit = 1 << layer;
+                    let on = self.visible_layers & bit != 0;
+                    if ui.selectable_label(on, format!("L{layer}")).clicked() {
+                        self.visible_layers ^= bit;
+                    }
+                }
+
+                ui.separator();
+                ui.add(Slider::new(&mut self.global_speed_multiplier, 0.0..=5.0).text("Speed"));
+                ui.toggle_value(&mut self.record_mode, "⏺ Record")
+                    .on_hover_text("Sample every node's rotation into its keyframes");
+                let recorded = flatten(&self.scene_objects)
+                    .iter()
+                    .any(|o| !o.keyframes.is_empty());
+                let play = if self.playback.is_some() {
+                    "Stop Playback"
+                } else {
+                    "Play Recording"
+                };
+                if ui.add_enabled(recorded, egui::Button::new(play)).clicked() {
+                    self.playback = match self.playback {
+                        Some(_) => None,
+                        None => Some(0.0),
+                    };
+                }
+                if ui
+                    .add_enabled(recorded, egui::Button::new("Clear Recording"))
+                    .clicked()
+                {
+                    visit_mut(&mut self.scene_objects, 0, &mut |o, _| o.keyframes.clear());
+                    self.playback = None;
+                }
+
+                ui.separator();
+                egui::ComboBox::from_label("Layout")
+                    .selected_text(format!("{:?}", self.layout_mode))
+                    .show_ui(ui, |ui| {
+                        for mode in [
+                            LayoutMode::Tree,
+                            LayoutMode::Circular,
+                            LayoutMode::Treemap,
+                            LayoutMode::ForceDirected,
+                            LayoutMode::FoldedRows,
+                        ] {
+                            ui.selectable_value(&mut self.layout_mode, mode, format!("{mode:?}"));
+                        }
+                    });
+                if self.layout_mode == LayoutMode::ForceDirected
+                    && ui.button("Reset Force Layout").clicked()
+                {
+                    self.reset_force_layout();
+                }
+                if ui
+                    .button("Reset All Overrides")
+                    .on_hover_text("Put every dragged node back on its layout slot")
+                    .clicked()
+                {
+                    self.confirm_reset_overrides = true;
+                }
+
+                ui.separator();
+                egui::ComboBox::from_label("Connectors")
+                    .selected_text(format!("{:?}", self.connector_style))
+                    .show_ui(ui, |ui| {
+                        for style in [
+                            ConnectorStyle::Diagonal,
+                            ConnectorStyle::Orthogonal,
+                            ConnectorStyle::Bezier,
+                        ] {
+                            ui.selectable_value(
+                                &mut self.connector_style,
+                                style,
+                                format!("{style:?}"),
+                            );
+                        }
+                    });
+
+                ui.separator();
+                let snap_label = |snap: Option<f32>| match snap {
+                    Some(step) => format!("{step}°"),
+                    None => "Free".to_owned(),
+                };
+                egui::ComboBox::from_label("Snap Rotation")
+                    .selected_text(snap_label(self.snap_rotation))
+                    .show_ui(ui, |ui| {
+                        for snap in [None, Some(15.0), Some(30.0), Some(45.0), Some(90.0)] {
+                            ui.selectable_value(&mut self.snap_rotation, snap, snap_label(snap));
+                        }
+                    });
+
+                ui.separator();
+                egui::ComboBox::from_label("Theme")
+                    .selected_text(format!("{:?}", self.theme))
+                    .show_ui(ui, |ui| {
+                        for theme in [Theme::Dark, Theme::Light, Theme::Custom] {
+                            if ui
+                                .selectable_value(&mut self.theme, theme, format!("{theme:?}"))
+                                .clicked()
+                                && theme == Theme::Custom
+                            {
+                                self.theme_editor_open = true;
+                            }
+                        }
+                    });
+                if self.theme == Theme::Custom && ui.button("Theme Editor").clicked() {
+                    self.theme_editor_open = true;
+                }
+
+                ui.separator();
+                if ui
+                    .add_enabled(
+                        !self.selected_ids.is_empty(),
+                        egui::Button::new("Create Group"),
+                    )
+                    .clicked()
+                {
+                    const PALETTE: [Color32; 4] = [
+                        Color32::from_rgb(80, 160, 255),
+                        Color32::from_rgb(255, 170, 60),
+                        Color32::from_rgb(90, 200, 120),
+                        Color32::from_rgb(230, 90, 160),
+                    ];
+                    self.next_group_id += 1;
+                    self.groups.push(NodeGroup {
+                        id: self.next_group_id,
+                        label: format!("Group {}", self.next_group_id),
+                        node_ids: self.selected_ids.clone(),
+                        color: PALETTE[self.groups.len() % PALETTE.len()],
+                    });
+                    self.show_groups = true;
+                }
+
+                ui.separator();
+                let mode = self.link_mode;
+                for (m, label) in [
+                    (LinkMode::Add, "Add Link"),
+                    (LinkMode::Remove, "Remove Link"),
+                ] {
+                    if ui.selectable_label(mode == m, label).clicked() {
+                        self.link_mode = if mode == m { LinkMode::Off } else { m };
+                        self.link_source = None;
+                    }
+                }
+
+                ui.separator();
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.search_query)
+                        .hint_text("Search")
+                        .desired_width(120.0),
+                );
+                let query = self.search_query.to_lowercase();
+                if !query.is_empty() {
+                    search_matches = flatten(&self.scene_objects)
+                        .iter()
+                        .filter(|o| o.text.to_lowercase().contains(&query))
+                        .map(|o| o.id)
+                        .collect();
+                    ui.label(format!("{} matches", search_matches.len()));
+                }
+
+                ui.separator();
+                if ui.button("Import from DOT").clicked() {
+                    self.dot_import_open = true;
+                }
+                if ui.button("Export to DOT").clicked() {
+                    let dot = dot_export::export(&self.scene_objects);
+                    ctx.output_mut(|o| o.commands.push(OutputCommand::CopyText(dot.clone())));
+                    self.dot_export = Some(dot);
+                    self.dot_export_status = Some("Copied to clipboard".into());
+                }
+                let laid_out = matches!(
+                    self.layout_mode,
+                    LayoutMode::Tree | LayoutMode::Circular | LayoutMode::FoldedRows
+                );
+                if ui
+                    .add_enabled(laid_out, egui::Button::new("Export SVG"))
+                    .on_disabled_hover_text("Needs the Tree, Circular or FoldedRows layout")
+                    .clicked()
+                    && let Some(layout) = &self.layout_cache
+                {
+                    let svg = svg_export::export_svg(
+                        &self.scene_objects,
+                        layout,
+                        self.canvas_rect.size(),
+                    );
+                    let path = Path::new(&self.scene_path).with_extension("svg");
+                    let msg = match storage::write(&path, &svg) {
+                        Ok(()) => format!("Saved to {}", path.display()),
+                        Err(e) => format!("SVG export failed: {e}"),
+                    };
+                    self.toast = Some((msg, input.time));
+                }
+            });
+        });
+
+        if layout_settings
+            != (
+                self.max_visible_depth,
+                self.proportional_size,
+                self.layout_mode,
+                self.visible_layers,
+            )
+        {
+            self.layout_dirty = true;
+        }
+
+        /* ----- status bar ----- */
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            let mono = |s: String| egui::RichText::new(s).monospace().size(12.0);
+            ui.horizontal(|ui| {
+                if self.lasso_mode {
+                    ui.label(mono("Lasso Mode (drag to select, L to exit)".into()).strong());
+                    ui.separator();
+                }
+                match input.pointer.hover_pos() {
+                    Some(p) => {
+                        let w = self.screen_to_world(p);
+                        ui.label(mono(format!("World: ({:.0}, {:.0})", w.x, w.y)));
+                    }
+                    None => {
+                        ui.label(mono("World: —".into()));
+                    }
+                }
+                ui.separator();
+                ui.label(mono(format!("Nodes: {}", self.total_node_count())));
+                let selected_depth = self
+                    .active_settings_id
+                    .and_then(|id| depth(&self.scene_objects, id));
+                ui.label(mono(match selected_depth {
+                    Some(d) => format!("Depth: {d}"),
+                    None => "Depth: —".into(),
+                }));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.label(mono(format!("Zoom: {:.0}%", self.zoom * 100.0)));
+                    ui.separator();
+                    let last = |stack: &[(SceneSnapshot, String)]| {
+                        stack.last().map_or("—".to_owned(), |(_, d)| d.clone())
+                    };
+                    ui.label(mono(format!("Redo: {}", last(&self.redo_stack))));
+                    ui.label(mono(format!("Undo: {}", last(&self.undo_stack))));
+                    if let Some((text, _)) = &self.status_flash {
+                        ui.separator();
+                        ui.label(mono(text.clone()).color(Color32::YELLOW));
+                    }
+                });
+            });
+        });
+
+        /* ----- palette panel ----- */
+        if self.show_palette {
+            let mut changed = false;
+            let mut remove = None;
+            egui::SidePanel::left("palette")
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.heading("Palette");
+                    for (i, (name, color)) in self.palette.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            changed |= color_edit(ui, color);
+                            ui.label(name.as_str());
+                            if ui.small_button("✖").clicked() {
+                                remove = Some(i);
+                            }
+                        });
+                    }
+                    if self.palette.is_empty() {
+                        ui.weak("Add colors with Set from Node in the inspector");
+                    }
+                });
+            if let Some(i) = remove {
+                self.palette.remove(i);
+                changed = true;
+            }
+            if changed && let Err(e) = self.save_config() {
+                self.toast = Some((format!("Couldn't save palette: {e}"), input.time));
+            }
+        }
+
+        /* ----- rules ----- */
+        let (warnings, violations) = check_rules(&self.rules, &self.scene_objects);
+        self.warning_nodes = warnings;
+        if self.show_rules {
+            let mut remove = None;
+            egui::SidePanel::left("rules")
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.heading("Rules");
+                    for (i, (rule, count)) in self.rules.iter_mut().zip(&violations).enumerate() {
+                        ui.push_id(i, |ui| {
+                            ui.horizontal(|ui| {
+                                egui::ComboBox::from_id_salt("condition")
+                                    .selected_text(rule.condition.label())
+                                    .show_ui(ui, |ui| {
+                                        for c in RuleCondition::ALL {
+                                            let same = std::mem::discriminant(&c)
+                                                == std::mem::discriminant(&rule.condition);
+                                            if ui.selectable_label(same, c.label()).clicked()
+                                                && !same
+                                            {
+                                                rule.condition = c;
+                                            }
+                                        }
+                                    });
+                                match &mut rule.condition {
+                                    RuleCondition::NameLongerThan(n)
+                                    | RuleCondition::ChildCountGreaterThan(n) => {
+                                        ui.add(egui::DragValue::new(n));
+                                    }
+                                    RuleCondition::DepthGreaterThan(n) => {
+                                        ui.add(egui::DragValue::new(n));
+                                    }
+                                    RuleCondition::RotationSpeedAbove(v) => {
+                                        ui.add(egui::DragValue::new(v).speed(1.0).suffix("°/s"));
+                                    }
+                                    RuleCondition::HasNoTags => {}
+                                }
+                                if ui.small_button("✖").clicked() {
+                                    remove = Some(i);
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut rule.message)
+                                        .hint_text("message")
+                                        .desired_width(140.0),
+                                );
+                                let text = format!("{count} nodes");
+                                if *count > 0 {
+                                    ui.colored_label(BadgeKind::Warning.color(), text);
+                                } else {
+                                    ui.weak(text);
+                                }
+                            });
+                        });
+                        ui.separator();
+                    }
+                    if self.rules.is_empty() {
+                        ui.weak("Nodes breaking a rule get a ⚠ badge");
+                    }
+                    if ui.button("Add Rule").clicked() {
+                        self.rules.push(NodeRule {
+                            condition: RuleCondition::HasNoTags,
+                            message: String::new(),
+                        });
+                    }
+                });
+            if let Some(i) = remove {
+                self.rules.remove(i);
+            }
+        }
+
+        /* ----- outline table ----- */
+        if self.show_outline {
+            let mut rows = vec![];
+            // parents come before their children, so each child's slot is known by then
+            let mut slots = HashMap::new();
+            visit(&self.scene_objects, 0, &mut |o, depth| {
+                for (i, c) in o.children.iter().enumerate() {
+                    slots.insert(c.id, (o.id, i));
+                }
+                rows.push(OutlineRow {
+                    id: o.id,
+                    name: o.text.lines().next().unwrap_or_default().to_owned(),
+                    shape: o.shape,
+                    layer: o.layer,
+                    depth,
+                    children: o.children.len(),
+                    slot: slots.get(&o.id).copied(),
+                });
+            });
+            let mut dropped = None;
+            let mut sort = self.outline_sort;
+            let mut clicked = None;
+            egui::SidePanel::left("outline")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    TableBuilder::new(ui)
+                        .striped(true)
+                        .resizable(true)
+                        .sense(egui::Sense::click())
+                        .columns(Column::auto().resizable(true), OutlineColumn::ALL.len() - 1)
+                        .column(Column::remainder())
+                        .header(20.0, |mut header| {
+                            for (column, title) in OutlineColumn::ALL {
+                                header.col(|ui| {
+                                    let arrow = match sort {
+                                        (c, true) if c == column => " ⏶",
+                                        (c, false) if c == column => " ⏷",
+                                        _ => "",
+                                    };
+                                    if ui
+                                        .selectable_label(
+                                            sort.0 == column,
+                                            format!("{title}{arrow}"),
+                                        )
+                                        .clicked()
+                                    {
+                                        sort = (column, sort.0 != column || !sort.1);
+                                    }
+                                });
+                            }
+                        })
+                        .body(|body| {
+                            // only the rows in view are laid out, so big scenes stay cheap
+                            sort_outline(&mut rows, sort.0, sort.1);
+                            body.rows(18.0, rows.len(), |mut row| {
+                                let r = &rows[row.index()];
+                                row.set_selected(self.active_settings_id == Some(r.id));
+                                row.col(|ui| {
+                                    ui.label(r.id.to_string());
+                                });
+                                row.col(|ui| {
+                                    // drop on the canvas to place it, or on a node to reparent
+                                    let drag_id = egui::Id::new(("outline_drag", r.id));
+                                    ui.dnd_drag_source(drag_id, r.id, |ui| ui.label(&r.name));
+                                });
+                                row.col(|ui| {
+                                    ui.label(format!("{:?}", r.shape));
+                                });
+                                row.col(|ui| {
+                                    ui.label(r.layer.to_string());
+                                });
+                                row.col(|ui| {
+                                    ui.label(r.depth.to_string());
+                                });
+                                row.col(|ui| {
+                                    ui.label(r.children.to_string());
+                                });
+                                let resp = row.response();
+                                if resp.clicked() {
+                                    clicked = Some(r.id);
+                                }
+                                // other rows take drops; the canvas takes them too
+                                if let Some(dragged) = resp.dnd_hover_payload::<u32>()
+                                    && *dragged != r.id
+                                    && let Some(pointer) = resp.ctx.pointer_latest_pos()
+                                {
+                                    let zone = OutlineDrop::at(resp.rect, pointer.y);
+                                    let painter = resp.ctx.layer_painter(resp.layer_id);
+                                    let color = resp.ctx.style().visuals.selection.bg_fill;
+                                    let stroke = Stroke::new(2.0, color);
+                                    match zone {
+                                        OutlineDrop::Before => {
+                                            painter.hline(
+                                                resp.rect.x_range(),
+                                                resp.rect.top(),
+                                                stroke,
+                                            );
+                                        }
+                                        OutlineDrop::After => {
+                                            painter.hline(
+                                                resp.rect.x_range(),
+                                                resp.rect.bottom(),
+                                                stroke,
+                                            );
+                                        }
+                                        OutlineDrop::Onto => {
+                                            painter.rect_filled(
+                                                resp.rect,
+                                                2.0,
+                                                color.gamma_multiply(0.3),
+                                            );
+                                        }
+                                    }
+                                    if let Some(dragged) = resp.dnd_release_payload::<u32>() {
+                                        dropped = Some((*dragged, r.id, r.slot, zone));
+                                    }
+                                }
+                            });
+                        });
+                });
+            self.outline_sort = sort;
+            if let Some(id) = clicked {
+                self.active_settings_id = Some(id);
+            }
+            if let Some((node_id, target, slot, zone)) = dropped {
+                let from = rows.iter().find(|r| r.id == node_id).and_then(|r| r.slot);
+                match (zone, slot) {
+                    (OutlineDrop::Onto, _) => self.requests.push(EditorRequest::Reparent {
+                        node_id,
+                        new_parent_id: Some(target),
+                    }),
+                    // roots have no order to keep, so the node just becomes one
+                    (_, None) => {
+                        if from.is_some() {
+                            self.requests.push(EditorRequest::Reparent {
+                                node_id,
+                                new_parent_id: None,
+                            });
+                        }
+                    }
+                    (_, Some((parent_id, index))) => {
+                        let mut new_index = index + usize::from(zone == OutlineDrop::After);
+                        match from {
+                            // moving down a list shifts the rows below it up by one
+                            Some((p, i)) if p == parent_id => {
+                                if i < new_index {
+                                    new_index -= 1;
+                                }
+                            }
+                            // a move elsewhere lands at the end first, then into place
+                            _ => self.requests.push(EditorRequest::Reparent {
+                                node_id,
+                                new_parent_id: Some(parent_id),
+                            }),
+                        }
+                        self.requests.push(EditorRequest::MoveChild {
+                            parent_id,
+                            child_id: node_id,
+                            new_index,
+                        });
+                    }
+                }
+            }
+        }
+
+        /* ----- watch panel: live values of chosen nodes ----- */
+        let objects = &self.scene_objects;
+        self.watch_list
+            .retain(|&id| find_object_by_id(objects, id).is_some());
+        if !self.watch_list.is_empty() {
+            let mut unwatch = None;
+            egui::SidePanel::right("watch")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    ui.heading("Watch");
+                    TableBuilder::new(ui)
+                        .striped(true)
+                        .columns(Column::auto(), 4)
+                        .column(Column::remainder())
+                        .header(20.0, |mut header| {
+                            for title in ["Name", "Rotation", "Color", "Speed", ""] {
+                                header.col(|ui| {
+                                    ui.strong(title);
+                                });
+                            }
+                        })
+                        .body(|mut body| {
+                            for &id in &self.watch_list {
+                                let Some(o) = self.node_index.get(&self.scene_objects, id) else {
+                                    continue;
+                                };
+                                body.row(18.0, |mut row| {
+                                    row.col(|ui| {
+                                        ui.label(o.text.lines().next().unwrap_or_default());
+                                    });
+                                    row.col(|ui| {
+                                        ui.monospace(format!("{:.1}°", o.current_rotation));
+                                    });
+                                    row.col(|ui| {
+                                        egui::color_picker::show_color(
+                                            ui,
+                                            o.color,
+                                            egui::Vec2::new(24.0, 12.0),
+                                        );
+                                    });
+                                    row.col(|ui| {
+                                        ui.monospace(format!("{:.2}", o.rotation_speed));
+                                    });
+                                    row.col(|ui| {
+                                        if ui.small_button("×").clicked() {
+                                            unwatch = Some(id);
+                                        }
+                                    });
+                                });
+                            }
+                        });
+                });
+            if let Some(id) = unwatch {
+                self.watch_list.retain(|&w| w != id);
+            }
+        }
+
+        // nodes from a loaded scene, a paste or an undo only carry the path
+        let textures = &mut self.textures;
+        visit_mut(&mut self.scene_objects, 0, &mut |o, _| {
+            if o.texture.is_none()
+                && let Some(path) = &o.texture_path
+            {
+                o.texture = node_texture(textures, ctx, path, false).ok();
+            }
+        });
+
+        /* ----- drawing canvas ----- */
+        let mut rename_anchor = None;
+        let mut drag_snapshot = None;
+        let mut expand_to = None;
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let (rect, rect_b) = self.split_mode.split(ui.max_rect());
+            if self.layout_mode == LayoutMode::FoldedRows
+                && self.canvas_rect.width() != rect.width()
+            {
+                // rows are spread across the canvas, so they follow its width
+                self.layout_dirty = true;
+            }
+            self.canvas_rect = rect;
+            self.viewport_b = rect_b;
+            let resp = ui.allocate_rect(rect, egui::Sense::click_and_drag());
+            let painter = &ui.painter_at(rect);
+            let background = match self.theme {
+                Theme::Custom => self.custom_theme.background,
+                _ => ui.visuals().panel_fill,
+            };
+            painter.rect_filled(rect, 0.0, background);
+            let sizes = self
+                .proportional_size
+                .then(|| proportional_sizes(&self.scene_objects));
+            let lc = LayoutCtx {
+                max_depth: self.max_visible_depth,
+                sizes: sizes.as_ref(),
+                visible_layers: self.visible_layers,
+                collapse: &self.collapse_anim,
+            };
+            let to_screen = |p: egui::Vec2| {
+                let offset = rect.min.to_vec2() + rect.size() / 2.0;
+                let v = offset + (p - self.camera_target) * self.zoom;
+                Pos2::new(v.x, v.y)
+            };
+            let to_world = |p: Pos2| {
+                (p.to_vec2() - rect.min.to_vec2() - rect.size() / 2.0) / self.zoom
+                    + self.camera_target
+            };
+
+            if self.show_grid {
+                let stroke = ui.visuals().widgets.noninteractive.bg_stroke;
+                let (lo, hi) = (to_world(rect.min), to_world(rect.max));
+                let mut x = (lo.x / GRID_SPACING).floor() * GRID_SPACING;
+                while x <= hi.x {
+                    let sx = to_screen(egui::Vec2::new(x, 0.0)).x;
+                    painter.vline(sx, rect.y_range(), stroke);
+                    x += GRID_SPACING;
+                }
+                let mut y = (lo.y / GRID_SPACING).floor() * GRID_SPACING;
+                while y <= hi.y {
+                    let sy = to_screen(egui::Vec2::new(0.0, y)).y;
+                    painter.hline(rect.x_range(), sy, stroke);
+                    y += GRID_SPACING;
+                }
+            }
+
+            let mut treemap: HashMap<u32, Rect> = HashMap::new();
+            let mut treemap_layout: HashMap<u32, egui::Vec2> = HashMap::new();
+            let layout = match self.layout_mode {
+                // these only depend on the scene, so they're kept between frames
+                LayoutMode::Tree | LayoutMode::Circular | LayoutMode::FoldedRows => {
+                    if self.layout_dirty || self.layout_cache.is_none() {
+                        let mut m = HashMap::new();
+                        if self.layout_mode == LayoutMode::Circular {
+                            layout_circular_roots(&self.scene_objects, &mut m, &lc);
+                        } else if self.layout_mode == LayoutMode::FoldedRows {
+                            layout_folded_rows(&self.scene_objects, rect.width(), &mut m, &lc);
+                        } else {
+                            let mut cy = 100.0;
+                            for o in &self.scene_objects {
+                                layout_recursive(o, 200.0, cy, &mut cy, &mut m, 0, None, &lc);
+                            }
+                        }
+                        visit(&self.scene_objects, 0, &mut |o, _| {
+                            if let Some(p) = o.position_override
+                                && let Some(slot) = m.get_mut(&o.id)
+                            {
+                                *slot = p;
+                            }
+                        });
+                        self.layout_cache = Some(m);
+                        self.layout_dirty = false;
+                    }
+                    self.layout_cache.as_ref().unwrap_or(&treemap_layout)
+                }
+                // tiles follow the panel rect and camera, so this one is rebuilt every frame
+                LayoutMode::Treemap => {
+                    layout_treemap(&self.scene_objects, rect, &mut treemap, 0, None, &lc);
+                    treemap_layout
+                        .extend(treemap.iter().map(|(&id, r)| (id, to_world(r.center()))));
+                    &treemap_layout
+                }
+                // integrated every frame rather than cached, cooling as it goes
+                LayoutMode::ForceDirected => {
+                    if self.layout_dirty {
+                        self.fd_cooling = 1.0;
+                        // the tree layouts rebuild from scratch when switched back to
+                        self.layout_cache = None;
+                        self.layout_dirty = false;
+                    }
+                    let mut graph = ForceGraph::default();
+                    graph.collect(&self.scene_objects, &lc, None, 0, None);
+                    step_force_layout(&graph, &mut self.fd_positions, self.fd_cooling);
+                    self.fd_cooling *= (-FD_COOLING_RATE * dt).exp();
+                    &self.fd_positions
+                }
+            };
+
+            let layout = if self.physics_mode && self.layout_mode != LayoutMode::Treemap {
+                let locked: HashSet<u32> = flatten(&self.scene_objects)
+                    .into_iter()
+                    .filter(|o| o.locked)
+                    .map(|o| o.id)
+                    .collect();
+                step_springs(
+                    layout,
+                    &mut self.physics_pos,
+                    &mut self.velocities,
+                    &locked,
+                    self.spring_k,
+                    dt,
+                );
+                &self.physics_pos
+            } else {
+                self.physics_pos.clear();
+                self.velocities.clear();
+                layout
+            };
+
+            // 3D preview swaps in projected positions and sizes, so drawing and
+            // picking below work on what is actually on screen
+            let mut projected = HashMap::new();
+            let mut projected_sizes = HashMap::new();
+            if self.preview_3d && self.layout_mode != LayoutMode::Treemap {
+                for o in flatten(&self.scene_objects) {
+                    if let Some(&p) = layout.get(&o.id) {
+                        let (q, scale) = project_3d(p, o.z_offset, self.camera_target);
+                        projected.insert(o.id, q);
+                        projected_sizes.insert(o.id, node_size(sizes.as_ref(), o.id) * scale);
+                    }
+                }
+            }
+            let (layout, sizes) = if projected.is_empty() {
+                (layout, sizes)
+            } else {
+                (&projected, Some(projected_sizes))
+            };
+
+            let parent_names = self.show_parent_labels.then(|| {
+                let mut names = HashMap::new();
+                visit(&self.scene_objects, 0, &mut |o, _| {
+                    let name = o.text.lines().next().unwrap_or_default();
+                    for c in &o.children {
+                        names.insert(c.id, name.to_owned());
+                    }
+                });
+                names
+            });
+            let glows = RefCell::new(vec![]);
+            let dc = DrawCtx {
+                painter,
+                objects: &self.scene_objects,
+                index: &self.node_index,
+                layout,
+                to_screen: &to_screen,
+                max_depth: self.max_visible_depth,
+                sizes: sizes.as_ref(),
+                selected: &self.selected_ids,
+                active: self.active_settings_id,
+                pulse_phase: self.pulse_phase,
+                renaming: self.renaming_node,
+                connector: self.connector_style,
+                accessibility: self.accessibility_mode,
+                label_color: ui.visuals().text_color(),
+                flow: &self.flow_phase,
+                spawn: &self.edge_spawn_progress,
+                links: &self.links,
+                search: (!self.search_query.is_empty()).then_some(&search_matches),
+                time: self.time_since_last_update,
+                visible_layers: self.visible_layers,
+                collapse: &self.collapse_anim,
+                folded: &self.folded_duplicates,
+                deleting: &self.deleting_nodes,
+                elbows: &self.edge_elbow_offset,
+                zoom: self.zoom,
+                snap_rotation: self.snap_rotation,
+                show_arrows: self.show_arrows,
+                parent_names: parent_names.as_ref(),
+                show_health: self.show_health,
+                warnings: &self.warning_nodes,
+                glows: (self.show_glow && self.glow_available).then_some(&glows),
+            };
+            // the glows are only known once the nodes are drawn, but go underneath
+            // them, so their slot is taken first and filled in afterwards
+            let glow_slot = painter.add(Shape::Noop);
+            if self.layout_mode == LayoutMode::Treemap {
+                draw_treemap(&dc, &self.scene_objects, &treemap);
+            } else {
+                draw_groups(&dc, &self.groups);
+                for o in &self.scene_objects {
+                    draw_world(&dc, o, 0, 1.0);
+                }
+                draw_group_links(&dc, &self.groups, &self.group_links);
+            }
+            // same scene and layout through the second camera; tiles are laid
+            // out in screen space, so the treemap is left to the main view
+            if let Some(rect_b) = rect_b {
+                let painter_b = &ui.painter_at(rect_b);
+                painter_b.rect_filled(rect_b, 0.0, background);
+                if self.layout_mode != LayoutMode::Treemap {
+                    let to_screen_b =
+                        |p: egui::Vec2| rect_b.center() + (p - self.camera_target_b) * self.zoom_b;
+                    let dc_b = dc.through(painter_b, &to_screen_b, self.zoom_b);
+                    draw_groups(&dc_b, &self.groups);
+                    for o in &self.scene_objects {
+                        draw_world(&dc_b, o, 0, 1.0);
+                    }
+                    draw_group_links(&dc_b, &self.groups, &self.group_links);
+                }
+                let stroke = ui.visuals().widgets.noninteractive.bg_stroke;
+                painter_b.rect_stroke(rect_b, 0.0, stroke, egui::StrokeKind::Inside);
+            }
+            let glows = glows.take();
+            if !glows.is_empty() {
+                painter.set(glow_slot, wgpu_glow::callback(rect, glows));
+            }
+            if let Some(src) = self.link_source
+                && let Some(&p) = layout.get(&src)
+            {
+                let r = node_size(sizes.as_ref(), src) * 0.8;
+                painter.circle_stroke(to_screen(p), r, Stroke::new(2.0, LINK_COLOR));
+            }
+
+            rename_anchor = self
+                .renaming_node
+                .and_then(|id| self.node_index.get(&self.scene_objects, id))
+                .and_then(|o| {
+                    let center = if o.pinned_to_screen {
+                        o.screen_pos
+                    } else {
+                        to_screen(*layout.get(&o.id)?)
+                    };
+                    Some(Pos2::new(
+                        center.x,
+                        center.y + node_size(sizes.as_ref(), o.id) * 0.65,
+                    ))
+                });
+
+            let hit = HitCtx {
+                sizes: sizes.as_ref(),
+                zoom: self.zoom,
+                deleting: &self.deleting_nodes,
+                snap_rotation: self.snap_rotation,
+                time: self.time_since_last_update,
+            };
+
+            /* elbow grips: drag an orthogonal connector's vertical segment sideways */
+            let handles = if self.connector_style == ConnectorStyle::Orthogonal
+                && self.layout_mode != LayoutMode::Treemap
+                && !self.preview_3d
+            {
+                elbow_handles(
+                    &self.scene_objects,
+                    layout,
+                    &to_screen,
+                    &self.edge_elbow_offset,
+                    self.zoom,
+                )
+            } else {
+                vec![]
+            };
+            for &(_, p) in &handles {
+                let grip = Rect::from_center_size(p, egui::Vec2::splat(ELBOW_HANDLE));
+                painter.rect_filled(grip, 1.0, Color32::GRAY);
+            }
+            let handle_at = |pos: Pos2| {
+                handles
+                    .iter()
+                    .find(|&&(_, p)| {
+                        Rect::from_center_size(p, egui::Vec2::splat(ELBOW_HANDLE + 4.0))
+                            .contains(pos)
+                    })
+                    .map(|&(edge, _)| edge)
+            };
+            let on_handle = input.pointer.interact_pos().and_then(handle_at);
+            if resp.double_clicked()
+                && let Some(edge) = on_handle
+            {
+                self.edge_elbow_offset.remove(&edge);
+            }
+            if resp.drag_started_by(egui::PointerButton::Primary)
+                && let Some(edge) = on_handle
+            {
+                self.dragging_elbow = Some((edge, false));
+            }
+            if let Some((edge, moved)) = &mut self.dragging_elbow {
+                let dx = resp.drag_delta().x / self.zoom;
+                if dx != 0.0 {
+                    *self.edge_elbow_offset.entry(*edge).or_default() += dx;
+                    *moved = true;
+                }
+                if resp.drag_stopped() {
+                    // a grab without a move puts the elbow back where the layout wants it
+                    if !*moved {
+                        self.edge_elbow_offset.remove(edge);
+                    }
+                    self.dragging_elbow = None;
+                }
+            }
+
+            /* double-click: zoom to a node, or fit the whole scene from empty space */
+            if resp.double_clicked()
+                && on_handle.is_none()
+                && self.layout_mode != LayoutMode::Treemap
+                && let Some(pos) = input.pointer.interact_pos()
+            {
+                let world = to_world(pos);
+                match self
+                    .scene_objects
+                    .iter()
+                    .find_map(|o| find_clicked_object(o, world, layout, &hit))
+                    .and_then(|id| layout.get(&id))
+                {
+                    Some(&p) => {
+                        self.camera_goal = Some(p);
+                        self.target_zoom = Some(FOCUS_ZOOM);
+                    }
+                    None => self.fit_requested = true,
+                }
+            }
+            if std::mem::take(&mut self.fit_requested)
+                && self.layout_mode != LayoutMode::Treemap
+                && let Some(bounds) = layout
+                    .values()
+                    .map(|p| Rect::from_center_size(Pos2::new(p.x, p.y), egui::Vec2::ZERO))
+                    .reduce(|a, b| a.union(b))
+            {
+                // leave room for the nodes themselves and their labels
+                const MARGIN: f32 = 120.0;
+                let view = rect.size();
+                let zoom = (view.x / (bounds.width() + MARGIN))
+                    .min(view.y / (bounds.height() + MARGIN))
+                    .clamp(MIN_ZOOM, MAX_ZOOM);
+                self.camera_goal = Some(bounds.center().to_vec2());
+                self.target_zoom = Some(zoom);
+            }
+            if std::mem::take(&mut self.center_requested) && self.layout_mode != LayoutMode::Treemap
+            {
+                let active = self
+                    .active_settings_id
+                    .and_then(|id| layout.get(&id))
+                    .copied();
+                let center = active.or_else(|| {
+                    layout
+                        .values()
+                        .map(|p| Rect::from_center_size(p.to_pos2(), egui::Vec2::ZERO))
+                        .reduce(|a, b| a.union(b))
+                        .map(|bounds| bounds.center().to_vec2())
+                });
+                if let Some(center) = center {
+                    self.camera_goal = Some(center);
+                }
+            }
+            if let Some(id) = self.zoom_to_subtree.take()
+                && self.layout_mode != LayoutMode::Treemap
+                && let Some(obj) = self.node_index.get(&self.scene_objects, id)
+                && let Some(bounds) = subtree_bounding_box(obj, layout, NODE_SIZE)
+            {
+                let view = rect.size();
+                let zoom = (view.x / bounds.width())
+                    .min(view.y / bounds.height())
+                    .clamp(MIN_ZOOM, MAX_ZOOM);
+                self.camera_goal = Some(bounds.center().to_vec2());
+                self.target_zoom = Some(zoom);
+            }
+
+            /* comment bubble and info tooltip for the hovered node */
+            let hovered = input.pointer.hover_pos().and_then(|pos| {
+                flatten(&self.scene_objects)
+                    .into_iter()
+                    .filter_map(|o| {
+                        let center = if o.pinned_to_screen {
+                            o.screen_pos
+                        } else {
+                            to_screen(*layout.get(&o.id)?)
+                        };
+                        Some((o.id, center.distance(pos)))
+                    })
+                    .filter(|&(_, d)| d <= HOVER_RADIUS)
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(id, _)| id)
+            });
+            // leaving a node's radius resets its timer
+            self.hover_timers.retain(|&id, _| Some(id) == hovered);
+            if let Some(id) = hovered {
+                *self.hover_timers.entry(id).or_default() += dt;
+            }
+            if let Some(obj) = hovered.and_then(|id| self.node_index.get(&self.scene_objects, id)) {
+                if !obj.comment.is_empty()
+                    && let Some(&p) = layout.get(&obj.id)
+                {
+                    draw_comment_bubble(painter, to_screen(p), &obj.comment);
+                }
+                if self
+                    .hover_timers
+                    .get(&obj.id)
+                    .is_some_and(|&t| t >= TOOLTIP_DELAY)
+                {
+                    egui::show_tooltip_at_pointer(
+                        ctx,
+                        ui.layer_id(),
+                        egui::Id::new("node_tooltip"),
+                        |ui| {
+                            egui::Grid::new("node_tooltip_grid").show(ui, |ui| {
+                                ui.label("ID");
+                                ui.label(obj.id.to_string());
+                                ui.end_row();
+                                ui.label("Name");
+                                ui.label(&obj.text);
+                                ui.end_row();
+                                ui.label("Shape");
+                                ui.label(format!("{:?}", obj.shape));
+                                ui.end_row();
+                                ui.label("Color");
+                                egui::color_picker::show_color(
+                                    ui,
+                                    obj.color,
+                                    egui::Vec2::new(24.0, 12.0),
+                                );
+                                ui.end_row();
+                                ui.label("Layer");
+                                ui.label(obj.layer.to_string());
+                                ui.end_row();
+                                ui.label("Tags");
+                                ui.label(if obj.tags.is_empty() {
+                                    "—".to_owned()
+                                } else {
+                                    obj.tags.join(", ")
+                                });
+                                ui.end_row();
+                                ui.label("Descendants");
+                                ui.label(count_descendants(obj).to_string());
+                                ui.end_row();
+                            });
+                            if !obj.memo.is_empty() {
+                                const MAX_MEMO: usize = 200;
+                                let memo = match obj.memo.char_indices().nth(MAX_MEMO) {
+                                    Some((cut, _)) => format!("{}…", &obj.memo[..cut]),
+                                    None => obj.memo.clone(),
+                                };
+                                ui.separator();
+                                ui.label(memo_layout(&memo, ui.style()));
+                            }
+                        },
+                    );
+                }
+            }
+
+            /* lasso: collect the path while dragging, select what it encloses on release */
+            if self.lasso_mode {
+                if resp.dragged_by(egui::PointerButton::Primary)
+                    && let Some(pos) = input.pointer.interact_pos()
+                    && self
+                        .lasso_points
+                        .last()
+                        .is_none_or(|l| l.distance(pos) > 2.0)
+                {
+                    self.lasso_points.push(pos);
+                }
+                if resp.drag_stopped() {
+                    let lasso = std::mem::take(&mut self.lasso_points);
+                    if lasso.len() >= 3 {
+                        if !input.modifiers.shift {
+                            self.selected_ids.clear();
+                        }
+                        for o in flatten(&self.scene_objects)
+                            .into_iter()
+                            .filter(|o| !o.locked)
+                        {
+                            let center = if o.pinned_to_screen {
+                                Some(o.screen_pos)
+                            } else {
+                                layout.get(&o.id).map(|&p| to_screen(p))
+                            };
+                            if center.is_some_and(|c| point_in_polygon(c, &lasso)) {
+                                self.selected_ids.insert(o.id);
+                            }
+                        }
+                    }
+                }
+                if self.lasso_points.len() >= 2 {
+                    painter.add(Shape::Path(egui::epaint::PathShape {
+                        points: self.lasso_points.clone(),
+                        closed: true,
+                        fill: Color32::from_rgba_unmultiplied(100, 160, 255, 40),
+                        stroke: Stroke::new(1.5, Color32::from_rgb(100, 160, 255)).into(),
+                    }));
+                }
+            }
+
+            /* drag a node to give it a manual position */
+            if !self.lasso_mode
+                && !self.preview_3d
+                && self.link_mode == LinkMode::Off
+                && self.layout_mode != LayoutMode::Treemap
+            {
+                if resp.drag_started_by(egui::PointerButton::Primary)
+                    && self.dragging_elbow.is_none()
+                    && let Some(pos) = input.pointer.interact_pos()
+                {
+                    let world = to_world(pos);
+                    self.dragging_node = self
+                        .scene_objects
+                        .iter()
+                        .find_map(|o| find_clicked_object(o, world, layout, &hit));
+                    if let Some(id) = self.dragging_node {
+                        drag_snapshot = Some((self.snapshot(), id));
+                    }
+                }
+                if let Some(id) = self.dragging_node
+                    && let Some(pos) = input.pointer.interact_pos()
+                    && let Some(obj) = self.node_index.get_mut(&mut self.scene_objects, id)
+                {
+                    let mut p = to_world(pos);
+                    if resp.drag_stopped() && self.show_grid && self.snap_to_grid {
+                        p = snap(p, GRID_SPACING);
+                    }
+                    obj.position_override = Some(p);
+                    self.layout_dirty = true;
+                }
+                if resp.drag_stopped() {
+                    self.dragging_node = None;
+                }
+            }
+
+            /* outline rows dropped here: onto a node to reparent, elsewhere to place */
+            if self.layout_mode != LayoutMode::Treemap && !self.preview_3d {
+                if let Some(id) = egui::DragAndDrop::payload::<u32>(ctx)
+                    && let Some(pos) = input.pointer.hover_pos()
+                    && rect.contains(pos)
+                    && let Some(obj) = self.node_index.get(&self.scene_objects, *id)
+                {
+                    let sz = node_size(sizes.as_ref(), obj.id) * obj.shown_scale();
+                    let rotation = snapped_rotation(obj.current_rotation, self.snap_rotation);
+                    let (shape, color) =
+                        resolve_appearance(&self.scene_objects, &self.node_index, obj);
+                    let ghost = color.gamma_multiply(0.4);
+                    draw_plain_shape(painter, obj, shape, pos, sz, rotation, ghost);
+                }
+                if let Some(id) = resp.dnd_release_payload::<u32>()
+                    && let Some(pos) = input.pointer.interact_pos()
+                {
+                    let id = *id;
+                    let world = to_world(pos);
+                    let target = self
+                        .scene_objects
+                        .iter()
+                        .find_map(|o| find_clicked_object(o, world, layout, &hit))
+                        .filter(|&t| t != id);
+                    if let Some(new_parent) = target {
+                        self.requests.push(EditorRequest::Reparent {
+                            node_id: id,
+                            new_parent_id: Some(new_parent),
+                        });
+                    } else if self.node_index.get(&self.scene_objects, id).is_some() {
+                        drag_snapshot = Some((self.snapshot(), id));
+                        let mut p = world;
+                        if self.show_grid && self.snap_to_grid {
+                            p = snap(p, GRID_SPACING);
+                        }
+                        if let Some(obj) = self.node_index.get_mut(&mut self.scene_objects, id) {
+                            obj.position_override = Some(p);
+                        }
+                        self.layout_dirty = true;
+                    }
+                }
+            }
+
+            /* right-click a node for its context menu; a right-drag still pans */
+            if resp.secondary_clicked()
+                && let Some(pos) = input.pointer.interact_pos()
+            {
+                let world = to_world(pos);
+                self.context_node = if self.layout_mode == LayoutMode::Treemap {
+                    find_treemap_object(&self.scene_objects, pos, &treemap)
+                } else {
+                    find_pinned_object(&self.scene_objects, pos).or_else(|| {
+                        self.scene_objects
+                            .iter()
+                            .find_map(|o| find_clicked_object(o, world, layout, &hit))
+                    })
+                };
+            }
+            if let Some(id) = self.context_node {
+                resp.context_menu(|ui| {
+                    // locked nodes stay out of the selection, as with Ctrl+A
+                    let subtree = self.node_index.get(&self.scene_objects, id).map(|o| {
+                        std::iter::once(o)
+                            .chain(flatten(&o.children))
+                            .filter(|c| !c.locked)
+                            .map(|c| c.id)
+                            .collect::<Vec<u32>>()
+                    });
+                    let Some(subtree) = subtree else {
+                        ui.close_menu();
+                        return;
+                    };
+                    if ui.button("Select Subtree").clicked() {
+                        self.selected_ids.extend(subtree);
+                        ui.close_menu();
+                    } else if ui.button("Deselect Subtree").clicked() {
+                        for id in subtree {
+                            self.selected_ids.remove(&id);
+                        }
+                        ui.close_menu();
+                    }
+                    ui.menu_button("Expand to Depth", |ui| {
+                        for (label, depth) in [
+                            ("1 level", Some(1)),
+                            ("2 levels", Some(2)),
+                            ("3 levels", Some(3)),
+                            ("All", None),
+                        ] {
+                            if ui.button(label).clicked() {
+                                expand_to = Some((id, depth));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+            }
+
+            if resp.clicked()
+                && input.pointer.primary_released()
+                && let Some(pos) = input.pointer.interact_pos()
+            {
+                let world = to_world(pos);
+                let clicked = if self.layout_mode == LayoutMode::Treemap {
+                    find_treemap_object(&self.scene_objects, pos, &treemap)
+                } else {
+                    find_pinned_object(&self.scene_objects, pos).or_else(|| {
+                        self.scene_objects
+                            .iter()
+                            .filter_map(|o| find_clicked_object(o, world, layout, &hit))
+                            .next()
+                    })
+                };
+                match self.link_mode {
+                    LinkMode::Add => {
+                        if let Some(id) = clicked {
+                            match self.link_source.take() {
+                                None => self.link_source = Some(id),
+                                Some(src) if src != id && !self.links.contains(&(src, id)) => {
+                                    self.links.push((src, id));
+                                }
+                                Some(_) => {}
+                            }
+                        }
+                    }
+                    LinkMode::Remove => {
+                        const PICK_DIST: f32 = 6.0;
+                        let ends = |id| layout.get(&id).map(|&p| to_screen(p));
+                        let near = self.links.iter().position(|&(a, b)| {
+                            matches!((ends(a), ends(b)), (Some(a), Some(b))
+                                if distance_to_segment(pos, a, b) <= PICK_DIST)
+                        });
+                        if let Some(i) = near {
+                            self.links.remove(i);
+                        }
+                    }
+                    LinkMode::Off if input.modifiers.shift => {
+                        if let Some(id) = clicked
+                            && !self.selected_ids.remove(&id)
+                        {
+                            self.selected_ids.insert(id);
+                        }
+                    }
+                    LinkMode::Off => {
+                        self.selected_ids = clicked.into_iter().collect();
+                        self.active_settings_id = clicked;
+                        if let Some(id) = clicked
+                            && let Some(events) = self
+                                .node_index
+                                .get(&self.scene_objects, id)
+                                .map(|o| o.on_click_events.clone())
+                                .filter(|e| !e.is_empty())
+                        {
+                            let targets = self
+                                .links
+                                .iter()
+                                .filter(|(src, _)| *src == id)
+                                .map(|&(_, target)| target)
+                                .collect::<Vec<_>>();
+                            if !targets.is_empty() {
+                                self.requests.push(EditorRequest::FireClickEvents {
+                                    node_id: id,
+                                    targets,
+                                    events,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        if let Some((id, depth)) = expand_to {
+            self.expand_to_depth(id, depth);
+        }
+        if let Some((snapshot, id)) = drag_snapshot {
+            let description = format!("Move {}", quoted_name(&self.scene_objects, id));
+            self.push_undo_snapshot(snapshot, description);
+        }
+
+        /* ----- breadcrumbs from the root to the inspected node ----- */
+        if let Some(path) = self
+            .active_settings_id
+            .and_then(|id| find_object_path(&self.scene_objects, id))
+        {
+            // deep chains keep only the parent and the node itself
+            const MAX_CRUMBS: usize = 5;
+            let (truncated, shown) = if path.len() > MAX_CRUMBS {
+                (true, &path[path.len() - 2..])
+            } else {
+                (false, &path[..])
+            };
+            let mut jump_to = None;
+            egui::Area::new(egui::Id::new("breadcrumbs"))
+                .fixed_pos(self.canvas_rect.min + egui::Vec2::splat(8.0))
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        if truncated {
+                            ui.label("…");
+                            ui.label(">");
+                        }
+                        for (i, &id) in shown.iter().enumerate() {
+                            if i > 0 {
+                                ui.label(">");
+                            }
+                            let name = self
+                                .node_index
+                                .get(&self.scene_objects, id)
+                                .map_or("?", |o| o.text.lines().next().unwrap_or(""));
+                            if ui.button(name).clicked() {
+                                jump_to = Some(id);
+                            }
+                        }
+                    });
+                });
+            if let Some(id) = jump_to {
+                self.active_settings_id = Some(id);
+                if let Some(&p) = self.layout_cache.as_ref().and_then(|m| m.get(&id)) {
+                    self.camera_goal = Some(p);
+                }
+            }
+        }
+
+        /* ----- minimap: press or drag to move the camera ----- */
+        if self.show_minimap
+            && self.layout_mode != LayoutMode::Treemap
+            && let Some(layout) = &self.layout_cache
+            && let Some(bounds) = layout
+                .values()
+                .map(|p| Rect::from_center_size(Pos2::new(p.x, p.y), egui::Vec2::ZERO))
+                .reduce(|a, b| a.union(b))
+        {
+            let bounds = bounds.expand(NODE_SIZE);
+            let scale = (MINIMAP_SIZE.x / bounds.width()).min(MINIMAP_SIZE.y / bounds.height());
+            let mut pan_to = None;
+            egui::Area::new(egui::Id::new("minimap"))
+                .fixed_pos(Pos2::new(
+                    self.canvas_rect.min.x + 8.0,
+                    self.canvas_rect.max.y - MINIMAP_SIZE.y - 8.0,
+                ))
+                .show(ctx, |ui| {
+                    let (resp, painter) = ui.allocate_painter(MINIMAP_SIZE, egui::Sense::drag());
+                    let rect = resp.rect;
+                    let to_mini =
+                        |p: egui::Vec2| rect.center() + (p - bounds.center().to_vec2()) * scale;
+                    painter.rect_filled(rect, 4.0, Color32::from_black_alpha(180));
+                    let nodes: Vec<_> = flatten(&self.scene_objects)
+                        .into_iter()
+                        .filter_map(|o| Some((layout.get(&o.id)?, o.color)))
+                        .collect();
+                    for &(&p, color) in nodes.iter().take(MINIMAP_MAX_NODES) {
+                        painter.circle_filled(to_mini(p), 1.5, color);
+                    }
+                    if nodes.len() > MINIMAP_MAX_NODES {
+                        painter.text(
+                            rect.right_bottom() - egui::Vec2::splat(4.0),
+                            egui::Align2::RIGHT_BOTTOM,
+                            format!("…+{} more", nodes.len() - MINIMAP_MAX_NODES),
+                            FontId::proportional(10.0),
+                            Color32::GRAY,
+                        );
+                    }
+                    let view = Rect::from_center_size(
+                        to_mini(self.camera_target),
+                        self.canvas_rect.size() / self.zoom * scale,
+                    );
+                    painter.with_clip_rect(rect).rect_stroke(
+                        view,
+                        0.0,
+                        Stroke::new(1.0, Color32::WHITE),
+                        egui::StrokeKind::Inside,
+                    );
+                    if resp.is_pointer_button_down_on()
+                        && let Some(pos) = resp.interact_pointer_pos()
+                    {
+                        pan_to = Some((pos - rect.center()) / scale + bounds.center().to_vec2());
+                    }
+                });
+            if let Some(p) = pan_to {
+                self.camera_target = p;
+                self.camera_goal = None;
+            }
+        }
+
+        /* ----- inline rename (F2) ----- */
+        if let Some(id) = self.renaming_node {
+            match (
+                rename_anchor,
+                self.node_index.get_mut(&mut self.scene_objects, id),
+            ) {
+                (Some(anchor), Some(obj)) => {
+                    const WIDTH: f32 = 120.0;
+                    egui::Area::new(egui::Id::new("inline_rename"))
+                        .fixed_pos(anchor - egui::Vec2::new(WIDTH / 2.0, 10.0))
+                        .show(ctx, |ui| {
+                            let resp = ui.add(
+                                egui::TextEdit::singleline(&mut obj.text_buffer)
+                                    .desired_width(WIDTH),
+                            );
+                            if resp.lost_focus() {
+                                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                                    obj.text_buffer.clone_from(&obj.text);
+                                } else {
+                                    obj.text.clone_from(&obj.text_buffer);
+                                }
+                                self.renaming_node = None;
+                            } else {
+                                resp.request_focus();
+                            }
+                        });
+                }
+                _ => self.renaming_node = None,
+            }
+        }
+
+        if let Some((msg, since)) = &self.toast {
+            if input.time - since < TOAST_SECS {
+                egui::Area::new(egui::Id::new("toast"))
+                    .anchor(egui::Align2::RIGHT_BOTTOM, egui::Vec2::new(-12.0, -40.0))
+                    .show(ctx, |ui| {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| ui.label(msg));
+                    });
+            } else {
+                self.toast = None;
+            }
+        }
+
+        egui::Window::new("Help")
+            .open(&mut self.show_help)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcuts").striped(true).show(ui, |ui| {
+                    for (keys, action) in SHORTCUTS {
+                        ui.monospace(*keys);
+                        ui.label(*action);
+                        ui.end_row();
+                    }
+                });
+            });
+
+        egui::Window::new("Renderer Info")
+            .open(&mut self.renderer_info_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("renderer_info")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        let info = self
+                            .renderer_info
+                            .as_deref()
+                            .unwrap_or("Renderer: not wgpu");
+                        let versions = [
+                            ("egui", env!("EGUI_VERSION")),
+                            ("eframe", env!("EFRAME_VERSION")),
+                        ];
+                        let lines = info.lines().filter_map(|l| l.split_once(": "));
+                        for (key, value) in lines.chain(versions) {
+                            ui.label(key);
+                            ui.monospace(value);
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        egui::Window::new("Theme Editor")
+            .open(&mut self.theme_editor_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let t = &mut self.custom_theme;
+                egui::Grid::new("theme_colors").show(ui, |ui| {
+                    for (name, color) in [
+                        ("Background", &mut t.background),
+                        ("Panel", &mut t.panel),
+                        ("Text", &mut t.text),
+                    ] {
+                        ui.label(name);
+                        color_edit(ui, color);
+                        ui.end_row();
+                    }
+                });
+            });
+
+        egui::Window::new("Groups")
+            .open(&mut self.show_groups)
+            .show(ctx, |ui| {
+                if self.groups.is_empty() {
+                    ui.label("Select nodes and press Create Group.");
+                }
+                let names: Vec<(u32, String)> = self
+                    .groups
+                    .iter()
+                    .map(|g| (g.id, g.label.clone()))
+                    .collect();
+                let mut dissolve = None;
+                let mut new_link = None;
+                let mut remove_link = None;
+                for g in &mut self.groups {
+                    ui.horizontal(|ui| {
+                        color_edit(ui, &mut g.color);
+                        ui.add(egui::TextEdit::singleline(&mut g.label).desired_width(120.0));
+                        ui.label(format!("{} nodes", g.node_ids.len()));
+                        if ui.button("Dissolve").clicked() {
+                            dissolve = Some(g.id);
+                        }
+                        ui.menu_button("Link to", |ui| {
+                            for (id, name) in names.iter().filter(|(id, _)| *id != g.id) {
+                                if ui.button(name).clicked() {
+                                    new_link = Some((g.id, *id, g.color));
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+                    for (i, l) in self.group_links.iter_mut().enumerate() {
+                        if l.from_group != g.id {
+                            continue;
+                        }
+                        ui.horizontal(|ui| {
+                            ui.add_space(16.0);
+                            let to = names.iter().find(|(id, _)| *id == l.to_group);
+                            ui.label(format!("→ {}", to.map_or("?", |(_, name)| name)));
+                            color_edit(ui, &mut l.color);
+                            ui.add(
+                                egui::TextEdit::singleline(&mut l.label)
+                                    .hint_text("label")
+                                    .desired_width(100.0),
+                            );
+                            if ui.small_button("🗑").on_hover_text("Remove link").clicked() {
+                                remove_link = Some(i);
+                            }
+                        });
+                    }
+                }
+                if let Some((from_group, to_group, color)) = new_link {
+                    self.group_links.push(GroupLink {
+                        from_group,
+                        to_group,
+                        label: String::new(),
+                        color,
+                    });
+                }
+                if let Some(i) = remove_link {
+                    self.group_links.remove(i);
+                }
+                if let Some(id) = dissolve {
+                    self.groups.retain(|g| g.id != id);
+                    self.group_links
+                        .retain(|l| l.from_group != id && l.to_group != id);
+                }
+            });
+
+        egui::Window::new("Statistics")
+            .open(&mut self.show_stats)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let mut weights = vec![];
+                visit(&self.scene_objects, 0, &mut |o, depth| {
+                    if depth > 0 {
+                        weights.push(o.edge_weight);
+                    }
+                });
+                let deepest = self.scene_objects.iter().map(max_depth_in_subtree).max();
+                egui::Grid::new("stats").striped(true).show(ui, |ui| {
+                    ui.label("Nodes");
+                    ui.label(node_count(&self.scene_objects).to_string());
+                    ui.end_row();
+                    ui.label("Max depth");
+                    ui.label(deepest.map_or("—".into(), |d| d.to_string()));
+                    ui.end_row();
+                    ui.label("Edges");
+                    ui.label(weights.len().to_string());
+                    ui.end_row();
+                    if !weights.is_empty() {
+                        let avg = weights.iter().sum::<f32>() / weights.len() as f32;
+                        let max = weights.iter().copied().fold(f32::MIN, f32::max);
+                        ui.label("Avg edge weight");
+                        ui.label(format!("{avg:.2}"));
+                        ui.end_row();
+                        ui.label("Max edge weight");
+                        ui.label(format!("{max:.2}"));
+                        ui.end_row();
+                    }
+                });
+            });
+
+        egui::Window::new("Compare Scenes")
+            .open(&mut self.compare_open)
+            .show(ctx, |ui| {
+                egui::Grid::new("compare_paths").show(ui, |ui| {
+                    ui.label("Before:");
+                    ui.text_edit_singleline(&mut self.compare_paths[0]);
+                    ui.end_row();
+                    ui.label("After:");
+                    ui.text_edit_singleline(&mut self.compare_paths[1]);
+                    ui.end_row();
+                });
+                if ui.button("Compare").clicked() {
+                    let load = |p: &str| scene_file::load(Path::new(p)).map(|s| s.objects);
+                    match (load(&self.compare_paths[0]), load(&self.compare_paths[1])) {
+                        (Ok(before), Ok(after)) => {
+                            let diff = diff_scenes(&before, &after);
+                            self.comparison = Some(SceneComparison {
+                                before,
+                                after,
+                                diff,
+                            });
+                            self.compare_error = None;
+                            self.compare_picked = None;
+                        }
+                        (Err(e), _) | (_, Err(e)) => self.compare_error = Some(e.to_string()),
+                    }
+                }
+                if let Some(err) = &self.compare_error {
+                    ui.colored_label(Color32::RED, err);
+                }
+                let Some(c) = &self.comparison else {
+                    return;
+                };
+
+                ui.separator();
+                ui.label(format!(
+                    "{} added, {} removed, {} changed",
+                    c.diff.added.len(),
+                    c.diff.removed.len(),
+                    c.diff.changed.len()
+                ));
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        ui.columns(2, |cols| {
+                            cols[0].strong("Before");
+                            diff_tree(
+                                &mut cols[0],
+                                &c.before,
+                                &c.diff,
+                                false,
+                                &mut self.compare_picked,
+                            );
+                            cols[1].strong("After");
+                            diff_tree(
+                                &mut cols[1],
+                                &c.after,
+                                &c.diff,
+                                true,
+                                &mut self.compare_picked,
+                            );
+                        });
+                    });
+
+                if let Some(id) = self.compare_picked
+                    && let Some(a) = find_object_by_id(&c.before, id)
+                    && let Some(b) = find_object_by_id(&c.after, id)
+                {
+                    ui.separator();
+                    let hex = |c: Color32| format!("#{:02x}{:02x}{:02x}", c.r(), c.g(), c.b());
+                    let rows = [
+                        ("Name", a.text.clone(), b.text.clone()),
+                        ("Color", hex(a.color), hex(b.color)),
+                        ("Shape", format!("{:?}", a.shape), format!("{:?}", b.shape)),
+                        (
+                            "Rotation Speed",
+                            format!("{:.1}", a.rotation_speed),
+                            format!("{:.1}", b.rotation_speed),
+                        ),
+                    ];
+                    egui::Grid::new("compare_props")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong("Property");
+                            ui.strong("Before");
+                            ui.strong("After");
+                            ui.end_row();
+                            for (name, before, after) in rows {
+                                let color = if before == after {
+                                    ui.visuals().text_color()
+                                } else {
+                                    Color32::YELLOW
+                                };
+                                ui.label(name);
+                                ui.colored_label(color, before);
+                                ui.colored_label(color, after);
+                                ui.end_row();
+                            }
+                        });
+                }
+            });
+
+        if let Some(previews) = &self.template_gallery {
+            let mut open = true;
+            let mut chosen = None;
+            egui::Window::new("New from Template")
+                .open(&mut open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    egui::Grid::new("templates")
+                        .spacing([12.0, 12.0])
+                        .show(ui, |ui| {
+                            for (i, (template, preview)) in
+                                TEMPLATES.iter().zip(previews).enumerate()
+                            {
+                                ui.vertical(|ui| {
+                                    let (rect, resp) = ui.allocate_exact_size(
+                                        egui::Vec2::new(140.0, 100.0),
+                                        egui::Sense::click(),
+                                    );
+                                    let visuals = ui.style().interact(&resp);
+                                    ui.painter().rect(
+                                        rect,
+                                        4.0,
+                                        ui.visuals().extreme_bg_color,
+                                        visuals.bg_stroke,
+                                        egui::StrokeKind::Inside,
+                                    );
+                                    draw_template_thumbnail(
+                                        ui.painter(),
+                                        rect.shrink(10.0),
+                                        preview,
+                                    );
+                                    ui.label(template.name);
+                                    if resp.clicked() {
+                                        chosen = Some(i);
+                                    }
+                                });
+                                if i % 3 == 2 {
+                                    ui.end_row();
+                                }
+                            }
+                        });
+                    ui.separator();
+                    if ui.button("Blank Scene").clicked() {
+                        chosen = Some(TEMPLATES.len());
+                    }
+                });
+            if let Some(i) = chosen {
+                let (objects, name) = match (previews.get(i), TEMPLATES.get(i)) {
+                    (Some(preview), Some(template)) => (preview.clone(), template.name),
+                    _ => (build_blank_scene(&mut 0), "Blank Scene"),
+                };
+                self.replace_scene(objects, format!("New from template \"{name}\""));
+                open = false;
+            }
+            if !open {
+                self.template_gallery = None;
+            }
+        }
+
+        let mut metrics_open = self.metrics_export_open;
+        egui::Window::new("Export Metrics CSV")
+            .open(&mut metrics_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Path:");
+                    ui.text_edit_singleline(&mut self.metrics_path);
+                });
+                if ui.button("Export").clicked() {
+                    let layout: HashMap<u32, egui::Vec2> = flatten(&self.scene_objects)
+                        .into_iter()
+                        .filter_map(|o| Some((o.id, self.world_position(o.id)?)))
+                        .collect();
+                    let csv = csv_export::export_metrics_csv(&self.scene_objects, &layout);
+                    let path = Path::new(&self.metrics_path);
+                    let msg = match storage::write(path, &csv) {
+                        Ok(()) => format!("Saved to {}", path.display()),
+                        Err(e) => format!("Metrics export failed: {e}"),
+                    };
+                    self.toast = Some((msg, input.time));
+                    self.metrics_export_open = false;
+                }
+            });
+        self.metrics_export_open &= metrics_open;
+
+        if self.confirm_reset_overrides {
+            egui::Window::new("Reset All Overrides")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label("Reset all manual positions? Undo (Ctrl+Z) brings them back.");
+                    ui.horizontal(|ui| {
+                        if ui.button("Reset").clicked() {
+                            self.reset_position_overrides();
+                            self.confirm_reset_overrides = false;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.confirm_reset_overrides = false;
+                        }
+                    });
+                });
+        }
+
+        if let Some(at) = self.clipboard_picker {
+            let mut open = true;
+            let mut chosen = None;
+            egui::Window::new("Paste from History")
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .fixed_pos(self.world_to_screen(at))
+                .show(ctx, |ui| {
+                    for (i, json) in self.clipboard_history.iter().enumerate() {
+                        let label = match serde_json::from_str::<SceneObject>(json) {
+                            Ok(o) => match count_descendants(&o) {
+                                0 => o.text,
+                                n => format!("{} (+{n})", o.text),
+                            },
+                            Err(_) => "(unreadable)".to_owned(),
+                        };
+                        if ui.selectable_label(false, label).clicked() {
+                            chosen = Some(i);
+                        }
+                    }
+                });
+            if let Some(json) = chosen.and_then(|i| self.clipboard_history.get(i)).cloned() {
+                self.paste_subtree(&json, Some(at), &input);
+                open = false;
+            }
+            if !open || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+                self.clipboard_picker = None;
+            }
+        }
+
+        let mut types_open = self.node_types_open;
+        egui::Window::new("Node Types")
+            .open(&mut types_open)
+            .show(ctx, |ui| {
+                let mut names: Vec<String> = self.node_types.keys().cloned().collect();
+                names.sort();
+                let mut remove = None;
+                for name in &names {
+                    let Some(spec) = self.node_types.get_mut(name) else {
+                        continue;
+                    };
+                    egui::CollapsingHeader::new(name)
+                        .id_salt(("node_type", name))
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("Shape:");
+                                ui.radio_value(&mut spec.shape, ShapeKind::Square, "Square");
+                                ui.radio_value(&mut spec.shape, ShapeKind::Circle, "Circle");
+                                ui.radio_value(&mut spec.shape, ShapeKind::Triangle, "Triangle");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Color:");
+                                color_edit(ui, &mut spec.default_color);
+                            });
+                            ui.label("Allowed children:");
+                            ui.horizontal_wrapped(|ui| {
+                                for child in &names {
+                                    let mut on = spec.allowed_child_types.contains(child);
+                                    if ui.checkbox(&mut on, child).changed() {
+                                        if on {
+                                            spec.allowed_child_types.push(child.clone());
+                                        } else {
+                                            spec.allowed_child_types.retain(|t| t != child);
+                                        }
+                                    }
+                                }
+                            });
+                            if ui.button("Delete Type").clicked() {
+                                remove = Some(name.clone());
+                            }
+                        });
+                }
+                if let Some(name) = remove {
+                    // nodes keep the name, which now constrains nothing
+                    self.node_types.remove(&name);
+                    for spec in self.node_types.values_mut() {
+                        spec.allowed_child_types.retain(|t| *t != name);
+                    }
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.new_type_name);
+                    let name = self.new_type_name.trim().to_owned();
+                    if ui
+                        .add_enabled(
+                            !name.is_empty() && !self.node_types.contains_key(&name),
+                            egui::Button::new("Add Type"),
+                        )
+                        .clicked()
+                    {
+                        self.node_types.insert(
+                            name,
+                            NodeTypeSpec {
+                                allowed_child_types: vec![],
+                                shape: ShapeKind::Square,
+                                default_color: Color32::WHITE,
+                            },
+                        );
+                        self.new_type_name.clear();
+                    }
+                });
+            });
+        self.node_types_open &= types_open;
+
+        let mut find_open = self.find_replace_open;
+        egui::Window::new("Find & Replace")
+            .open(&mut find_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("find_replace")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Find:");
+                        ui.text_edit_singleline(&mut self.find_text);
+                        ui.end_row();
+                        ui.label("Replace:");
+                        ui.text_edit_singleline(&mut self.replace_text);
+                        ui.end_row();
+                    });
+                ui.checkbox(&mut self.find_case_sensitive, "Match case");
+                ui.horizontal(|ui| {
+                    for (label, all) in [("Replace First", false), ("Replace All", true)] {
+                        if ui
+                            .add_enabled(!self.find_text.is_empty(), egui::Button::new(label))
+                            .on_hover_text(if all {
+                                "Every match in every name"
+                            } else {
+                                "The first match in each name"
+                            })
+                            .clicked()
+                        {
+                            self.requests.push(EditorRequest::ReplaceText {
+                                find: self.find_text.clone(),
+                                replace: self.replace_text.clone(),
+                                case_sensitive: self.find_case_sensitive,
+                                all,
+                            });
+                        }
+                    }
+                });
+            });
+        self.find_replace_open &= find_open;
+
+        let mut dot_open = self.dot_import_open;
+        egui::Window::new("Import from DOT")
+            .open(&mut dot_open)
+            .show(ctx, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.dot_input)
+                        .code_editor()
+                        .hint_text("digraph { A -> B; A -> C [shape=circle]; }"),
+                );
+                if let Some(err) = &self.dot_error {
+                    ui.colored_label(Color32::RED, err);
+                }
+                if ui.button("Import").clicked() {
+                    match dot_import::parse(&self.dot_input) {
+                        Ok(mut roots) => {
+                            self.assign_new_ids(&mut roots);
+                            self.scene_objects.extend(roots);
+                            self.layout_dirty = true;
+                            self.dot_error = None;
+                            self.dot_import_open = false;
+                        }
+                        Err(e) => self.dot_error = Some(e.to_string()),
+                    }
+                }
+            });
+        self.dot_import_open &= dot_open;
+
+        let mut export_open = self.dot_export.is_some();
+        if let Some(dot) = &self.dot_export {
+            egui::Window::new("DOT Export")
+                .open(&mut export_open)
+                .show(ctx, |ui| {
+                    let mut preview: String = dot.lines().take(20).collect::<Vec<_>>().join("\n");
+                    if dot.lines().count() > 20 {
+                        preview.push_str("\n…");
+                    }
+                    ui.add(
+                        egui::TextEdit::multiline(&mut preview)
+                            .font(egui::TextStyle::Monospace)
+                            .interactive(false),
+                    );
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut self.dot_export_path);
+                        if ui.button("Save").clicked() {
+                            self.dot_export_status = Some(
+                                match storage::write(Path::new(&self.dot_export_path), dot) {
+                                    Ok(()) => format!("Saved to {}", self.dot_export_path),
+                                    Err(e) => format!("Save failed: {e}"),
+                                },
+                            );
+                        }
+                    });
+                    if let Some(status) = &self.dot_export_status {
+                        ui.label(status);
+                    }
+                });
+        }
+        if !export_open {
+            self.dot_export = None;
+        }
+
+        egui::Window::new("Price graph").show(ctx, |ui| {
+            self.graph.ui(ui);
+        });
+
+        /* ----- inspector ----- */
+        let mut save_prefabs = false;
+        let mut save_palette = false;
+        let mut save_window_pos = false;
+        let mut open_link = None;
+        let mut insert_prefab = None;
+        let batch = self.selected_ids.len() > 1;
+        if batch {
+            let mut ids: Vec<u32> = self.selected_ids.iter().copied().collect();
+            ids.sort_unstable();
+            // the controls start from the lowest-ID node's values
+            if let Some(first) = ids
+                .iter()
+                .find_map(|&id| self.node_index.get(&self.scene_objects, id))
+            {
+                let (mut speed, mut color, mut shape) =
+                    (first.rotation_speed, first.color, first.shape);
+                egui::Window::new(format!("Batch Edit: {} nodes", ids.len()))
+                    .id(egui::Id::new("batch_inspector"))
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        ui.label("Rotation Speed:");
+                        if ui.add(Slider::new(&mut speed, -360.0..=360.0)).changed() {
+                            self.batch_edit(EditorRequest::BatchSetRotationSpeed {
+                                ids: ids.clone(),
+                                value: speed,
+                            });
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label("Color:");
+                            if color_edit(ui, &mut color) {
+                                self.batch_edit(EditorRequest::BatchSetColor {
+                                    ids: ids.clone(),
+                                    color,
+                                });
+                            }
+                        });
+                        ui.label("Shape:");
+                        for (kind, name) in [
+                            (ShapeKind::Square, "Square"),
+                            (ShapeKind::Circle, "Circle"),
+                            (ShapeKind::Triangle, "Triangle"),
+                        ] {
+                            if ui.radio_value(&mut shape, kind, name).changed() {
+                                self.requests.push(EditorRequest::BatchSetShape {
+                                    ids: ids.clone(),
+                                    shape,
+                                });
+                            }
+                        }
+                    });
+            }
+        }
+        if !ctx.input(|i| i.pointer.any_down())
+            && let Some((snapshot, description)) = self.batch_undo.take()
+        {
+            self.push_undo_snapshot(snapshot, description);
+        }
+        if let Some(id) = self.active_settings_id
+            && !batch
+            && let Some(path) = find_object_path(&self.scene_objects, id)
+            && let Some(node_path) = node_path_string(&self.scene_objects, id)
+            && let is_root = path.len() == 1
+            && let parent_id = path.iter().rev().nth(1).copied()
+            // the node may move under anything known that is outside its own subtree
+            && let known = ids_in(&self.scene_objects)
+            && let Some(subtree) = self
+                .node_index
+                .get(&self.scene_objects, id)
+                .map(|o| ids_in(std::slice::from_ref(o)))
+            // (parent, index among its children, sibling count) for child nodes
+            && let siblings = parent_id
+                .and_then(|p| self.node_index.get(&self.scene_objects, p))
+                .and_then(|p| {
+                    let index = p.children.iter().position(|c| c.id == id)?;
+                    Some((p.id, index, p.children.len()))
+                })
+            && let taken_slugs = flatten(&self.scene_objects)
+                .into_iter()
+                .filter(|o| o.id != id)
+                .map(|o| o.slug.clone())
+                .collect::<HashSet<String>>()
+            && let transform = self.node_transform(id)
+            && let parent_type = parent_id
+                .and_then(|p| self.node_index.get(&self.scene_objects, p))
+                .and_then(|p| p.node_type.clone())
+            && let Some(obj) = self.node_index.get_mut(&mut self.scene_objects, id)
+        {
+            let describe_parent = |p: Option<u32>| p.map_or("root".to_owned(), |p| p.to_string());
+            if !matches!(&self.reparent_buffer, Some((for_id, _)) if *for_id == id) {
+                self.reparent_buffer = Some((id, describe_parent(parent_id)));
+                self.reparent_error = None;
+            }
+            if !matches!(&self.slug_buffer, Some((for_id, _)) if *for_id == id) {
+                self.slug_buffer = Some((id, obj.slug.clone()));
+                self.slug_error = None;
+            }
+            let mut window = egui::Window::new(format!(
+                "Settings: {}",
+                obj.text.lines().next().unwrap_or_default()
+            ))
+            // keyed by node so a rename doesn't forget where the window was
+            .id(egui::Id::new(("inspector", id)))
+            .resizable(false)
+            .vscroll(true);
+            if let Some(&pos) = self.window_positions.get(&id) {
+                window = window.default_pos(pos);
+            }
+            let mut tab = self.inspector_tab.get(&id).copied().unwrap_or(0);
+            let shown = window.show(ctx, |ui| {
+                if let Some(widget) = self.last_changed_widget.take()
+                    && let Some(resp) = ui.ctx().read_response(widget)
+                    && resp.layer_id == ui.layer_id()
+                    && !ui.clip_rect().contains_rect(resp.rect)
+                {
+                    ui.scroll_to_rect(resp.rect, Some(egui::Align::Center));
+                }
+                if obj.locked {
+                    ui.horizontal(|ui| {
+                        ui.label("Locked 🔒");
+                        if ui.button("Unlock").clicked() {
+                            obj.locked = false;
+                        }
+                    });
+                    return;
+                }
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut obj.locked, "Locked");
+                    ui.checkbox(&mut obj.disabled, "Disabled");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Path:");
+                    // a &str buffer can be selected but not edited
+                    ui.add(
+                        egui::TextEdit::singleline(&mut node_path.as_str()).desired_width(180.0),
+                    );
+                    if ui.button("Copy Path").clicked() {
+                        let text = node_path.clone();
+                        ctx.output_mut(|o| o.commands.push(OutputCommand::CopyText(text)));
+                    }
+                });
+                let depth = path.len() as u32 - 1;
+                for rule in self
+                    .rules
+                    .iter()
+                    .filter(|r| r.condition.violated_by(obj, depth))
+                {
+                    let text = match rule.message.trim() {
+                        "" => rule.condition.label(),
+                        message => message,
+                    };
+                    ui.colored_label(BadgeKind::Warning.color(), format!("⚠ {text}"));
+                }
+                ui.horizontal(|ui| {
+                    for (i, name) in INSPECTOR_TABS.iter().enumerate() {
+                        ui.selectable_value(&mut tab, i, *name);
+                    }
+                });
+                ui.separator();
+                match tab {
+                    0 => {
+                        ui.label("Name:");
+                        // Enter adds a line here, so the name applies once focus leaves
+                        let resp =
+                            ui.add(egui::TextEdit::multiline(&mut obj.text_buffer).desired_rows(3));
+                        if resp.lost_focus() {
+                            obj.text.clone_from(&obj.text_buffer);
+                        }
+                        if ui.button("Apply Name").clicked() {
+                            obj.text.clone_from(&obj.text_buffer);
+                        }
+
+                        if let Some((_, buffer)) = &mut self.slug_buffer {
+                            ui.horizontal(|ui| {
+                                ui.label("ID:");
+                                let resp = ui.text_edit_singleline(buffer);
+                                let submitted = resp.lost_focus()
+                                    && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                                if (ui.button("Apply").clicked() || submitted)
+                                    && *buffer != obj.slug
+                                {
+                                    let taken = taken_slugs.iter().map(String::as_str).collect();
+                                    match validate_slug(buffer.trim(), &taken) {
+                                        Ok(()) => {
+                                            obj.slug = buffer.trim().to_owned();
+                                            buffer.clone_from(&obj.slug);
+                                            self.slug_error = None;
+                                        }
+                                        Err(e) => self.slug_error = Some(e),
+                                    }
+                                }
+                            });
+                            if let Some(err) = &self.slug_error {
+                                ui.colored_label(Color32::RED, err);
+                            }
+                        }
+
+                        if let Some((_, buffer)) = &mut self.reparent_buffer {
+                            ui.horizontal(|ui| {
+                                ui.label("Parent ID:");
+                                let resp =
+                                    ui.add(egui::TextEdit::singleline(buffer).desired_width(60.0));
+                                if resp.lost_focus()
+                                    && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                                {
+                                    let input = buffer.trim();
+                                    let target = if input.eq_ignore_ascii_case("root") {
+                                        Ok(None)
+                                    } else {
+                                        match input.parse::<u32>() {
+                                            Ok(p) if !known.contains(&p) => {
+                                                Err(format!("No node with ID {p}"))
+                                            }
+                                            Ok(p) if subtree.contains(&p) => {
+                                                Err("Can't move a node under itself".to_owned())
+                                            }
+                                            Ok(p) => Ok(Some(p)),
+                                            Err(_) => Err(format!("`{input}` is not a node ID")),
+                                        }
+                                    };
+                                    match target {
+                                        Ok(new_parent_id) => {
+                                            if new_parent_id != parent_id {
+                                                self.requests.push(EditorRequest::Reparent {
+                                                    node_id: id,
+                                                    new_parent_id,
+                                                });
+                                            }
+                                            *buffer = describe_parent(new_parent_id);
+                                            self.reparent_error = None;
+                                        }
+                                        Err(e) => self.reparent_error = Some(e),
+                                    }
+                                }
+                            });
+                            if let Some(err) = &self.reparent_error {
+                                ui.colored_label(Color32::RED, err);
+                            }
+                        }
+
+                        ui.separator();
+                        ui.label("Comment:");
+                        ui.text_edit_multiline(&mut obj.comment);
+                        ui.horizontal(|ui| {
+                            ui.label("Memo:");
+                            let editing = self.editing_memo == Some(obj.id);
+                            let toggle = if editing { "View Memo" } else { "Edit Memo" };
+                            if ui.small_button(toggle).clicked() {
+                                self.editing_memo = (!editing).then_some(obj.id);
+                            }
+                        });
+                        if self.editing_memo == Some(obj.id) {
+                            ui.text_edit_multiline(&mut obj.memo);
+                        } else {
+                            ui.label(memo_layout(&obj.memo, ui.style()));
+                        }
+
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            ui.label("Type:");
+                            let mut names: Vec<&String> = self.node_types.keys().collect();
+                            names.sort();
+                            let before = obj.node_type.clone();
+                            egui::ComboBox::from_id_salt("node_type")
+                                .selected_text(obj.node_type.as_deref().unwrap_or("None"))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut obj.node_type, None, "None");
+                                    for name in names {
+                                        ui.selectable_value(
+                                            &mut obj.node_type,
+                                            Some(name.clone()),
+                                            name,
+                                        );
+                                    }
+                                });
+                            // picking a type gives the node that type's look
+                            if obj.node_type != before
+                                && let Some(spec) =
+                                    obj.node_type.as_ref().and_then(|t| self.node_types.get(t))
+                            {
+                                obj.shape = spec.shape;
+                                obj.color = spec.default_color;
+                            }
+                        });
+                        if let Some(pt) = &parent_type
+                            && let Some(spec) = self.node_types.get(pt)
+                            && !obj
+                                .node_type
+                                .as_ref()
+                                .is_some_and(|t| spec.allowed_child_types.contains(t))
+                        {
+                            ui.colored_label(
+                                Color32::YELLOW,
+                                format!("Its parent's type '{pt}' doesn't allow this"),
+                            );
+                        }
+                        ui.label("Shape:");
+                        ui.radio_value(&mut obj.shape, ShapeKind::Square, "Square");
+                        ui.radio_value(&mut obj.shape, ShapeKind::Circle, "Circle");
+                        ui.radio_value(&mut obj.shape, ShapeKind::Triangle, "Triangle");
+                        let is_reference = matches!(obj.shape, ShapeKind::Reference { .. });
+                        if ui.radio(is_reference, "Reference").clicked() && !is_reference {
+                            // start out mirroring the parent, the likeliest thing to copy
+                            let target_id = parent_id.unwrap_or(id);
+                            obj.shape = ShapeKind::Reference { target_id };
+                        }
+                        if let ShapeKind::Reference { target_id } = &mut obj.shape {
+                            ui.horizontal(|ui| {
+                                ui.label("Target ID:");
+                                ui.add(egui::DragValue::new(target_id));
+                            });
+                            if !known.contains(target_id) {
+                                ui.colored_label(Color32::YELLOW, "No node with that ID");
+                            } else if *target_id == id {
+                                ui.colored_label(Color32::YELLOW, "Can't mirror itself");
+                            }
+                        }
+                        ui.add_enabled(
+                            obj.shape == ShapeKind::Square,
+                            Slider::new(&mut obj.corner_radius, 0.0..=20.0).text("Corner Radius"),
+                        );
+                        ui.horizontal(|ui| {
+                            ui.label("Morph To:");
+                            let name = |shape: Option<ShapeKind>| match shape {
+                                None => "None",
+                                Some(ShapeKind::Square) => "Square",
+                                Some(ShapeKind::Circle) => "Circle",
+                                Some(ShapeKind::Triangle) => "Triangle",
+                                Some(ShapeKind::Reference { .. }) => "Reference",
+                            };
+                            let before = obj.morph_target;
+                            egui::ComboBox::from_id_salt("morph_to")
+                                .selected_text(name(obj.morph_target))
+                                .show_ui(ui, |ui| {
+                                    for shape in [
+                                        None,
+                                        Some(ShapeKind::Square),
+                                        Some(ShapeKind::Circle),
+                                        Some(ShapeKind::Triangle),
+                                    ] {
+                                        ui.selectable_value(
+                                            &mut obj.morph_target,
+                                            shape,
+                                            name(shape),
+                                        );
+                                    }
+                                });
+                            if obj.morph_target != before {
+                                obj.morph_progress = 0.0;
+                            }
+                            if obj.morph_target.is_some() {
+                                ui.add(
+                                    egui::ProgressBar::new(obj.morph_progress).desired_width(80.0),
+                                );
+                            }
+                        });
+
+                        ui.separator();
+                        ui.label("Color:");
+                        ui.horizontal(|ui| {
+                            color_edit(ui, &mut obj.color);
+                            for (name, color) in &self.palette {
+                                let swatch = egui::Button::new("")
+                                    .fill(*color)
+                                    .min_size(egui::Vec2::splat(14.0));
+                                if ui.add(swatch).on_hover_text(name).clicked() {
+                                    obj.color = *color;
+                                }
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.palette_name)
+                                    .hint_text("color name")
+                                    .desired_width(100.0),
+                            );
+                            let name = self.palette_name.trim();
+                            if ui
+                                .add_enabled(!name.is_empty(), egui::Button::new("Set from Node"))
+                                .on_hover_text("Add this node's color to the palette")
+                                .clicked()
+                            {
+                                // a name already in the palette takes the new color
+                                match self.palette.iter_mut().find(|(n, _)| n == name) {
+                                    Some((_, color)) => *color = obj.color,
+                                    None => self.palette.push((name.to_owned(), obj.color)),
+                                }
+                                self.palette_name.clear();
+                                save_palette = true;
+                            }
+                        });
+                        ui.add(Slider::new(&mut obj.opacity, 0.0..=1.0).text("Opacity"));
+                        ui.add(Slider::new(&mut obj.fill_ratio, 0.0..=1.0).text("Fill Ratio"));
+                        ui.horizontal(|ui| {
+                            ui.label("Label Color:");
+                            let mut color = obj.text_color.unwrap_or(ui.visuals().text_color());
+                            if color_edit(ui, &mut color) {
+                                obj.text_color = Some(color);
+                            }
+                            if obj.text_color.is_some() && ui.small_button("Theme").clicked() {
+                                obj.text_color = None;
+                            }
+                        });
+                        ui.add(Slider::new(&mut obj.text_size, 8.0..=32.0).text("Label Size"));
+                        ui.horizontal(|ui| {
+                            ui.label("Layer:");
+                            self.layout_dirty |= ui
+                                .add(egui::DragValue::new(&mut obj.layer).range(0..=7))
+                                .changed();
+                        });
+
+                        ui.separator();
+                        if obj.position_override.is_some() && ui.button("Reset Position").clicked()
+                        {
+                            obj.position_override = None;
+                            self.layout_dirty = true;
+                        }
+                        if !obj.children.is_empty() {
+                            ui.checkbox(&mut obj.collapsed, "Collapse Children");
+                            ui.horizontal(|ui| {
+                                ui.label("Expand Depth");
+                                let mut unlimited = obj.expand_depth.is_none();
+                                let mut depth = obj.expand_depth.unwrap_or(1);
+                                let mut changed = ui.toggle_value(&mut unlimited, "∞").changed();
+                                changed |= ui
+                                    .add_enabled(
+                                        !unlimited,
+                                        egui::DragValue::new(&mut depth).range(0..=20),
+                                    )
+                                    .changed();
+                                obj.expand_depth = (!unlimited).then_some(depth);
+                                self.layout_dirty |= changed;
+                            });
+                        }
+                        // pinned nodes give up their slot in the tree layout
+                        self.layout_dirty |= ui
+                            .checkbox(&mut obj.pinned_to_screen, "Pin to Screen")
+                            .changed();
+                        ui.add_enabled_ui(obj.pinned_to_screen, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("X");
+                                ui.add(egui::DragValue::new(&mut obj.screen_pos.x));
+                                ui.label("Y");
+                                ui.add(egui::DragValue::new(&mut obj.screen_pos.y));
+                            });
+                        });
+                        ui.add(
+                            Slider::new(&mut obj.z_offset, -500.0..=500.0).text("Z Offset (3D)"),
+                        );
+
+                        ui.separator();
+                        ui.label("World Transform:");
+                        match transform {
+                            Some(m) => {
+                                egui::Grid::new(("world_transform", id)).striped(true).show(
+                                    ui,
+                                    |ui| {
+                                        for row in m {
+                                            for v in row {
+                                                ui.monospace(format!("{v:>8.2}"));
+                                            }
+                                            ui.end_row();
+                                        }
+                                    },
+                                );
+                            }
+                            None => {
+                                ui.weak("Not placed in world space in this layout");
+                            }
+                        }
+                    }
+                    1 => {
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_salt("animation_preset")
+                                .selected_text("Presets")
+                                .show_ui(ui, |ui| {
+                                    for preset in AnimationPreset::ALL {
+                                        if ui
+                                            .selectable_label(false, format!("{preset:?}"))
+                                            .clicked()
+                                        {
+                                            obj.apply_preset(preset);
+                                        }
+                                    }
+                                });
+                            if ui.button("Clear Preset").clicked() {
+                                obj.clear_animation();
+                            }
+                        });
+                        ui.label("Rotation Speed:");
+                        let hint = format!("{:.1}", obj.rotation_speed);
+                        if ui
+                            .add(egui::TextEdit::singleline(&mut obj.speed_expr).hint_text(hint))
+                            .changed()
+                        {
+                            obj.speed_parsed = None;
+                        }
+                        if let Some(Err(e)) = obj.parsed_speed() {
+                            ui.colored_label(Color32::RED, e.to_string());
+                        }
+                        rotation_gauge(ui, obj.rotation_speed, obj.current_rotation);
+                        if ui.button("Reset Rotation").clicked() {
+                            self.requests
+                                .push(EditorRequest::ResetRotation { node_id: Some(id) });
+                        }
+
+                        ui.separator();
+                        let mut animated = obj.scale_anim.is_some();
+                        if ui.checkbox(&mut animated, "Scale Animation").changed() {
+                            obj.scale_anim = animated.then_some((0.2, 1.0));
+                        }
+                        let (mut amplitude, mut frequency) = obj.scale_anim.unwrap_or((0.2, 1.0));
+                        ui.add_enabled_ui(animated, |ui| {
+                            ui.add(Slider::new(&mut amplitude, 0.0..=1.0).text("Amplitude"));
+                            ui.add(Slider::new(&mut frequency, 0.1..=5.0).text("Frequency (Hz)"));
+                        });
+                        if animated {
+                            obj.scale_anim = Some((amplitude, frequency));
+                        }
+
+                        ui.separator();
+                        ui.label("Tracks:");
+                        animation_tracks_editor(ui, &mut obj.animations);
+
+                        ui.separator();
+                        ui.label("Script:");
+                        ui.add(
+                            egui::TextEdit::multiline(&mut obj.script)
+                                .font(egui::TextStyle::Monospace)
+                                .desired_rows(3)
+                                .hint_text("rotation_speed = sin(t) * 90"),
+                        );
+                        if ui.button("Run").clicked() {
+                            match vm::compile(&obj.script) {
+                                Ok(program) => {
+                                    obj.program = Some(program);
+                                    self.script_error = None;
+                                }
+                                Err(e) => self.script_error = Some((id, e.to_string())),
+                            }
+                        }
+                        if let Some((for_id, e)) = &self.script_error
+                            && *for_id == id
+                        {
+                            ui.colored_label(Color32::RED, e);
+                        }
+                    }
+                    2 => {
+                        ui.horizontal(|ui| {
+                            ui.label("Icon:");
+                            let current = obj.icon.as_deref().unwrap_or("Icon");
+                            ui.menu_button(current.to_owned(), |ui| {
+                                egui::Grid::new("icon_picker").show(ui, |ui| {
+                                    for (i, icon) in ICONS.iter().enumerate() {
+                                        if ui.button(*icon).clicked() {
+                                            obj.icon = Some((*icon).to_owned());
+                                            ui.close_menu();
+                                        }
+                                        if i % 6 == 5 {
+                                            ui.end_row();
+                                        }
+                                    }
+                                });
+                            });
+                            if obj.icon.is_some() && ui.button("Clear").clicked() {
+                                obj.icon = None;
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Priority:");
+                            for (level, label) in [(0, "—"), (1, "L"), (2, "M"), (3, "H")] {
+                                ui.selectable_value(&mut obj.priority, level, label);
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Badges:");
+                            for badge in BadgeKind::ALL {
+                                let on = obj.badges.contains(&badge);
+                                let room = obj.badges.len() < MAX_BADGES;
+                                let toggle = ui
+                                    .add_enabled(
+                                        on || room,
+                                        egui::SelectableLabel::new(on, badge.symbol()),
+                                    )
+                                    .on_hover_text(format!("{badge:?}"))
+                                    .on_disabled_hover_text(format!("At most {MAX_BADGES} badges"));
+                                if toggle.clicked() {
+                                    if on {
+                                        obj.badges.retain(|&b| b != badge);
+                                    } else {
+                                        obj.badges.push(badge);
+                                    }
+                                }
+                            }
+                        });
+
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            let mut gradient =
+                                matches!(obj.fill_mode, FillMode::LinearGradient { .. });
+                            if ui.radio_value(&mut gradient, false, "Solid").clicked() {
+                                obj.fill_mode = FillMode::Solid;
+                            }
+                            if ui.radio_value(&mut gradient, true, "Gradient").clicked()
+                                && obj.fill_mode == FillMode::Solid
+                            {
+                                obj.fill_mode = FillMode::LinearGradient { to: Color32::BLACK };
+                            }
+                        });
+                        if let FillMode::LinearGradient { to } = &mut obj.fill_mode {
+                            ui.horizontal(|ui| {
+                                ui.label("Gradient end:");
+                                color_edit(ui, to);
+                            });
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label("Image:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.image_path)
+                                    .hint_text("picture.png")
+                                    .desired_width(120.0),
+                            );
+                            if ui.button("Load Image").clicked() {
+                                let path = PathBuf::from(self.image_path.trim());
+                                match node_texture(&mut self.textures, ctx, &path, true) {
+                                    Ok(texture) => {
+                                        obj.texture = Some(texture);
+                                        obj.texture_path = Some(path);
+                                    }
+                                    Err(e) => {
+                                        self.toast = Some((
+                                            format!("Couldn't load {}: {e}", path.display()),
+                                            input.time,
+                                        ));
+                                    }
+                                }
+                            }
+                        });
+                        if let Some(path) =
+                            obj.texture_path.as_ref().map(|p| p.display().to_string())
+                        {
+                            ui.horizontal(|ui| {
+                                ui.weak(path);
+                                if ui.small_button("Clear Image").clicked() {
+                                    obj.texture_path = None;
+                                    obj.texture = None;
+                                }
+                            });
+                            if obj.texture.is_none() {
+                                ui.colored_label(Color32::RED, "Image couldn't be loaded");
+                            }
+                        }
+                        ui.add_enabled_ui(!obj.children.is_empty(), |ui| {
+                            ui.horizontal(|ui| {
+                                if ui.button("Paint Subtree").clicked() {
+                                    self.requests.push(EditorRequest::PaintSubtree {
+                                        node_id: id,
+                                        color: obj.color,
+                                        blend: self.paint_blend,
+                                    });
+                                }
+                                ui.checkbox(&mut self.paint_blend, "Blend");
+                            });
+                        });
+                    }
+                    _ => {
+                        if !is_root {
+                            ui.label("Edge Style:");
+                            ui.horizontal(|ui| {
+                                ui.radio_value(&mut obj.edge_style, EdgeStyle::Solid, "Solid");
+                                ui.radio_value(&mut obj.edge_style, EdgeStyle::Dashed, "Dashed");
+                                ui.radio_value(&mut obj.edge_style, EdgeStyle::Dotted, "Dotted");
+                            });
+                            ui.label("Edge Weight:");
+                            ui.add(Slider::new(&mut obj.edge_weight, 0.1..=10.0));
+                            ui.separator();
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label("Link:");
+                            ui.text_edit_singleline(&mut obj.link);
+                            if ui
+                                .add_enabled(!obj.link.is_empty(), egui::Button::new("Open"))
+                                .clicked()
+                            {
+                                open_link = Some(obj.link.trim().to_owned());
+                            }
+                        });
+
+                        ui.separator();
+                        ui.label("Step-Children (drawn here, live elsewhere):");
+                        let mut remove = None;
+                        for (i, &step_id) in obj.step_children.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("#{step_id}"));
+                                if ui.button("✖").clicked() {
+                                    remove = Some(i);
+                                }
+                            });
+                        }
+                        if let Some(i) = remove {
+                            obj.step_children.remove(i);
+                            self.layout_dirty = true;
+                        }
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut self.new_step_child).prefix("ID "));
+                            let step_id = self.new_step_child;
+                            // `path` runs from the root down to this node, so it
+                            // also rules out the node itself
+                            let addable = known.contains(&step_id)
+                                && !path.contains(&step_id)
+                                && !obj.step_children.contains(&step_id)
+                                && !obj.children.iter().any(|c| c.id == step_id);
+                            if ui
+                                .add_enabled(addable, egui::Button::new("Add Step-Child"))
+                                .on_disabled_hover_text(
+                                    "Needs the ID of another node that isn't an ancestor or child",
+                                )
+                                .clicked()
+                            {
+                                obj.step_children.push(step_id);
+                                self.layout_dirty = true;
+                            }
+                        });
+
+                        ui.separator();
+                        ui.label("On Click Events (sent along outgoing links):");
+                        let mut remove = None;
+                        for (i, event) in obj.on_click_events.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                let name = match event {
+                                    NodeEvent::SetColor(_) => "Set Color",
+                                    NodeEvent::ToggleDisabled => "Toggle Disabled",
+                                    NodeEvent::SetRotationSpeed(_) => "Set Rotation Speed",
+                                };
+                                egui::ComboBox::from_id_salt(("click_event", i))
+                                    .selected_text(name)
+                                    .show_ui(ui, |ui| {
+                                        for (kind, label) in [
+                                            (NodeEvent::SetColor(Color32::WHITE), "Set Color"),
+                                            (NodeEvent::ToggleDisabled, "Toggle Disabled"),
+                                            (
+                                                NodeEvent::SetRotationSpeed(0.0),
+                                                "Set Rotation Speed",
+                                            ),
+                                        ] {
+                                            let same = std::mem::discriminant(event)
+                                                == std::mem::discriminant(&kind);
+                                            if ui.selectable_label(same, label).clicked() && !same {
+                                                *event = kind;
+                                            }
+                                        }
+                                    });
+                                match event {
+                                    NodeEvent::SetColor(c) => {
+                                        color_edit(ui, c);
+                                    }
+                                    NodeEvent::SetRotationSpeed(v) => {
+                                        ui.add(egui::DragValue::new(v).speed(1.0));
+                                    }
+                                    NodeEvent::ToggleDisabled => {}
+                                }
+                                if ui.button("✖").clicked() {
+                                    remove = Some(i);
+                                }
+                            });
+                        }
+                        if let Some(i) = remove {
+                            obj.on_click_events.remove(i);
+                        }
+                        if ui.button("Add Event").clicked() {
+                            obj.on_click_events
+                                .push(NodeEvent::SetColor(Color32::WHITE));
+                        }
+                    }
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Add Child").clicked() {
+                        self.requests
+                            .push(EditorRequest::AddChild { parent_id: id });
+                    }
+                    if ui.button("Add Child (Wizard)").clicked() {
+                        let config = self.last_wizard_config.clone();
+                        let tags = config.tags.join(", ");
+                        self.wizard = Some((id, config, tags));
+                    }
+                    if ui.button("Delete Node").clicked() {
+                        self.requests
+                            .push(EditorRequest::DeleteNode { node_id: id });
+                        self.active_settings_id = None;
+                    }
+                    if ui
+                        .add_enabled(parent_id.is_some(), egui::Button::new("Merge into Parent"))
+                        .clicked()
+                        && let Some(keep_id) = parent_id
+                    {
+                        self.requests.push(EditorRequest::MergeNodes {
+                            keep_id,
+                            discard_id: id,
+                        });
+                        self.active_settings_id = Some(keep_id);
+                    }
+                    if ui.button("Zoom to Subtree").clicked() {
+                        self.zoom_to_subtree = Some(id);
+                    }
+                    if ui
+                        .add_enabled(!self.watch_list.contains(&id), egui::Button::new("Watch"))
+                        .clicked()
+                    {
+                        self.watch_list.push(id);
+                    }
+                });
+                if let Some((parent_id, index, count)) = siblings {
+                    ui.horizontal(|ui| {
+                        let mut move_to = |new_index| {
+                            self.requests.push(EditorRequest::MoveChild {
+                                parent_id,
+                                child_id: id,
+                                new_index,
+                            });
+                        };
+                        if ui
+                            .add_enabled(index > 0, egui::Button::new("Move Up"))
+                            .clicked()
+                        {
+                            move_to(index - 1);
+                        }
+                        if ui
+                            .add_enabled(index + 1 < count, egui::Button::new("Move Down"))
+                            .clicked()
+                        {
+                            move_to(index + 1);
+                        }
+                    });
+                }
+                if !obj.children.is_empty() {
+                    ui.separator();
+                    ui.label("Children (drag to reorder):");
+                    // a drop over a row's top half lands above it, the bottom half below
+                    let mut drop = None;
+                    for (i, c) in obj.children.iter().enumerate() {
+                        let label =
+                            format!("{} #{}", c.text.lines().next().unwrap_or_default(), c.id);
+                        let row = ui
+                            .dnd_drag_source(egui::Id::new(("child_row", c.id)), c.id, |ui| {
+                                ui.label(label)
+                            })
+                            .response;
+                        if let Some(dragged) = row.dnd_hover_payload::<u32>()
+                            && let Some(from) = obj.children.iter().position(|c| c.id == *dragged)
+                            && let Some(pointer) = ui.ctx().pointer_latest_pos()
+                        {
+                            let gap = if pointer.y < row.rect.center().y {
+                                i
+                            } else {
+                                i + 1
+                            };
+                            let y = if gap == i {
+                                row.rect.top()
+                            } else {
+                                row.rect.bottom()
+                            };
+                            let stroke = Stroke::new(2.0, ui.visuals().selection.bg_fill);
+                            ui.painter().hline(row.rect.x_range(), y, stroke);
+                            if row.dnd_release_payload::<u32>().is_some() {
+                                // taking the child out first shifts the gaps below it up
+                                let new_index = if from < gap { gap - 1 } else { gap };
+                                drop = (new_index != from).then_some((*dragged, new_index));
+                            }
+                        }
+                    }
+                    if let Some((child_id, new_index)) = drop {
+                        self.requests.push(EditorRequest::MoveChild {
+                            parent_id: id,
+                            child_id,
+                            new_index,
+                        });
+                    }
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.prefab_name)
+                            .hint_text("prefab name")
+                            .desired_width(120.0),
+                    );
+                    let name = self.prefab_name.trim();
+                    if ui
+                        .add_enabled(!name.is_empty(), egui::Button::new("Save as Prefab"))
+                        .clicked()
+                    {
+                        let mut root = obj.clone();
+                        root.is_prefab_instance = None;
+                        self.prefabs.insert(name.to_owned(), vec![root]);
+                        save_prefabs = true;
+                    }
+                });
+                let mut names: Vec<&String> = self.prefabs.keys().collect();
+                names.sort();
+                ui.add_enabled_ui(!names.is_empty(), |ui| {
+                    ui.menu_button("Insert Prefab", |ui| {
+                        for name in names {
+                            if ui.button(name).clicked() {
+                                insert_prefab = Some(name.clone());
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+                if let Some(status) = &self.prefab_status {
+                    ui.label(status);
+                }
+
+                // widgets don't report changes anywhere central, so go by what
+                // has keyboard focus or is being dragged while input arrives
+                let editing = input.pointer.is_decidedly_dragging()
+                    || input.events.iter().any(|e| {
+                        matches!(
+                            e,
+                            egui::Event::Text(_)
+                                | egui::Event::Paste(_)
+                                | egui::Event::Key { pressed: true, .. }
+                        )
+                    });
+                let ctx = ui.ctx();
+                if editing
+                    && let Some(widget) = ctx.memory(|m| m.focused()).or_else(|| ctx.dragged_id())
+                    && ctx
+                        .read_response(widget)
+                        .is_some_and(|r| r.layer_id == ui.layer_id())
+                {
+                    self.last_changed_widget = Some(widget);
+                }
+            });
+            self.inspector_tab.insert(id, tab);
+            // record a move once the drag is over, not on every frame of it
+            if let Some(shown) = shown
+                && let pos = shown.response.rect.min
+                && self.window_positions.get(&id) != Some(&pos)
+                && !input.pointer.any_down()
+            {
+                self.window_positions.insert(id, pos);
+                save_window_pos = true;
+            }
+        }
+        let mut close_wizard = false;
+        if let Some((parent_id, config, tags)) = &mut self.wizard {
+            egui::Window::new("Add Child")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut config.name);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Shape:");
+                        ui.radio_value(&mut config.shape, ShapeKind::Square, "Square");
+                        ui.radio_value(&mut config.shape, ShapeKind::Circle, "Circle");
+                        ui.radio_value(&mut config.shape, ShapeKind::Triangle, "Triangle");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Color:");
+                        color_edit(ui, &mut config.color);
+                    });
+                    ui.add(
+                        Slider::new(&mut config.rotation_speed, -360.0..=360.0)
+                            .text("Rotation Speed"),
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label("Tags:");
+                        ui.add(egui::TextEdit::singleline(tags).hint_text("comma, separated"));
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Add").clicked() {
+                            config.tags = tags
+                                .split(',')
+                                .map(str::trim)
+                                .filter(|t| !t.is_empty())
+                                .map(str::to_owned)
+                                .collect();
+                            self.last_wizard_config = config.clone();
+                            self.requests.push(EditorRequest::AddChildConfigured {
+                                parent_id: *parent_id,
+                                config: config.clone(),
+                            });
+                            close_wizard = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            close_wizard = true;
+                        }
+                    });
+                });
+        }
+        if close_wizard {
+            self.wizard = None;
+        }
+        if let Some(link) = open_link
+            && let Err(msg) = self.follow_link(ctx, &link)
+        {
+            self.toast = Some((msg, input.time));
+        }
+        if save_window_pos && let Err(e) = self.save_config() {
+            self.toast = Some((format!("Couldn't save window position: {e}"), input.time));
+        }
+        if save_palette && let Err(e) = self.save_config() {
+            self.toast = Some((format!("Couldn't save palette: {e}"), input.time));
+        }
+        if save_prefabs {
+            self.prefab_status = Some(match self.save_config() {
+                Ok(()) => format!("Saved prefab to {CONFIG_PATH}"),
+                Err(e) => format!("Couldn't save prefab: {e}"),
+            });
+        }
+        if let Some(name) = insert_prefab
+            && let Some(parent_id) = self.active_settings_id
+            && let Some(mut nodes) = self.prefabs.get(&name).cloned()
+        {
+            self.assign_new_ids(&mut nodes);
+            for n in &mut nodes {
+                n.is_prefab_instance = Some(name.clone());
+            }
+            self.requests.push(EditorRequest::InsertChildren {
+                parent_id: Some(parent_id),
+                nodes,
+            });
+        }
+
+        // deletes play out as a shrink first and only reach the scene once it's done
+        let mut finished = vec![];
+        self.deleting_nodes.retain(|&id, remaining| {
+            *remaining -= dt;
+            if *remaining <= 0.0 {
+                finished.push(id);
+            }
+            *remaining > 0.0
+        });
+        for r in &self.requests {
+            if let EditorRequest::DeleteNode { node_id } = *r {
+                self.deleting_nodes.entry(node_id).or_insert(DELETE_SECS);
+                self.selected_ids.remove(&node_id);
+                if self.active_settings_id == Some(node_id) {
+                    self.active_settings_id = None;
+                }
+            }
+        }
+        self.requests
+            .retain(|r| !matches!(r, EditorRequest::DeleteNode { .. }));
+        self.requests.extend(
+            finished
+                .into_iter()
+                .map(|node_id| EditorRequest::DeleteNode { node_id }),
+        );
+
+        // nodes that exist before the requests run, to spot the children they add
+        let before = if self.requests.is_empty() {
+            HashSet::new()
+        } else {
+            let mut description = self.requests[0].description(&self.scene_objects);
+            if self.requests.len() > 1 {
+                description += &format!(" (+{} more)", self.requests.len() - 1);
+            }
+            self.push_undo(description);
+            self.layout_dirty = true;
+            ids_in(&self.scene_objects)
+        };
+        if self.active_settings_id != self.announced_id {
+            self.announced_id = self.active_settings_id;
+            if self.announce_selection
+                && let Some(id) = self.active_settings_id
+                && let Some(path) = find_object_path(&self.scene_objects, id)
+                && let Some(obj) = self.node_index.get(&self.scene_objects, id)
+            {
+                ctx.copy_text(format!("spoken: {}", node_description(obj, path.len() - 1)));
+            }
+        }
+
+        let outcome = process_requests(
+            &mut self.scene_objects,
+            &self.node_index,
+            &mut self.requests,
+            &mut self.next_id,
+            &self.node_types,
+            self.sound.as_ref().filter(|_| !self.mute),
+        );
+        if !outcome.errors.is_empty() {
+            self.toast = Some((outcome.errors.join("\n"), input.time));
+        }
+        if let Some(status) = outcome.status {
+            self.status_flash = Some((status, STATUS_FLASH_SECS));
+        }
+        if !before.is_empty() {
+            for o in flatten(&self.scene_objects) {
+                for c in &o.children {
+                    if before.contains(&o.id) && !before.contains(&c.id) {
+                        self.edge_spawn_progress.insert((o.id, c.id), 0.0);
+                    }
+                }
+            }
+        }
+
+        ctx.request_repaint_after(std::time::Duration::from_secs_f32(dt));
+    }
+}
+
+/* ---------------- helpers ---------------- */
+
+/// The tree the editor starts with, numbered from `next_id` like `AppState::new_id`.
+fn sample_scene(next_id: &mut u32) -> Vec<SceneObject> {
+    let mut node = |name: &str, shape, color| {
+        *next_id += 1;
+        SceneObject::new(*next_id, name, shape, color)
+    };
+    let mut root = node("Root", ShapeKind::Square, Color32::RED);
+    let mut a = node("Data", ShapeKind::Circle, Color32::BLUE);
+    let mut b = node("Render", ShapeKind::Triangle, Color32::GREEN);
+
+    a.children
+        .push(node("Mesh", ShapeKind::Square, Color32::YELLOW));
+    a.children.push(node(
+        "Texture",
+        ShapeKind::Triangle,
+        Color32::from_rgb(255, 128, 0),
+    ));
+    b.children.push(node(
+        "Shader",
+        ShapeKind::Circle,
+        Color32::from_rgb(128, 0, 255),
+    ));
+
+    root.children.push(a);
+    root.children.push(b);
+    vec![root]
+}
+
+/// The inspector's list of a node's animation tracks, each with its
+/// keyframes as `(seconds, value)` rows.
+fn animation_tracks_editor(ui: &mut egui::Ui, tracks: &mut Vec<AnimationTrack>) {
+    let mut remove = None;
+    for (i, track) in tracks.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("property")
+                    .selected_text(format!("{:?}", track.property))
+                    .show_ui(ui, |ui| {
+                        for p in AnimatableProperty::ALL {
+                            ui.selectable_value(&mut track.property, p, format!("{p:?}"));
+                        }
+                    });
+                egui::ComboBox::from_id_salt("loop_mode")
+                    .selected_text(format!("{:?}", track.loop_mode))
+                    .show_ui(ui, |ui| {
+                        for m in LoopMode::ALL {
+                            ui.selectable_value(&mut track.loop_mode, m, format!("{m:?}"));
+                        }
+                    });
+                if ui.small_button("✖").on_hover_text("Remove track").clicked() {
+                    remove = Some(i);
+                }
+            });
+            ui.indent("keyframes", |ui| {
+                let mut drop = None;
+                let mut moved = false;
+                for (k, (t, value)) in track.keyframes.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        let resp = ui.add(
+                            egui::DragValue::new(t)
+                                .speed(0.05)
+                                .range(0.0..=f32::MAX)
+                                .suffix(" s"),
+                        );
+                        // re-sorted once the drag ends, so the row stays under the pointer
+                        moved |= resp.drag_stopped() || resp.changed() && !resp.dragged();
+                        ui.add(egui::DragValue::new(value).speed(0.01));
+                        if ui.small_button("✖").clicked() {
+                            drop = Some(k);
+                        }
+                    });
+                }
+                if let Some(k) = drop {
+                    track.keyframes.remove(k);
+                }
+                if moved {
+                    track.keyframes.sort_by(|a, b| a.0.total_cmp(&b.0));
+                }
+                if ui.small_button("+ Keyframe").clicked() {
+                    let next = track
+                        .keyframes
+                        .last()
+                        .map_or((0.0, 0.0), |&(t, v)| (t + 1.0, v));
+                    track.keyframes.push(next);
+                }
+            });
+        });
+    }
+    if let Some(i) = remove {
+        tracks.remove(i);
+    }
+    if ui.button("Add Track").clicked() {
+        tracks.push(AnimationTrack {
+            property: AnimatableProperty::Rotation,
+            keyframes: vec![(0.0, 0.0), (1.0, 360.0)],
+            loop_mode: LoopMode::Loop,
+        });
+    }
+}
+
+/// One side of the Compare Scenes view: added green, removed red, changed yellow.
+/// Changed nodes are clickable and pick the node for the property table.
+fn diff_tree(
+    ui: &mut egui::Ui,
+    v: &[SceneObject],
+    diff: &SceneDiff,
+    after_side: bool,
+    picked: &mut Option<u32>,
+) {
+    for o in v {
+        let label = format!("{} #{}", o.text, o.id);
+        if diff.changed.contains(&o.id) {
+            let text = egui::RichText::new(label).color(Color32::YELLOW);
+            if ui.selectable_label(*picked == Some(o.id), text).clicked() {
+                *picked = Some(o.id);
+            }
+        } else if after_side && diff.added.contains(&o.id) {
+            ui.colored_label(Color32::GREEN, label);
+        } else if !after_side && diff.removed.contains(&o.id) {
+            ui.colored_label(Color32::RED, label);
+        } else {
+            ui.label(label);
+        }
+        if !o.children.is_empty() {
+            ui.indent(o.id, |ui| {
+                diff_tree(ui, &o.children, diff, after_side, picked)
+            });
+        }
+    }
+}
+
+fn color_edit(ui: &mut egui::Ui, color: &mut Color32) -> bool {
+    let rgba = color.to_array();
+    let mut col = [
+        rgba[0] as f32 / 255.0,
+        rgba[1] as f32 / 255.0,
+        rgba[2] as f32 / 255.0,
+        rgba[3] as f32 / 255.0,
+    ];
+    let changed = ui.color_edit_button_rgba_unmultiplied(&mut col).changed();
+    if changed {
+        *color = Color32::from_rgba_unmultiplied(
+            (col[0] * 255.0) as u8,
+            (col[1] * 255.0) as u8,
+            (col[2] * 255.0) as u8,
+            (col[3] * 255.0) as u8,
+        );
+    }
+    changed
+}
+
+fn lerp_color(a: Color32, b: Color32, t: f32) -> Color32 {
+    let l = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t).round() as u8;
+    let (a, b) = (a.to_array(), b.to_array());
+    Color32::from_rgba_premultiplied(l(a[0], b[0]), l(a[1], b[1]), l(a[2], b[2]), l(a[3], b[3]))
+}
+
+/// Depth-first, pre-order walk calling `f(node, depth)` for every node.
+pub fn visit<F: FnMut(&SceneObject, u32)>(v: &[SceneObject], depth: u32, f: &mut F) {
+    for o in v {
+        f(o, depth);
+        visit(&o.children, depth + 1, f);
+    }
+}
+
+/// Mutable counterpart of [`visit`]. `f` runs on a node before its children.
+pub fn visit_mut<F: FnMut(&mut SceneObject, u32)>(v: &mut [SceneObject], depth: u32, f: &mut F) {
+    for o in v {
+        f(o, depth);
+        visit_mut(&mut o.children, depth + 1, f);
+    }
+}
+
+/// All nodes in depth-first, pre-order.
+pub fn flatten(v: &[SceneObject]) -> Vec<&SceneObject> {
+    fn walk<'a>(v: &'a [SceneObject], out: &mut Vec<&'a SceneObject>) {
+        for o in v {
+            out.push(o);
+            walk(&o.children, out);
+        }
+    }
+    let mut out = vec![];
+    walk(v, &mut out);
+    out
+}
+
+/// Mutable counterpart of [`flatten`]. A node and its children can't be borrowed
+/// mutably at once, so this returns child-index paths in the same order as
+/// `flatten`; resolve them one at a time with [`object_at_path_mut`].
+pub fn flatten_mut(v: &mut [SceneObject]) -> Vec<Vec<usize>> {
+    fn walk(v: &[SceneObject], prefix: &mut Vec<usize>, out: &mut Vec<Vec<usize>>) {
+        for (i, o) in v.iter().enumerate() {
+            prefix.push(i);
+            out.push(prefix.clone());
+            walk(&o.children, prefix, out);
+            prefix.pop();
+        }
+    }
+    let mut out = vec![];
+    walk(v, &mut vec![], &mut out);
+    out
+}
+
+pub fn object_at_path<'a>(v: &'a [SceneObject], path: &[usize]) -> Option<&'a SceneObject> {
+    let (&first, rest) = path.split_first()?;
+    let o = v.get(first)?;
+    if rest.is_empty() {
+        Some(o)
+    } else {
+        object_at_path(&o.children, rest)
+    }
+}
+
+pub fn object_at_path_mut<'a>(
+    v: &'a mut [SceneObject],
+    path: &[usize],
+) -> Option<&'a mut SceneObject> {
+    let (&first, rest) = path.split_first()?;
+    let o = v.get_mut(first)?;
+    if rest.is_empty() {
+        Some(o)
+    } else {
+        object_at_path_mut(&mut o.children, rest)
+    }
+}
+
+fn ids_in(v: &[SceneObject]) -> HashSet<u32> {
+    flatten(v).iter().map(|o| o.id).collect()
+}
+
+/// Runs frame scripts, then advances every node's rotation; `speed` scales all of
+/// them at once. Unparsable speed expressions fall back to `rotation_speed`.
+pub fn animate(v: &mut [SceneObject], speed: f32, env: &vm::Env) {
+    animate_nodes(v, speed, env, None);
+}
+
+/// `animate` with the rotations advanced by `compute` instead, which lands
+/// them a frame or so late; see `wgpu_compute`.
+pub fn animate_on_gpu(
+    v: &mut [SceneObject],
+    speed: f32,
+    env: &vm::Env,
+    compute: &mut wgpu_compute::RotationCompute,
+) {
+    let mut speeds = vec![];
+    animate_nodes(v, speed, env, Some(&mut speeds));
+    compute.step(v, &speeds, env.dt);
+}
+
+/// With `speeds`, each node's speed (zero if disabled) is pushed there in
+/// `visit` order instead of being applied to its rotation.
+fn animate_nodes(
+    v: &mut [SceneObject],
+    speed: f32,
+    env: &vm::Env,
+    mut speeds: Option<&mut Vec<f32>>,
+) {
+    visit_mut(v, 0, &mut |o, _| {
+        if !o.script.is_empty() {
+            // scenes loaded from disk haven't been through the Run button yet
+            if o.program.is_none() {
+                o.program = vm::compile(&o.script).ok();
+            }
+            // taken out while it runs, since the run writes back into `o`
+            if let Some(program) = o.program.take() {
+                run_script(&program, env, o);
+                o.program = Some(program);
+            }
+        }
+        let node_speed = match o.parsed_speed() {
+            Some(Ok(e)) => e.eval(env.t),
+            _ => o.rotation_speed,
+        };
+        let node_speed = if o.disabled { 0.0 } else { node_speed * speed };
+        match speeds.as_deref_mut() {
+            Some(speeds) => speeds.push(node_speed),
+            None => o.current_rotation += node_speed * env.dt,
+        }
+        o.animated = AnimatedValues::default();
+        if !o.disabled && !o.animations.is_empty() {
+            for track in std::mem::take(&mut o.animations) {
+                if let Some(value) = track.value_at(env.t) {
+                    track.property.apply(o, value);
+                }
+                o.animations.push(track);
+            }
+        }
+        if !o.disabled
+            && let Some(target) = o.morph_target
+        {
+            o.morph_progress += o.rotation_speed.abs() * 0.01 * speed * env.dt;
+            if o.morph_progress >= 1.0 {
+                o.shape = target;
+                o.morph_target = None;
+                o.morph_progress = 0.0;
+            }
+        }
+    });
+}
+
+/// Sets every recorded node's rotation to its keyframes `elapsed` seconds in,
+/// counted from the earliest keyframe of any node. Returns whether the
+/// recording has any left to play.
+fn play_keyframes(v: &mut [SceneObject], elapsed: f32) -> bool {
+    let (mut start, mut end) = (f32::INFINITY, f32::NEG_INFINITY);
+    visit(v, 0, &mut |o, _| {
+        if let (Some(first), Some(last)) = (o.keyframes.first(), o.keyframes.last()) {
+            start = start.min(first.0);
+            end = end.max(last.0);
+        }
+    });
+    let t = start + elapsed;
+    visit_mut(v, 0, &mut |o, _| {
+        if let Some(rotation) = sample_keyframes(&o.keyframes, t) {
+            o.current_rotation = rotation;
+        }
+    });
+    t < end
+}
+
+/// The value at `t`, straight-line between the keyframes either side and held
+/// past the ends.
+fn sample_keyframes(keyframes: &[(f32, f32)], t: f32) -> Option<f32> {
+    let i = keyframes.partition_point(|&(kt, _)| kt <= t);
+    match (i.checked_sub(1).map(|i| keyframes[i]), keyframes.get(i)) {
+        (Some((t0, a)), Some(&(t1, b))) => Some(a + (b - a) * (t - t0) / (t1 - t0)),
+        (Some((_, a)), None) => Some(a),
+        (None, next) => next.map(|&(_, b)| b),
+    }
+}
+
+fn run_script(program: &vm::Program, env: &vm::Env, o: &mut SceneObject) {
+    let [r, g, b, a] = o.color.to_array();
+    let mut regs = vm::Registers {
+        rotation_speed: o.rotation_speed,
+        color: [r as f32, g as f32, b as f32],
+        scale: o.scale,
+    };
+    program.run(env, &mut regs);
+    let channel = |c: f32| c.clamp(0.0, 255.0) as u8;
+    o.rotation_speed = regs.rotation_speed;
+    o.color = Color32::from_rgba_premultiplied(
+        channel(regs.color[0]),
+        channel(regs.color[1]),
+        channel(regs.color[2]),
+        a,
+    );
+    o.scale = regs.scale.max(0.0);
+}
+
+/// `remaining_depth` is how many more levels the ancestors' `expand_depth` lets
+/// through; `None` is unlimited.
+#[allow(clippy::too_many_arguments)]
+fn layout_recursive(
+    o: &SceneObject,
+    x: f32,
+    y: f32,
+    cur: &mut f32,
+    m: &mut HashMap<u32, egui::Vec2>,
+    current_depth: u32,
+    remaining_depth: Option<u32>,
+    lc: &LayoutCtx,
+) -> f32 {
+    if !layer_visible(o, lc.visible_layers) {
+        return 0.0;
+    }
+    // spacing grows with the node so big proportional nodes don't overlap
+    let k = (node_size(lc.sizes, o.id) / NODE_SIZE).max(1.0);
+    let xs = 250.0 * k;
+    let ys = 120.0 * k;
+    let open = openness(lc.collapse, o.id);
+    let remaining = depth_budget(remaining_depth, o);
+    let expand =
+        current_depth < lc.max_depth.unwrap_or(u32::MAX) && remaining != Some(0) && open > 0.0;
+    let mut th = 0.0;
+    let mut cy = y;
+    if expand {
+        let below = remaining.map(|d| d - 1);
+        for c in &o.children {
+            th += layout_recursive(c, x + xs, cy, &mut cy, m, current_depth + 1, below, lc);
+        }
+        // step-children take no slot here: they're drawn where their true
+        // parent puts them, with only the connector running back to this node
+        if open < 1.0 {
+            // squash the subtree into the parent's slot as it folds away
+            visit(&o.children, 0, &mut |d, _| {
+                if let Some(p) = m.get_mut(&d.id) {
+                    p.y = y + (p.y - y) * open;
+                }
+            });
+            th *= open;
+        }
+    }
+    if o.pinned_to_screen {
+        // pinned nodes live in screen space and take no slot in the layout
+        if th > 0.0 {
+            *cur = y + th;
+        }
+        return th;
+    }
+    let p = if expand && !o.children.is_empty() {
+        egui::Vec2::new(x, y + th.max(ys) / 2.0 - ys / 2.0)
+    } else {
+        egui::Vec2::new(x, *cur)
+    };
+    m.insert(o.id, p);
+    let h = th.max(ys);
+    *cur = y + h;
+    h
+}
+
+/// Vertical distance between the rows of `layout_folded_rows`.
+const ROW_HEIGHT: f32 = 120.0;
+
+/// Puts every shown node at depth `d` in row `d`, spaced evenly across
+/// `canvas_width` in tree order. Hidden, folded and depth-limited nodes are
+/// left out the same way `layout_recursive` leaves them out.
+fn layout_folded_rows(
+    v: &[SceneObject],
+    canvas_width: f32,
+    m: &mut HashMap<u32, egui::Vec2>,
+    lc: &LayoutCtx,
+) {
+    fn collect(
+        o: &SceneObject,
+        depth: usize,
+        remaining_depth: Option<u32>,
+        rows: &mut Vec<Vec<u32>>,
+        lc: &LayoutCtx,
+    ) {
+        if !layer_visible(o, lc.visible_layers) {
+            return;
+        }
+        if !o.pinned_to_screen {
+            if rows.len() <= depth {
+                rows.resize_with(depth + 1, Vec::new);
+            }
+            rows[depth].push(o.id);
+        }
+        let remaining = depth_budget(remaining_depth, o);
+        if (depth as u32) < lc.max_depth.unwrap_or(u32::MAX)
+            && remaining != Some(0)
+            && openness(lc.collapse, o.id) > 0.0
+        {
+            let below = remaining.map(|d| d - 1);
+            for c in &o.children {
+                collect(c, depth + 1, below, rows, lc);
+            }
+        }
+    }
+    let mut rows = vec![];
+    for o in v {
+        collect(o, 0, None, &mut rows, lc);
+    }
+    for (depth, row) in rows.iter().enumerate() {
+        let step = canvas_width / row.len() as f32;
+        let y = 100.0 + depth as f32 * ROW_HEIGHT;
+        for (i, &id) in row.iter().enumerate() {
+            m.insert(id, egui::Vec2::new(step * (i as f32 + 0.5), y));
+        }
+    }
+}
+
+/// Radius of the first ring of children; later rings are wider so fans don't collide.
+const CIRCLE_RADIUS: f32 = 180.0;
+/// Spread of a non-root fan, centred on the direction away from the grandparent.
+const ARC_ANGLE: f32 = std::f32::consts::PI;
+
+/// Roots sit on one big ring (or at the origin when there's only one), each
+/// with its subtree fanned out around it.
+fn layout_circular_roots(v: &[SceneObject], m: &mut HashMap<u32, egui::Vec2>, lc: &LayoutCtx) {
+    let n = v.len();
+    let ring = if n > 1 {
+        CIRCLE_RADIUS * 2.0 * n as f32 / std::f32::consts::PI
+    } else {
+        0.0
+    };
+    for (i, o) in v.iter().enumerate() {
+        let angle = i as f32 / n as f32 * std::f32::consts::TAU;
+        let p = egui::Vec2::angled(angle) * ring;
+        layout_circular(o, p, None, m, 0, None, lc);
+    }
+}
+
+/// Places `o` at `p` and its children on an arc around it. `outward` is the
+/// direction from the parent; roots have none and use the full circle.
+fn layout_circular(
+    o: &SceneObject,
+    p: egui::Vec2,
+    outward: Option<f32>,
+    m: &mut HashMap<u32, egui::Vec2>,
+    current_depth: u32,
+    remaining_depth: Option<u32>,
+    lc: &LayoutCtx,
+) {
+    if !layer_visible(o, lc.visible_layers) {
+        return;
+    }
+    if !o.pinned_to_screen {
+        m.insert(o.id, p);
+    }
+    let open = openness(lc.collapse, o.id);
+    let remaining = depth_budget(remaining_depth, o);
+    if current_depth >= lc.max_depth.unwrap_or(u32::MAX)
+        || remaining == Some(0)
+        || o.children.is_empty()
+        || open <= 0.0
+    {
+        return;
+    }
+    let k = (node_size(lc.sizes, o.id) / NODE_SIZE).max(1.0);
+    let radius = CIRCLE_RADIUS * k * (1.0 + 0.5 * current_depth as f32) * open;
+    let (arc, base) = match outward {
+        Some(dir) => (ARC_ANGLE, dir - ARC_ANGLE / 2.0),
+        None => (std::f32::consts::TAU, 0.0),
+    };
+    let n = o.children.len() as f32;
+    for (i, c) in o.children.iter().enumerate() {
+        let angle = (i as f32 + 0.5) / n * arc + base;
+        let cp = p + egui::Vec2::angled(angle) * radius;
+        let below = remaining.map(|d| d - 1);
+        layout_circular(c, cp, Some(angle), m, current_depth + 1, below, lc);
+    }
+}
+
+/// The tighter of the depth budget handed down from above and the node's own
+/// `expand_depth`.
+fn depth_budget(remaining_depth: Option<u32>, o: &SceneObject) -> Option<u32> {
+    match (remaining_depth, o.expand_depth) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Per-frame settings shared by every `layout_recursive` call.
+struct LayoutCtx<'a> {
+    max_depth: Option<u32>,
+    /// Draw size per node when proportional sizing is on.
+    sizes: Option<&'a HashMap<u32, f32>>,
+    visible_layers: u8,
+    collapse: &'a HashMap<u32, f32>,
+}
+
+/// One Hooke's-law step pulling each node in `positions` toward its slot in
+/// `layout`. New nodes start on their slot; locked nodes stay where they are.
+fn step_springs(
+    layout: &HashMap<u32, egui::Vec2>,
+    positions: &mut HashMap<u32, egui::Vec2>,
+    velocities: &mut HashMap<u32, egui::Vec2>,
+    locked: &HashSet<u32>,
+    k: f32,
+    dt: f32,
+) {
+    positions.retain(|id, _| layout.contains_key(id));
+    velocities.retain(|id, _| layout.contains_key(id));
+    for (&id, &target) in layout {
+        let current = positions.entry(id).or_insert(target);
+        if locked.contains(&id) {
+            continue;
+        }
+        let velocity = velocities.entry(id).or_default();
+        let force = (target - *current) * k - *velocity * SPRING_DAMPING;
+        *velocity += force * dt;
+        *current += *velocity * dt;
+    }
+}
+
+/// The visible part of the scene as the force layout sees it.
+#[derive(Default)]
+struct ForceGraph {
+    /// Each node with the position it's held at, if it isn't free to move:
+    /// where it was dragged to, or (for locked nodes) wherever it already is.
+    nodes: Vec<(u32, Option<egui::Vec2>, bool)>,
+    /// Parent → child springs, with the child's `edge_weight` as the stiffness.
+    edges: Vec<(u32, u32, f32)>,
+}
+
+impl ForceGraph {
+    /// Adds `v` and whatever of their subtrees is expanded, the same nodes the
+    /// tree layout would place.
+    fn collect(
+        &mut self,
+        v: &[SceneObject],
+        lc: &LayoutCtx,
+        parent: Option<u32>,
+        depth: u32,
+        remaining_depth: Option<u32>,
+    ) {
+        for o in v {
+            if !layer_visible(o, lc.visible_layers) || o.pinned_to_screen {
+                continue;
+            }
+            self.nodes.push((o.id, o.position_override, o.locked));
+            if let Some(p) = parent {
+                self.edges.push((p, o.id, o.edge_weight));
+            }
+            let remaining = depth_budget(remaining_depth, o);
+            if depth < lc.max_depth.unwrap_or(u32::MAX)
+                && remaining != Some(0)
+                && openness(lc.collapse, o.id) > 0.0
+            {
+                let below = remaining.map(|d| d - 1);
+                self.collect(&o.children, lc, Some(o.id), depth + 1, below);
+            }
+        }
+    }
+}
+
+/// One Fruchterman-Reingold step: every pair of nodes without an edge between
+/// them pushes apart, every edge pulls its ends together, and each node then
+/// moves along its net force by at most `FD_MAX_STEP * cooling`. New nodes start
+/// next to their parent.
+fn step_force_layout(graph: &ForceGraph, positions: &mut HashMap<u32, egui::Vec2>, cooling: f32) {
+    let ids: HashSet<u32> = graph.nodes.iter().map(|&(id, ..)| id).collect();
+    positions.retain(|id, _| ids.contains(id));
+    let mut rng = rand::rng();
+    let parents: HashMap<u32, u32> = graph.edges.iter().map(|&(p, c, _)| (c, p)).collect();
+    // nodes are in tree order, so a new node's parent is placed before it is
+    for &(id, ..) in &graph.nodes {
+        if !positions.contains_key(&id) {
+            let jitter =
+                egui::Vec2::new(rng.random_range(-1.0..=1.0), rng.random_range(-1.0..=1.0));
+            let start = match parents.get(&id).and_then(|p| positions.get(p)) {
+                Some(&parent) => parent + jitter * FD_SPACING * 0.5,
+                None => jitter * FD_SPACING,
+            };
+            positions.insert(id, start);
+        }
+    }
+
+    let connected: HashSet<(u32, u32)> = graph
+        .edges
+        .iter()
+        .flat_map(|&(a, b, _)| [(a, b), (b, a)])
+        .collect();
+    let mut push = HashMap::<u32, egui::Vec2>::new();
+    for (i, &(a, ..)) in graph.nodes.iter().enumerate() {
+        for &(b, ..) in &graph.nodes[i + 1..] {
+            if connected.contains(&(a, b)) {
+                continue;
+            }
+            let delta = positions[&a] - positions[&b];
+            let d = delta.length().max(1.0);
+            let f = delta / d * (FD_SPACING * FD_SPACING / d);
+            *push.entry(a).or_default() += f;
+            *push.entry(b).or_default() -= f;
+        }
+    }
+    for &(a, b, weight) in &graph.edges {
+        let delta = positions[&b] - positions[&a];
+        let d = delta.length().max(1.0);
+        let f = delta / d * (weight * d * d / FD_SPACING);
+        *push.entry(a).or_default() += f;
+        *push.entry(b).or_default() -= f;
+    }
+
+    let max_step = FD_MAX_STEP * cooling;
+    for &(id, held, locked) in &graph.nodes {
+        let p = positions.entry(id).or_default();
+        if let Some(held) = held {
+            *p = held;
+        } else if !locked && let Some(&f) = push.get(&id) {
+            *p += f.normalized() * f.length().min(max_step);
+        }
+    }
+}
+
+/// How open a node's subtree is, from `AppState::collapse_anim`.
+fn openness(collapse: &HashMap<u32, f32>, id: u32) -> f32 {
+    collapse.get(&id).copied().unwrap_or(1.0)
+}
+
+fn layer_visible(o: &SceneObject, visible_layers: u8) -> bool {
+    visible_layers & (1 << (o.layer & 7)) != 0
+}
+
+/// Fake perspective divide about `eye`: returns the projected world position and
+/// how much to scale the node's billboard.
+fn project_3d(p: egui::Vec2, z_offset: f32, eye: egui::Vec2) -> (egui::Vec2, f32) {
+    let depth = (p.x + z_offset) * 0.01;
+    let scale = 1.0 / (1.0 + depth * PERSPECTIVE).max(0.2);
+    (eye + (p - eye) * scale, scale)
+}
+
+fn node_size(sizes: Option<&HashMap<u32, f32>>, id: u32) -> f32 {
+    sizes.and_then(|m| m.get(&id)).copied().unwrap_or(NODE_SIZE)
+}
+
+/// Records the node count of every subtree in `v`, returning the total.
+fn subtree_counts(v: &[SceneObject], out: &mut HashMap<u32, usize>) -> usize {
+    v.iter()
+        .map(|o| {
+            let n = 1 + subtree_counts(&o.children, out);
+            out.insert(o.id, n);
+            n
+        })
+        .sum()
+}
+
+/// Maps each node's subtree node count onto 20..80 px on a log scale.
+fn proportional_sizes(v: &[SceneObject]) -> HashMap<u32, f32> {
+    let mut counts = HashMap::new();
+    let total = subtree_counts(v, &mut counts).max(2) as f32;
+    counts
+        .into_iter()
+        .map(|(id, n)| (id, 20.0 + 60.0 * (n as f32).ln() / total.ln()))
+        .collect()
+}
+
+/// Height of the label strip a treemap parent keeps above its children.
+const TREEMAP_HEADER: f32 = 18.0;
+
+/// Squarified treemap: lays `v` out inside `rect`, each subtree getting an area
+/// proportional to its node count. Rects are in screen space.
+fn layout_treemap(
+    v: &[SceneObject],
+    rect: Rect,
+    out: &mut HashMap<u32, Rect>,
+    current_depth: u32,
+    remaining_depth: Option<u32>,
+    lc: &LayoutCtx,
+) {
+    let mut counts = HashMap::new();
+    subtree_counts(v, &mut counts);
+    let mut items: Vec<(usize, f32)> = v
+        .iter()
+        .enumerate()
+        .filter(|(_, o)| layer_visible(o, lc.visible_layers))
+        .map(|(i, o)| (i, counts[&o.id] as f32))
+        .collect();
+    items.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    for (i, r) in squarify(&items, rect) {
+        let o = &v[i];
+        out.insert(o.id, r);
+        let remaining = depth_budget(remaining_depth, o);
+        let expand = current_depth < lc.max_depth.unwrap_or(u32::MAX)
+            && remaining != Some(0)
+            && openness(lc.collapse, o.id) > 0.0;
+        if expand && !o.children.is_empty() && r.height() > TREEMAP_HEADER * 2.0 {
+            let inner = Rect::from_min_max(
+                Pos2::new(r.min.x + 2.0, r.min.y + TREEMAP_HEADER),
+                Pos2::new(r.max.x - 2.0, r.max.y - 2.0),
+            );
+            let below = remaining.map(|d| d - 1);
+            layout_treemap(&o.children, inner, out, current_depth + 1, below, lc);
+        }
+    }
+}
+
+/// Splits `rect` into tiles for the (index, weight) pairs, which must be sorted
+/// by descending weight. Rows are grown along the short side while that keeps
+/// improving the worst aspect ratio.
+fn squarify(items: &[(usize, f32)], rect: Rect) -> Vec<(usize, Rect)> {
+    let total: f32 = items.iter().map(|i| i.1).sum();
+    if total <= 0.0 || rect.area() <= 0.0 {
+        return vec![];
+    }
+    let scale = rect.area() / total;
+    let areas: Vec<(usize, f32)> = items.iter().map(|&(i, w)| (i, w * scale)).collect();
+
+    let worst = |row: &[(usize, f32)], side: f32| {
+        let s: f32 = row.iter().map(|r| r.1).sum();
+        row.iter()
+            .map(|r| (side * side * r.1 / (s * s)).max(s * s / (side * side * r.1)))
+            .fold(0.0, f32::max)
+    };
+
+    let mut out = vec![];
+    let mut rest = rect;
+    let mut row: Vec<(usize, f32)> = vec![];
+    let mut flush = |row: &mut Vec<(usize, f32)>, rest: &mut Rect| {
+        let s: f32 = row.iter().map(|r| r.1).sum();
+        if rest.width() >= rest.height() {
+            // column on the left
+            let w = s / rest.height();
+            let mut y = rest.min.y;
+            for &(i, a) in row.iter() {
+                let h = a / w;
+                out.push((
+                    i,
+                    Rect::from_min_size(Pos2::new(rest.min.x, y), egui::Vec2::new(w, h)),
+                ));
+                y += h;
+            }
+            rest.min.x += w;
+        } else {
+            // row along the top
+            let h = s / rest.width();
+            let mut x = rest.min.x;
+            for &(i, a) in row.iter() {
+                let w = a / h;
+                out.push((
+                    i,
+                    Rect::from_min_size(Pos2::new(x, rest.min.y), egui::Vec2::new(w, h)),
+                ));
+                x += w;
+            }
+            rest.min.y += h;
+        }
+        row.clear();
+    };
+
+    for &item in &areas {
+        let side = rest.width().min(rest.height());
+        let mut candidate = row.clone();
+        candidate.push(item);
+        if row.is_empty() || worst(&candidate, side) <= worst(&row, side) {
+            row = candidate;
+        } else {
+            flush(&mut row, &mut rest);
+            row.push(item);
+        }
+    }
+    if !row.is_empty() {
+        flush(&mut row, &mut rest);
+    }
+    out
+}
+
+/// Treemap rendering: filled tiles with a white border, no connectors.
+fn draw_treemap<F>(dc: &DrawCtx<'_, F>, v: &[SceneObject], rects: &HashMap<u32, Rect>) {
+    for o in v {
+        let Some(&r) = rects.get(&o.id) else {
+            continue;
+        };
+        let stroke = if dc.selected.contains(&o.id) {
+            Stroke::new(2.0, Color32::LIGHT_BLUE)
+        } else {
+            Stroke::new(1.0, Color32::WHITE)
+        };
+        dc.painter.rect(
+            r,
+            0.0,
+            dc.accessibility.simulate(o.color),
+            stroke,
+            egui::StrokeKind::Inside,
+        );
+        let has_tiles = o.children.iter().any(|c| rects.contains_key(&c.id));
+        let label_pos = if has_tiles {
+            Pos2::new(r.center().x, r.min.y + TREEMAP_HEADER / 2.0)
+        } else {
+            r.center()
+        };
+        if dc.renaming != Some(o.id) {
+            dc.painter.text(
+                label_pos,
+                egui::Align2::CENTER_CENTER,
+                &o.text,
+                FontId::proportional(14.0),
+                Color32::WHITE,
+            );
+        }
+        draw_treemap(dc, &o.children, rects);
+    }
+}
+
+/// Per-frame inputs shared by every `draw_world` call.
+struct DrawCtx<'a, F> {
+    painter: &'a egui::Painter,
+    /// The whole scene, for resolving references.
+    objects: &'a [SceneObject],
+    index: &'a NodeIndex,
+    layout: &'a HashMap<u32, egui::Vec2>,
+    to_screen: &'a F,
+    max_depth: Option<u32>,
+    sizes: Option<&'a HashMap<u32, f32>>,
+    selected: &'a HashSet<u32>,
+    /// Node with the inspector open; drawn with a pulsing outline.
+    active: Option<u32>,
+    pulse_phase: f32,
+    /// Node whose label is replaced by the inline rename editor.
+    renaming: Option<u32>,
+    connector: ConnectorStyle,
+    /// Recolors node fills; the nodes' own colors are left alone.
+    accessibility: AccessibilityMode,
+    label_color: Color32,
+    flow: &'a HashMap<(u32, u32), f32>,
+    spawn: &'a HashMap<(u32, u32), f32>,
+    links: &'a [(u32, u32)],
+    /// IDs matching the toolbar search; `None` while the query is empty.
+    search: Option<&'a HashSet<u32>>,
+    /// Scene clock in seconds, for scale animations.
+    time: f32,
+    visible_layers: u8,
+    collapse: &'a HashMap<u32, f32>,
+    /// Folded repeat branches and their copy counts, drawn as a "×N" badge.
+    folded: &'a HashMap<u32, usize>,
+    /// Seconds left on each shrinking delete, see `AppState::deleting_nodes`.
+    deleting: &'a HashMap<u32, f32>,
+    /// Orthogonal connector elbow shifts in world units, see `edge_elbow_offset`.
+    elbows: &'a HashMap<(u32, u32), f32>,
+    zoom: f32,
+    /// See `AppState::snap_rotation`.
+    snap_rotation: Option<f32>,
+    show_arrows: bool,
+    /// First line of each non-root node's parent, when parent labels are on.
+    parent_names: Option<&'a HashMap<u32, String>>,
+    show_health: bool,
+    /// Nodes breaking a rule, given a warning badge; see `check_rules`.
+    warnings: &'a HashSet<u32>,
+    /// High-priority nodes drawn this frame, for the glow pass; `None` when off.
+    glows: Option<&'a RefCell<Vec<wgpu_glow::Glow>>>,
+}
+
+impl<'a, F> DrawCtx<'a, F> {
+    /// The same frame seen through another camera, for the split view. The
+    /// glow pass only covers the main view, so the copy draws without it.
+    fn through<'b, G>(
+        &self,
+        painter: &'b egui::Painter,
+        to_screen: &'b G,
+        zoom: f32,
+    ) -> DrawCtx<'b, G>
+    where
+        'a: 'b,
+    {
+        DrawCtx {
+            painter,
+            to_screen,
+            zoom,
+            glows: None,
+            objects: self.objects,
+            index: self.index,
+            layout: self.layout,
+            max_depth: self.max_depth,
+            sizes: self.sizes,
+            selected: self.selected,
+            active: self.active,
+            pulse_phase: self.pulse_phase,
+            renaming: self.renaming,
+            connector: self.connector,
+            accessibility: self.accessibility,
+            label_color: self.label_color,
+            flow: self.flow,
+            spawn: self.spawn,
+            links: self.links,
+            search: self.search,
+            time: self.time,
+            visible_layers: self.visible_layers,
+            collapse: self.collapse,
+            folded: self.folded,
+            deleting: self.deleting,
+            elbows: self.elbows,
+            snap_rotation: self.snap_rotation,
+            show_arrows: self.show_arrows,
+            parent_names: self.parent_names,
+            show_health: self.show_health,
+            warnings: self.warnings,
+        }
+    }
+}
+
+fn scale_factor(o: &SceneObject, time: f32) -> f32 {
+    match o.scale_anim {
+        Some((amplitude, frequency)) => {
+            1.0 + amplitude * (time * frequency * std::f32::consts::TAU).sin()
+        }
+        None => 1.0,
+    }
+}
+
+/// `opacity` fades a subtree that is folding away.
+fn draw_world<F>(dc: &DrawCtx<'_, F>, o: &SceneObject, current_depth: u32, opacity: f32)
+where
+    F: Fn(egui::Vec2) -> Pos2,
+{
+    let painter = dc.painter;
+    let to_screen = dc.to_screen;
+    let screen_pos = |o: &SceneObject| {
+        if !layer_visible(o, dc.visible_layers) {
+            None
+        } else if o.pinned_to_screen {
+            Some(o.screen_pos)
+        } else {
+            dc.layout.get(&o.id).map(|&p| to_screen(p))
+        }
+    };
+    let open = openness(dc.collapse, o.id);
+    let expand = current_depth < dc.max_depth.unwrap_or(u32::MAX) && open > 0.0;
+    let children: &[SceneObject] = if expand { &o.children } else { &[] };
+    let child_opacity = opacity * open;
+    let Some(center) = screen_pos(o) else {
+        return;
+    };
+
+    for c in children {
+        if let Some(cp) = screen_pos(c) {
+            let elbow = dc
+                .elbows
+                .get(&(o.id, c.id))
+                .map_or(0.0, |off| off * dc.zoom);
+            let mut points = connector_points(dc.connector, center, cp, elbow);
+            let spawning = dc.spawn.get(&(o.id, c.id));
+            if let Some(&progress) = spawning {
+                points = truncate_polyline(&points, progress);
+            }
+            let edge_color = Color32::GRAY.gamma_multiply(child_opacity);
+            draw_edge(
+                painter,
+                &points,
+                c.edge_style,
+                Stroke::new(1.0 + c.edge_weight * 0.5, edge_color),
+            );
+            if dc.show_arrows {
+                // a growing edge carries the arrow on its leading end; a finished
+                // one stops it at the child's edge so the shape doesn't hide it
+                let inset = match spawning {
+                    Some(_) => 0.0,
+                    None => node_size(dc.sizes, c.id) * c.shown_scale() * 0.5,
+                };
+                draw_arrowhead(painter, &points, inset, dc.zoom, edge_color);
+            }
+            if let Some(&phase) = dc.flow.get(&(o.id, c.id)) {
+                for offset in FLOW_DOTS {
+                    let p = point_along(&points, (phase + offset).fract());
+                    painter.circle_filled(p, 3.0, dc.accessibility.simulate(o.shown_color()));
+                }
+            }
+        }
+    }
+
+    if expand {
+        for &id in &o.step_children {
+            if let Some(c) = dc.index.get(dc.objects, id)
+                && let Some(cp) = screen_pos(c)
+            {
+                let points = connector_points(dc.connector, center, cp, 0.0);
+                let color = STEP_CHILD_COLOR.gamma_multiply(child_opacity);
+                draw_edge(painter, &points, EdgeStyle::Dashed, Stroke::new(1.5, color));
+            }
+        }
+    }
+
+    for &(_, target) in dc.links.iter().filter(|(src, _)| *src == o.id) {
+        if let Some(&tp) = dc.layout.get(&target) {
+            draw_link(
+                painter,
+                center,
+                to_screen(tp),
+                node_size(dc.sizes, target) * 0.5,
+            );
+        }
+    }
+
+    let shrink = dc
+        .deleting
+        .get(&o.id)
+        .map_or(1.0, |remaining| remaining / DELETE_SECS);
+    let sz = node_size(dc.sizes, o.id) * o.shown_scale() * scale_factor(o, dc.time) * shrink;
+    // everything but the matches fades out while a search is active
+    let dim = dc.search.is_some_and(|m| !m.contains(&o.id));
+    let alpha = if dim || o.disabled { 0.4 } else { 1.0 } * opacity * o.shown_opacity();
+    let fade = |c: Color32| dc.accessibility.simulate(c).gamma_multiply(alpha);
+    let (shape, color) = resolve_appearance(dc.objects, dc.index, o);
+    let fill = fade(color);
+    let rotation = snapped_rotation(o.current_rotation, dc.snap_rotation);
+    if o.priority >= 3
+        && let Some(glows) = dc.glows
+    {
+        glows.borrow_mut().push(wgpu_glow::Glow {
+            center,
+            size: sz,
+            rotation_deg: match shape {
+                ShapeKind::Triangle => rotation,
+                _ => 0.0,
+            },
+            color: fill,
+            shape: match shape {
+                ShapeKind::Square | ShapeKind::Reference { .. } => 0,
+                ShapeKind::Circle => 1,
+                ShapeKind::Triangle => 2,
+            },
+        });
+    }
+    if let Some(target) = o.morph_target.filter(|&t| t != shape) {
+        draw_morph(painter, o, shape, target, center, sz, rotation, fill);
+    } else {
+        // progress: the unfilled share shows a dark copy of the shape, and the fill
+        // is clipped to the left (or pie-sliced, for circles)
+        let partial = o.fill_ratio < 1.0;
+        let clipped = (partial && shape != ShapeKind::Circle).then(|| {
+            let left = center.x - sz / 2.0;
+            let keep = Rect::from_x_y_ranges(
+                left..=left + sz * o.fill_ratio.max(0.0),
+                center.y - sz..=center.y + sz,
+            );
+            painter.with_clip_rect(keep.intersect(painter.clip_rect()))
+        });
+        if partial {
+            draw_plain_shape(painter, o, shape, center, sz, rotation, darken(fill, 0.2));
+        }
+        let shape_painter = clipped.as_ref().unwrap_or(painter);
+        match shape {
+            ShapeKind::Square | ShapeKind::Reference { .. } => {
+                let rect = Rect::from_center_size(center, egui::Vec2::splat(sz));
+                if let FillMode::LinearGradient { to } = o.fill_mode {
+                    // top-to-bottom, one thin band per step
+                    const BANDS: usize = 16;
+                    let h = rect.height() / BANDS as f32;
+                    for i in 0..BANDS {
+                        let t = i as f32 / (BANDS - 1) as f32;
+                        let band = Rect::from_min_size(
+                            Pos2::new(rect.min.x, rect.min.y + h * i as f32),
+                            egui::Vec2::new(rect.width(), h + 0.5),
+                        );
+                        shape_painter.rect_filled(band, 0.0, lerp_color(fill, fade(to), t));
+                    }
+                } else {
+                    shape_painter.rect(
+                        rect,
+                        o.corner_radius,
+                        fill,
+                        Stroke::NONE,
+                        egui::StrokeKind::Middle,
+                    );
+                }
+            }
+            ShapeKind::Circle => {
+                if partial {
+                    draw_pie(painter, center, sz * 0.5, o.fill_ratio.max(0.0), fill);
+                } else if let FillMode::LinearGradient { to } = o.fill_mode {
+                    // outer ring starts at `color`, shrinking toward `to` at the center
+                    const RINGS: usize = 12;
+                    for i in 0..RINGS {
+                        let t = i as f32 / (RINGS - 1) as f32;
+                        let r = sz * 0.5 * (1.0 - i as f32 / RINGS as f32);
+                        shape_painter.circle_filled(center, r, lerp_color(fill, fade(to), t));
+                    }
+                } else {
+                    // `painter.circle` and `Shape::circle_filled` build the same
+                    // `CircleShape`; both get the same feathered edge
+                    shape_painter.circle(center, sz * 0.5, fill, Stroke::NONE);
+                }
+            }
+            ShapeKind::Triangle => {
+                // Anti-aliasing comes from the tessellator feathering a path's outline
+                // by about a pixel, so any `PathShape` (which `Shape::convex_polygon`
+                // also builds) is smooth even at sub-pixel sizes. What isn't is a raw
+                // `Mesh`, which skips the tessellator.
+                shape_painter.add(Shape::Path(egui::epaint::PathShape {
+                    points: triangle_points(center, sz, rotation),
+                    closed: true,
+                    fill,
+                    stroke: Stroke::NONE.into(),
+                }));
+            }
+        }
+        // a partly filled node keeps showing its progress instead
+        if let Some(texture) = o.texture
+            && !partial
+        {
+            let tint = Color32::WHITE.gamma_multiply(fill.a() as f32 / 255.0);
+            draw_texture(painter, texture, shape, center, sz, tint);
+        }
+    }
+    if dc.active == Some(o.id) {
+        // 0..1..0 once per pulse period
+        let wave = 0.5 - 0.5 * (dc.pulse_phase * std::f32::consts::TAU).cos();
+        let stroke = Stroke::new(
+            1.0 + 3.0 * wave,
+            Color32::from_rgba_unmultiplied(255, 255, 0, (128.0 + 127.0 * wave) as u8),
+        );
+        match shape {
+            ShapeKind::Square | ShapeKind::Reference { .. } => {
+                let rect = Rect::from_center_size(center, egui::Vec2::splat(sz + 6.0));
+                let rounding = if o.corner_radius > 0.0 {
+                    o.corner_radius + 3.0
+                } else {
+                    0.0
+                };
+                painter.rect_stroke(rect, rounding, stroke, egui::StrokeKind::Middle);
+            }
+            ShapeKind::Circle => {
+                painter.circle_stroke(center, sz * 0.5 + 3.0, stroke);
+            }
+            ShapeKind::Triangle => {
+                painter.add(Shape::closed_line(
+                    triangle_points(center, sz + 8.0, rotation),
+                    stroke,
+                ));
+            }
+        }
+    }
+    if let Some(icon) = &o.icon {
+        painter.text(
+            center,
+            egui::Align2::CENTER_CENTER,
+            icon,
+            FontId::proportional(sz * 0.4),
+            fade(Color32::WHITE),
+        );
+    }
+    if (o.collapsed || o.expand_depth == Some(0)) && !o.children.is_empty() {
+        // how much is folded away, in the bottom-right corner
+        let badge = center + egui::Vec2::splat(sz * 0.5);
+        painter.circle(
+            badge,
+            8.0,
+            fade(Color32::from_gray(30)),
+            Stroke::new(1.0, fade(Color32::WHITE)),
+        );
+        painter.text(
+            badge,
+            egui::Align2::CENTER_CENTER,
+            count_descendants(o).to_string(),
+            FontId::proportional(9.0),
+            fade(Color32::WHITE),
+        );
+    }
+    if let Some(copies) = dc.folded.get(&o.id) {
+        painter.text(
+            center + egui::Vec2::new(sz * 0.5, -sz * 0.5),
+            egui::Align2::LEFT_BOTTOM,
+            format!("×{copies}"),
+            FontId::proportional(11.0),
+            fade(Color32::WHITE),
+        );
+    }
+    if dc.selected.contains(&o.id) {
+        painter.circle_stroke(center, sz * 0.75, Stroke::new(2.0, Color32::LIGHT_BLUE));
+    }
+    if dc.search.is_some_and(|m| m.contains(&o.id)) {
+        painter.circle_stroke(center, sz * 0.85, Stroke::new(3.0, Color32::YELLOW));
+    }
+    if o.is_prefab_instance.is_some() {
+        // small "P" badge on the top-left corner of a prefab instance's root
+        let badge = center + egui::Vec2::new(-sz * 0.5, -sz * 0.5);
+        painter.circle_filled(badge, 7.0, Color32::from_rgb(128, 0, 255));
+        painter.text(
+            badge,
+            egui::Align2::CENTER_CENTER,
+            "P",
+            FontId::proportional(10.0),
+            Color32::WHITE,
+        );
+    }
+    let priority_color = match o.priority {
+        1 => Some(Color32::GREEN),
+        2 => Some(Color32::YELLOW),
+        3 => Some(Color32::RED),
+        _ => None,
+    };
+    if let Some(c) = priority_color {
+        // top edge, clear of the corners the badges and counts use
+        let badge = center + egui::Vec2::new(0.0, -sz * 0.5);
+        painter.circle_filled(badge, sz * 0.15, fade(c));
+    }
+    let corners = [(-0.5, -0.5), (0.5, -0.5), (-0.5, 0.5)];
+    // a rule warning takes the last corner if the node's own badges fill them
+    let warn = dc.warnings.contains(&o.id) && !o.badges.contains(&BadgeKind::Warning);
+    let own = if warn { MAX_BADGES - 1 } else { MAX_BADGES };
+    let badges = o
+        .badges
+        .iter()
+        .take(own)
+        .chain(warn.then_some(&BadgeKind::Warning));
+    for (badge, (x, y)) in badges.zip(corners) {
+        let at = center + egui::Vec2::new(x, y) * sz;
+        let r = sz * 0.18;
+        painter.circle_filled(at, r, fade(badge.color()));
+        painter.text(
+            at,
+            egui::Align2::CENTER_CENTER,
+            badge.symbol(),
+            FontId::proportional(r * 1.4),
+            fade(Color32::WHITE),
+        );
+    }
+    if dc.show_health {
+        draw_health_arc(painter, center, sz * 0.5 + 5.0, health_score(o), alpha);
+    }
+    if let Some(parent) = dc.parent_names.and_then(|names| names.get(&o.id)) {
+        painter.text(
+            Pos2::new(center.x, center.y - sz * 0.5 - 2.0),
+            egui::Align2::CENTER_BOTTOM,
+            format!("↑ {parent}"),
+            FontId::proportional(10.0),
+            fade(Color32::GRAY),
+        );
+    }
+    let mut label_bottom = center.y + sz * 0.5 + 2.0;
+    if dc.renaming != Some(o.id) {
+        // hangs down from just under the shape so extra lines don't cover it
+        let font = FontId::proportional(o.text_size);
+        let text = o
+            .text
+            .lines()
+            .map(|line| truncate_label(painter.ctx(), line, &font, sz * 1.5))
+            .collect::<Vec<_>>()
+            .join("\n");
+        label_bottom = painter
+            .text(
+                Pos2::new(center.x, label_bottom),
+                egui::Align2::CENTER_TOP,
+                text,
+                font,
+                fade(o.text_color.unwrap_or(dc.label_color)),
+            )
+            .max
+            .y;
+    }
+    if !o.timing_samples.is_empty() {
+        draw_sparkline(
+            painter,
+            Pos2::new(center.x, label_bottom + 2.0),
+            &o.timing_samples,
+            alpha,
+        );
+    }
+
+    for c in children {
+        draw_world(dc, c, current_depth + 1, child_opacity);
+    }
+}
+
+/// Bar chart of the last `SPARKLINE_BARS` samples in a strip centred just below
+/// `top`. Bars are scaled against the largest sample kept.
+fn draw_sparkline(painter: &egui::Painter, top: Pos2, samples: &VecDeque<f32>, alpha: f32) {
+    const BAR_W: f32 = 4.0;
+    const GAP: f32 = 1.0;
+    const HEIGHT: f32 = 16.0;
+    let max = samples.iter().cloned().fold(0.0_f32, f32::max);
+    let shown = samples.len().min(SPARKLINE_BARS);
+    let width = shown as f32 * (BAR_W + GAP) - GAP;
+    let mut x = top.x - width / 2.0;
+    for &ms in samples.iter().skip(samples.len() - shown) {
+        let h = if max > 0.0 { ms / max * HEIGHT } else { 0.0 };
+        let bar = Rect::from_min_max(
+            Pos2::new(x, top.y + HEIGHT - h),
+            Pos2::new(x + BAR_W, top.y + HEIGHT),
+        );
+        let color = if ms > FRAME_BUDGET_MS {
+            Color32::RED
+        } else {
+            Color32::GREEN
+        };
+        painter.rect(
+            bar,
+            0.0,
+            color.gamma_multiply(alpha),
+            Stroke::NONE,
+            egui::StrokeKind::Inside,
+        );
+        x += BAR_W + GAP;
+    }
+}
+
+/// Shortens `text` with a trailing ellipsis until it fits in `max_width`.
+fn truncate_label(ctx: &egui::Context, text: &str, font: &FontId, max_width: f32) -> String {
+    let width =
+        |s: String| ctx.fonts(|f| f.layout_no_wrap(s, font.clone(), Color32::WHITE).size().x);
+    if width(text.to_owned()) <= max_width {
+        return text.to_owned();
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let candidate: String = chars.iter().chain(['…'].iter()).collect();
+        if width(candidate.clone()) <= max_width {
+            return candidate;
+        }
+    }
+    "…".to_owned()
+}
+
+/// The shape and color `o` is drawn with. A reference borrows its target's; a
+/// target that is itself a reference isn't followed, so reference cycles can't
+/// loop, and a reference that leads nowhere drawable shows as a square in its
+/// own color.
+pub fn resolve_appearance(
+    objects: &[SceneObject],
+    index: &NodeIndex,
+    o: &SceneObject,
+) -> (ShapeKind, Color32) {
+    match o.shape {
+        ShapeKind::Reference { target_id } => match index.get(objects, target_id) {
+            Some(t) if !matches!(t.shape, ShapeKind::Reference { .. }) => {
+                (t.shape, t.shown_color())
+            }
+            _ => (ShapeKind::Square, o.shown_color()),
+        },
+        shape => (shape, o.shown_color()),
+    }
+}
+
+/// `shape` (`o`'s own, or what its reference resolves to) in a single flat
+/// color, ignoring its fill mode. Used for the outline-drag preview and under
+/// partly filled nodes.
+fn draw_plain_shape(
+    painter: &egui::Painter,
+    o: &SceneObject,
+    shape: ShapeKind,
+    center: Pos2,
+    sz: f32,
+    rotation_deg: f32,
+    fill: Color32,
+) {
+    match shape {
+        ShapeKind::Square | ShapeKind::Reference { .. } => {
+            let rect = Rect::from_center_size(center, egui::Vec2::splat(sz));
+            painter.rect_filled(rect, o.corner_radius, fill);
+        }
+        ShapeKind::Circle => {
+            painter.circle_filled(center, sz * 0.5, fill);
+        }
+        ShapeKind::Triangle => {
+            painter.add(Shape::convex_polygon(
+                triangle_points(center, sz, rotation_deg),
+                fill,
+                Stroke::NONE,
+            ));
+        }
+    }
+}
+
+/// A node's image, stretched over a square or cut to a circle. Triangles
+/// keep just their fill.
+fn draw_texture(
+    painter: &egui::Painter,
+    texture: egui::TextureId,
+    shape: ShapeKind,
+    center: Pos2,
+    sz: f32,
+    tint: Color32,
+) {
+    let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+    match shape {
+        ShapeKind::Square | ShapeKind::Reference { .. } => {
+            let rect = Rect::from_center_size(center, egui::Vec2::splat(sz));
+            painter.image(texture, rect, uv, tint);
+        }
+        ShapeKind::Circle => {
+            // a fan around the center, each rim vertex sampling the image's
+            // inscribed circle; like any raw mesh its edge isn't feathered
+            const SEGMENTS: u32 = 48;
+            let mut mesh = egui::Mesh::with_texture(texture);
+            let vertex = |dir: egui::Vec2| egui::epaint::Vertex {
+                pos: center + dir * sz * 0.5,
+                uv: uv.center() + dir * 0.5,
+                color: tint,
+            };
+            mesh.vertices.push(vertex(egui::Vec2::ZERO));
+            for i in 0..SEGMENTS {
+                let angle = std::f32::consts::TAU * i as f32 / SEGMENTS as f32;
+                mesh.vertices.push(vertex(egui::Vec2::angled(angle)));
+                mesh.add_triangle(0, 1 + i, 1 + (i + 1) % SEGMENTS);
+            }
+            painter.add(Shape::mesh(mesh));
+        }
+        ShapeKind::Triangle => {}
+    }
+}
+
+/// The texture for the image at `path`, decoded the first time it's asked for.
+/// A path that failed stays failed, without trying the file again every
+/// frame, until asked for with `retry`.
+fn node_texture(
+    cache: &mut HashMap<PathBuf, Option<egui::TextureHandle>>,
+    ctx: &egui::Context,
+    path: &Path,
+    retry: bool,
+) -> Result<egui::TextureId, AppError> {
+    if !retry && let Some(cached) = cache.get(path) {
+        return cached
+            .as_ref()
+            .map(egui::TextureHandle::id)
+            .ok_or_else(|| AppError::ParseError("image failed to load earlier".into()));
+    }
+    match storage::read_image(path) {
+        Ok(image) => {
+            let name = path.display().to_string();
+            let handle = ctx.load_texture(name, image, egui::TextureOptions::LINEAR);
+            let id = handle.id();
+            cache.insert(path.to_path_buf(), Some(handle));
+            Ok(id)
+        }
+        Err(e) => {
+            eprintln!("couldn't load {}: {e}", path.display());
+            cache.insert(path.to_path_buf(), None);
+            Err(e)
+        }
+    }
+}
+
+/// A node partway through turning from `from` into `to`. A square and a
+/// circle are one rounded rect with the corners growing or shrinking; any
+/// other pair is both shapes cross-faded.
+#[allow(clippy::too_many_arguments)]
+fn draw_morph(
+    painter: &egui::Painter,
+    o: &SceneObject,
+    from: ShapeKind,
+    to: ShapeKind,
+    center: Pos2,
+    sz: f32,
+    rotation_deg: f32,
+    fill: Color32,
+) {
+    let t = o.morph_progress.clamp(0.0, 1.0);
+    let round = sz * 0.5;
+    let radius = match (from, to) {
+        (ShapeKind::Square, ShapeKind::Circle) => Some(egui::lerp(o.corner_radius..=round, t)),
+        (ShapeKind::Circle, ShapeKind::Square) => Some(egui::lerp(round..=o.corner_radius, t)),
+        _ => None,
+    };
+    if let Some(radius) = radius {
+        let rect = Rect::from_center_size(center, egui::Vec2::splat(sz));
+        painter.rect_filled(rect, radius, fill);
+    } else {
+        draw_plain_shape(
+            painter,
+            o,
+            from,
+            center,
+            sz,
+            rotation_deg,
+            fill.gamma_multiply(1.0 - t),
+        );
+        draw_plain_shape(
+            painter,
+            o,
+            to,
+            center,
+            sz,
+            rotation_deg,
+            fill.gamma_multiply(t),
+        );
+    }
+}
+
+/// Clockwise arc from twelve o'clock, `score` of the way round, shading from
+/// red at 0 through yellow to green at 1.
+fn draw_health_arc(painter: &egui::Painter, center: Pos2, radius: f32, score: f32, alpha: f32) {
+    let score = score.clamp(0.0, 1.0);
+    if score <= 0.0 {
+        return;
+    }
+    let color = if score < 0.5 {
+        lerp_color(Color32::RED, Color32::YELLOW, score * 2.0)
+    } else {
+        lerp_color(Color32::YELLOW, Color32::GREEN, score * 2.0 - 1.0)
+    };
+    let steps = (48.0 * score).ceil().max(2.0) as usize;
+    let points = (0..=steps)
+        .map(|i| {
+            let a = std::f32::consts::TAU * score * i as f32 / steps as f32;
+            center + egui::Vec2::new(a.sin(), -a.cos()) * radius
+        })
+        .collect();
+    painter.add(Shape::Path(egui::epaint::PathShape {
+        points,
+        closed: false,
+        fill: Color32::TRANSPARENT,
+        stroke: Stroke::new(3.0, color.gamma_multiply(alpha)).into(),
+    }));
+}
+
+/// The inspector's speed dial: an arc from twelve o'clock, clockwise for a
+/// positive speed and counter-clockwise for a negative one, going all the way
+/// round at 180°/s. The needle points at the node's current rotation, around
+/// the speed itself in the middle.
+fn rotation_gauge(ui: &mut egui::Ui, speed: f32, rotation_deg: f32) {
+    let (resp, painter) = ui.allocate_painter(egui::Vec2::splat(80.0), egui::Sense::hover());
+    let center = resp.rect.center();
+    let radius = resp.rect.width() * 0.5 - 4.0;
+    let at = |angle: f32, r: f32| center + egui::Vec2::new(angle.sin(), -angle.cos()) * r;
+    painter.circle_filled(center, radius, Color32::from_gray(70));
+
+    let fraction = (speed.abs() / 180.0).min(1.0);
+    if fraction > 0.0 {
+        let color = if fraction < 0.5 {
+            lerp_color(Color32::GREEN, Color32::YELLOW, fraction * 2.0)
+        } else {
+            lerp_color(Color32::YELLOW, Color32::RED, fraction * 2.0 - 1.0)
+        };
+        let sweep = std::f32::consts::TAU * fraction * speed.signum();
+        let steps = (48.0 * fraction).ceil().max(2.0) as usize;
+        let points = (0..=steps)
+            .map(|i| at(sweep * i as f32 / steps as f32, radius - 2.0))
+            .collect();
+        painter.add(Shape::Path(egui::epaint::PathShape {
+            points,
+            closed: false,
+            fill: Color32::TRANSPARENT,
+            stroke: Stroke::new(4.0, color).into(),
+        }));
+    }
+
+    let stroke = Stroke::new(2.0, Color32::WHITE);
+    let angle = rotation_deg.to_radians();
+    let tip = at(angle, radius - 8.0);
+    // starts clear of the number in the middle
+    painter.line_segment([at(angle, radius * 0.5), tip], stroke);
+    for side in [-0.3, 0.3] {
+        painter.line_segment([tip, at(angle + side, radius - 16.0)], stroke);
+    }
+    painter.text(
+        center,
+        egui::Align2::CENTER_CENTER,
+        format!("{speed:.0}°/s"),
+        FontId::proportional(11.0),
+        Color32::WHITE,
+    );
+}
+
+/// Pie slice of the circle clockwise from twelve o'clock, `ratio` of the way round.
+/// A path fill has to be convex, so this is a plain mesh and its edge isn't
+/// anti-aliased.
+fn draw_pie(painter: &egui::Painter, center: Pos2, radius: f32, ratio: f32, fill: Color32) {
+    const SEGMENTS: usize = 48;
+    let steps = ((SEGMENTS as f32 * ratio).ceil() as usize).max(1);
+    let mut mesh = egui::Mesh::default();
+    mesh.colored_vertex(center, fill);
+    for i in 0..=steps {
+        let angle = std::f32::consts::TAU * ratio * i as f32 / steps as f32;
+        let dir = egui::Vec2::new(angle.sin(), -angle.cos());
+        mesh.colored_vertex(center + dir * radius, fill);
+        if i > 0 {
+            mesh.add_triangle(0, i as u32, i as u32 + 1);
+        }
+    }
+    painter.add(mesh);
+}
+
+/// `c` with its color channels scaled by `k`, alpha untouched.
+fn darken(c: Color32, k: f32) -> Color32 {
+    let [r, g, b, a] = c.to_array();
+    let s = |x: u8| (x as f32 * k) as u8;
+    Color32::from_rgba_premultiplied(s(r), s(g), s(b), a)
+}
+
+/// `rotation_deg` turned to the nearest multiple of `snap`, or left alone when
+/// there's no snap step.
+fn snapped_rotation(rotation_deg: f32, snap: Option<f32>) -> f32 {
+    match snap {
+        Some(step) if step > 0.0 => (rotation_deg / step).round() * step,
+        _ => rotation_deg,
+    }
+}
+
+pub(crate) fn triangle_points(center: Pos2, sz: f32, rotation_deg: f32) -> Vec<Pos2> {
+    let a = rotation_deg.to_radians();
+    let rot = |v: egui::Vec2| {
+        egui::Vec2::new(v.x * a.cos() - v.y * a.sin(), v.x * a.sin() + v.y * a.cos())
+            + center.to_vec2()
+    };
+    let v = |v: egui::Vec2| Pos2::new(v.x, v.y);
+    vec![
+        v(rot(egui::Vec2::new(0.0, -sz / 2.0))),
+        v(rot(egui::Vec2::new(-sz / 2.0, sz / 2.0))),
+        v(rot(egui::Vec2::new(sz / 2.0, sz / 2.0))),
+    ]
+}
+
+/// Routes a connector from `a` to `b` as a polyline in screen space.
+/// `elbow` shifts an orthogonal connector's vertical segment sideways, in
+/// screen points; the other styles ignore it.
+fn connector_points(style: ConnectorStyle, a: Pos2, b: Pos2, elbow: f32) -> Vec<Pos2> {
+    match style {
+        ConnectorStyle::Diagonal => vec![a, b],
+        ConnectorStyle::Orthogonal if elbow == 0.0 => vec![a, Pos2::new(b.x, a.y), b],
+        ConnectorStyle::Orthogonal => {
+            let x = b.x + elbow;
+            vec![a, Pos2::new(x, a.y), Pos2::new(x, b.y), b]
+        }
+        ConnectorStyle::Bezier => {
+            const SEGMENTS: usize = 24;
+            let h = egui::Vec2::new((b.x - a.x) / 2.0, 0.0);
+            let (c1, c2) = (a + h, b - h);
+            (0..=SEGMENTS)
+                .map(|i| {
+                    let t = i as f32 / SEGMENTS as f32;
+                    let u = 1.0 - t;
+                    let v = a.to_vec2() * (u * u * u)
+                        + c1.to_vec2() * (3.0 * u * u * t)
+                        + c2.to_vec2() * (3.0 * u * t * t)
+                        + b.to_vec2() * (t * t * t);
+                    Pos2::new(v.x, v.y)
+                })
+                .collect()
+        }
+    }
+}
+
+/// Grip position on each visible parent-child connector's vertical segment,
+/// halfway down it. Connectors too short to grab get none.
+fn elbow_handles<F>(
+    v: &[SceneObject],
+    layout: &HashMap<u32, egui::Vec2>,
+    to_screen: &F,
+    offsets: &HashMap<(u32, u32), f32>,
+    zoom: f32,
+) -> Vec<((u32, u32), Pos2)>
+where
+    F: Fn(egui::Vec2) -> Pos2,
+{
+    let mut handles = vec![];
+    visit(v, 0, &mut |o, _| {
+        let Some(&p) = layout.get(&o.id).filter(|_| !o.pinned_to_screen) else {
+            return;
+        };
+        let a = to_screen(p);
+        for c in &o.children {
+            let Some(&cp) = layout.get(&c.id).filter(|_| !c.pinned_to_screen) else {
+                continue;
+            };
+            let b = to_screen(cp);
+            if (b.y - a.y).abs() < ELBOW_HANDLE * 2.0 {
+                continue;
+            }
+            let shift = offsets.get(&(o.id, c.id)).map_or(0.0, |off| off * zoom);
+            handles.push(((o.id, c.id), Pos2::new(b.x + shift, (a.y + b.y) / 2.0)));
+        }
+    });
+    handles
+}
+
+/// Screen rect of a group's box around its visible members, with headroom on
+/// top for the label; `None` when none of them are laid out.
+fn group_rect<F>(dc: &DrawCtx<'_, F>, g: &NodeGroup) -> Option<Rect>
+where
+    F: Fn(egui::Vec2) -> Pos2,
+{
+    const PAD: f32 = 10.0;
+    let bounds = g
+        .node_ids
+        .iter()
+        .filter_map(|id| {
+            let p = (dc.to_screen)(*dc.layout.get(id)?);
+            Some(Rect::from_center_size(
+                p,
+                egui::Vec2::splat(node_size(dc.sizes, *id)),
+            ))
+        })
+        .reduce(|a, b| a.union(b))?;
+    Some(bounds.expand(PAD).with_min_y(bounds.min.y - PAD - 16.0))
+}
+
+/// Translucent rounded box around each group's visible members, label top-left.
+fn draw_groups<F>(dc: &DrawCtx<'_, F>, groups: &[NodeGroup])
+where
+    F: Fn(egui::Vec2) -> Pos2,
+{
+    for g in groups {
+        let Some(rect) = group_rect(dc, g) else {
+            continue;
+        };
+        let [r, gr, b, _] = g.color.to_array();
+        dc.painter.rect(
+            rect,
+            8.0,
+            Color32::from_rgba_unmultiplied(r, gr, b, 40),
+            Stroke::new(1.0, g.color),
+            egui::StrokeKind::Outside,
+        );
+        dc.painter.text(
+            rect.left_top() + egui::Vec2::new(6.0, 2.0),
+            egui::Align2::LEFT_TOP,
+            &g.label,
+            FontId::proportional(13.0),
+            g.color,
+        );
+    }
+}
+
+/// Thick arrow for each group link, from the edge of one group's box to the
+/// edge of the other's along the line between their centres, labelled halfway.
+fn draw_group_links<F>(dc: &DrawCtx<'_, F>, groups: &[NodeGroup], links: &[GroupLink])
+where
+    F: Fn(egui::Vec2) -> Pos2,
+{
+    let rect = |id: u32| group_rect(dc, groups.iter().find(|g| g.id == id)?);
+    // how far from the centre of `r` the line along `dir` leaves it
+    let exit = |r: Rect, dir: egui::Vec2| {
+        let tx = if dir.x != 0.0 {
+            r.width() / 2.0 / dir.x.abs()
+        } else {
+            f32::INFINITY
+        };
+        let ty = if dir.y != 0.0 {
+            r.height() / 2.0 / dir.y.abs()
+        } else {
+            f32::INFINITY
+        };
+        tx.min(ty)
+    };
+    for l in links {
+        let (Some(a), Some(b)) = (rect(l.from_group), rect(l.to_group)) else {
+            continue;
+        };
+        let dir = (b.center() - a.center()).normalized();
+        if dir == egui::Vec2::ZERO {
+            continue;
+        }
+        let start = a.center() + dir * exit(a, dir);
+        let end = b.center() - dir * exit(b, dir);
+        if (end - start).dot(dir) <= 0.0 {
+            // the boxes overlap along this line, so there's nothing to draw
+            continue;
+        }
+        dc.painter
+            .line_segment([start, end], Stroke::new(4.0, l.color));
+        draw_arrowhead(dc.painter, &[start, end], 0.0, 2.0, l.color);
+        if !l.label.is_empty() {
+            dc.painter.text(
+                start + (end - start) / 2.0 - dir.rot90() * 10.0,
+                egui::Align2::CENTER_CENTER,
+                &l.label,
+                FontId::proportional(13.0),
+                l.color,
+            );
+        }
+    }
+}
+
+/// Dashed purple arrow for a non-tree link, stopping `target_radius` short of `b`.
+fn draw_link(painter: &egui::Painter, a: Pos2, b: Pos2, target_radius: f32) {
+    let dir = (b - a).normalized();
+    let tip = b - dir * target_radius;
+    let stroke = Stroke::new(1.5, LINK_COLOR);
+    draw_edge(painter, &[a, tip], EdgeStyle::Dashed, stroke);
+    let back = tip - dir * 8.0;
+    let side = egui::Vec2::new(-dir.y, dir.x) * 4.0;
+    painter.line_segment([tip, back + side], stroke);
+    painter.line_segment([tip, back - side], stroke);
+}
+
+/// Filled arrowhead `inset` points back from the end of `points`, pointing
+/// along the last segment. Sized in world units, so it grows as the camera
+/// zooms in, but kept between 4 and 16 points long so it stays readable.
+fn draw_arrowhead(painter: &egui::Painter, points: &[Pos2], inset: f32, zoom: f32, color: Color32) {
+    let [.., from, end] = points else {
+        return;
+    };
+    let dir = (*end - *from).normalized();
+    if dir == egui::Vec2::ZERO {
+        return;
+    }
+    let length = (8.0 * zoom).clamp(4.0, 16.0);
+    let half_width = length * 5.0 / 8.0 / 2.0;
+    let tip = *end - dir * inset;
+    let back = tip - dir * length;
+    let side = dir.rot90() * half_width;
+    painter.add(Shape::convex_polygon(
+        vec![tip, back + side, back - side],
+        color,
+        Stroke::NONE,
+    ));
+}
+
+fn distance_to_segment(p: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_sq() > 0.0 {
+        ((p - a).dot(ab) / ab.length_sq()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    p.distance(a + ab * t)
+}
+
+/// Rounds each axis to the nearest multiple of `spacing`.
+fn snap(pos: egui::Vec2, spacing: f32) -> egui::Vec2 {
+    (pos / spacing).round() * spacing
+}
+
+/// Even-odd ray cast; `poly` is treated as closed.
+fn point_in_polygon(p: Pos2, poly: &[Pos2]) -> bool {
+    let mut inside = false;
+    let mut j = poly.len() - 1;
+    for (i, a) in poly.iter().enumerate() {
+        let b = poly[j];
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Point at fraction `t` (0..1) of a polyline's total length.
+fn point_along(points: &[Pos2], t: f32) -> Pos2 {
+    let total: f32 = points.windows(2).map(|w| w[0].distance(w[1])).sum();
+    let mut left = total * t;
+    for w in points.windows(2) {
+        let len = w[0].distance(w[1]);
+        if left <= len && len > 0.0 {
+            return w[0].lerp(w[1], left / len);
+        }
+        left -= len;
+    }
+    points.last().copied().unwrap_or(Pos2::ZERO)
+}
+
+/// The first `t` (0..1) of a polyline, by length.
+fn truncate_polyline(points: &[Pos2], t: f32) -> Vec<Pos2> {
+    let total: f32 = points.windows(2).map(|w| w[0].distance(w[1])).sum();
+    let mut left = total * t;
+    let mut out = points[..points.len().min(1)].to_vec();
+    for w in points.windows(2) {
+        let len = w[0].distance(w[1]);
+        if left < len {
+            out.push(w[0].lerp(w[1], left / len));
+            break;
+        }
+        out.push(w[1]);
+        left -= len;
+    }
+    out
+}
+
+/// The tree layout with every layer shown and nothing folded, as a scene looks
+/// when first opened; dragged nodes keep their `position_override`.
+pub fn tree_layout(v: &[SceneObject]) -> HashMap<u32, egui::Vec2> {
+    let collapse = HashMap::new();
+    let lc = LayoutCtx {
+        max_depth: None,
+        sizes: None,
+        visible_layers: u8::MAX,
+        collapse: &collapse,
+    };
+    let mut m = HashMap::new();
+    let mut cy = 100.0;
+    for o in v {
+        layout_recursive(o, 200.0, cy, &mut cy, &mut m, 0, None, &lc);
+    }
+    visit(v, 0, &mut |o, _| {
+        if let Some(p) = o.position_override
+            && let Some(slot) = m.get_mut(&o.id)
+        {
+            *slot = p;
+        }
+    });
+    m
+}
+
+/// Draws the scene the way the canvas does but with nothing from the editing
+/// session on top: no selection, search, animation or glow. Used for headless
+/// export.
+pub fn paint_scene(
+    painter: &egui::Painter,
+    objects: &[SceneObject],
+    links: &[(u32, u32)],
+    layout: &HashMap<u32, egui::Vec2>,
+    to_screen: &impl Fn(egui::Vec2) -> Pos2,
+    zoom: f32,
+) {
+    let (selected, empty, edges) = (HashSet::new(), HashMap::new(), HashMap::new());
+    let warnings = HashSet::new();
+    let index = NodeIndex::build(objects);
+    let dc = DrawCtx {
+        painter,
+        objects,
+        index: &index,
+        layout,
+        to_screen,
+        max_depth: None,
+        sizes: None,
+        selected: &selected,
+        active: None,
+        pulse_phase: 0.0,
+        renaming: None,
+        connector: ConnectorStyle::Diagonal,
+        accessibility: AccessibilityMode::None,
+        label_color: painter.ctx().style().visuals.text_color(),
+        flow: &edges,
+        spawn: &edges,
+        links,
+        search: None,
+        time: 0.0,
+        visible_layers: u8::MAX,
+        collapse: &empty,
+        folded: &HashMap::new(),
+        deleting: &empty,
+        elbows: &edges,
+        zoom,
+        snap_rotation: None,
+        show_arrows: false,
+        parent_names: None,
+        show_health: false,
+        warnings: &warnings,
+        glows: None,
+    };
+    for o in objects {
+        draw_world(&dc, o, 0, 1.0);
+    }
+}
+
+/// Miniature of a template scene in its tree layout, scaled to fit `rect`.
+fn draw_template_thumbnail(painter: &egui::Painter, rect: Rect, objects: &[SceneObject]) {
+    let layout = tree_layout(objects);
+    let Some(bounds) = layout
+        .values()
+        .map(|&p| Rect::from_center_size(p.to_pos2(), egui::Vec2::ZERO))
+        .reduce(|a, b| a.union(b))
+    else {
+        return;
+    };
+    let scale =
+        (rect.width() / bounds.width().max(1.0)).min(rect.height() / bounds.height().max(1.0));
+    let to_thumb = |p: egui::Vec2| rect.center() + (p.to_pos2() - bounds.center()) * scale;
+    let dot = (scale * NODE_SIZE * 0.5).clamp(1.5, 6.0);
+    visit(objects, 0, &mut |o, _| {
+        if let Some(&p) = layout.get(&o.id) {
+            for c in &o.children {
+                if let Some(&q) = layout.get(&c.id) {
+                    painter
+                        .line_segment([to_thumb(p), to_thumb(q)], Stroke::new(1.0, Color32::GRAY));
+                }
+            }
+        }
+    });
+    visit(objects, 0, &mut |o, _| {
+        if let Some(&p) = layout.get(&o.id) {
+            painter.circle_filled(to_thumb(p), dot, o.color);
+        }
+    });
+}
+
+/// Draws a connector polyline, splitting it into dash-gap pairs for the non-solid
+/// styles. The dash pattern carries over across corners.
+fn draw_edge(painter: &egui::Painter, points: &[Pos2], style: EdgeStyle, stroke: Stroke) {
+    let (dash, gap): (f32, f32) = match style {
+        EdgeStyle::Solid => {
+            for w in points.windows(2) {
+                painter.line_segment([w[0], w[1]], stroke);
+            }
+            return;
+        }
+        EdgeStyle::Dashed => (8.0, 6.0),
+        EdgeStyle::Dotted => (2.0, 4.0),
+    };
+    let mut on = true;
+    let mut left = dash;
+    for w in points.windows(2) {
+        let (mut a, b) = (w[0], w[1]);
+        let mut len = (b - a).length();
+        while len > f32::EPSILON {
+            let step = left.min(len);
+            let next = a + (b - a) * (step / len);
+            if on {
+                painter.line_segment([a, next], stroke);
+            }
+            a = next;
+            len -= step;
+            left -= step;
+            if left <= f32::EPSILON {
+                on = !on;
+                left = if on { dash } else { gap };
+            }
+        }
+    }
+}
+
+/// Renders `**bold**`, `_italic_` and `` `code` `` spans; markers toggle the
+/// style and an unclosed one runs to the end. Nothing nests inside code, and
+/// an `_` inside a word (`snake_case`) is left as it is.
+fn memo_layout(memo: &str, style: &egui::Style) -> egui::text::LayoutJob {
+    let mut job = egui::text::LayoutJob::default();
+    let (mut bold, mut italic, mut code) = (false, false, false);
+    let mut span = String::new();
+    let mut flush = |span: &mut String, bold: bool, italic: bool, code: bool| {
+        if span.is_empty() {
+            return;
+        }
+        let mut text = egui::RichText::new(std::mem::take(span));
+        if bold {
+            text = text.strong();
+        }
+        if italic {
+            text = text.italics();
+        }
+        if code {
+            text = text.code();
+        }
+        text.append_to(
+            &mut job,
+            style,
+            egui::FontSelection::Default,
+            egui::Align::Center,
+        );
+    };
+    let mut rest = memo;
+    let mut prev = None;
+    while let Some(c) = rest.chars().next() {
+        // an opening `_` must start a word and a closing one must end it
+        let outside = if italic {
+            rest[c.len_utf8()..].chars().next()
+        } else {
+            prev
+        };
+        if c == '`' {
+            flush(&mut span, bold, italic, code);
+            code = !code;
+            rest = &rest[1..];
+        } else if !code && rest.starts_with("**") {
+            flush(&mut span, bold, italic, code);
+            bold = !bold;
+            rest = &rest[2..];
+        } else if !code && c == '_' && !outside.is_some_and(char::is_alphanumeric) {
+            flush(&mut span, bold, italic, code);
+            italic = !italic;
+            rest = &rest[1..];
+        } else {
+            span.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        prev = Some(c);
+    }
+    flush(&mut span, bold, italic, code);
+    job
+}
+
+fn draw_comment_bubble(painter: &egui::Painter, node: Pos2, comment: &str) {
+    const POINTER: f32 = 8.0;
+    let fill = Color32::from_rgba_unmultiplied(255, 255, 200, 220);
+    let galley = painter.layout(
+        comment.to_owned(),
+        FontId::proportional(12.0),
+        Color32::BLACK,
+        200.0,
+    );
+    // bubble sits above the node, its pointer touching the top of the shape
+    let tip = Pos2::new(node.x, node.y - 20.0);
+    let size = galley.size() + egui::Vec2::splat(12.0);
+    let rect = Rect::from_min_size(
+        Pos2::new(tip.x - size.x / 2.0, tip.y - POINTER - size.y),
+        size,
+    );
+    painter.rect_filled(rect, 6.0, fill);
+    painter.add(Shape::convex_polygon(
+        vec![
+            Pos2::new(tip.x - POINTER / 2.0, rect.max.y),
+            Pos2::new(tip.x + POINTER / 2.0, rect.max.y),
+            tip,
+        ],
+        fill,
+        Stroke::NONE,
+    ));
+    painter.galley(rect.min + egui::Vec2::splat(6.0), galley, Color32::BLACK);
+}
+
+fn for_each_unlocked(v: &mut [SceneObject], ids: &[u32], mut f: impl FnMut(&mut SceneObject)) {
+    for &id in ids {
+        if let Some(o) = find_object_by_id_mut(v, id)
+            && !o.locked
+        {
+            f(o);
+        }
+    }
+}
+
+/// What `process_requests` has to tell the user afterwards.
+#[derive(Debug, Default)]
+struct RequestOutcome {
+    /// One message for each request that couldn't be applied.
+    errors: Vec<String>,
+    /// What the last request that reports anything did, for the status bar.
+    status: Option<String>,
+}
+
+/// Applies and drains `reqs`.
+fn process_requests(
+    v: &mut Vec<SceneObject>,
+    index: &NodeIndex,
+    reqs: &mut Vec<EditorRequest>,
+    next_id: &mut u32,
+    types: &HashMap<String, NodeTypeSpec>,
+    sound: Option<&SoundPlayer>,
+) -> RequestOutcome {
+    let mut errors = vec![];
+    let mut status = None;
+    for r in reqs.drain(..) {
+        match r {
+            EditorRequest::AddChild { parent_id } => {
+                if let Some(p) = index.get_mut(v, parent_id) {
+                    let node_type = match new_child_type(types, p) {
+                        Ok(t) => t,
+                        Err(e) => {
+                            errors.push(format!("Can't add a child to {parent_id}: {e}"));
+                            continue;
+                        }
+                    };
+                    if let Some(sound) = sound {
+                        sound.play_add();
+                    }
+                    *next_id += 1;
+                    let (shape, color) = node_type
+                        .as_ref()
+                        .and_then(|t| types.get(t))
+                        .map_or((ShapeKind::Square, Color32::WHITE), |spec| {
+                            (spec.shape, spec.default_color)
+                        });
+                    let mut node = SceneObject::new(*next_id, "New Node", shape, color);
+                    node.node_type = node_type;
+                    p.children.push(node);
+                }
+            }
+            EditorRequest::AddChildConfigured { parent_id, config } => {
+                if let Some(p) = index.get_mut(v, parent_id) {
+                    let node_type = match new_child_type(types, p) {
+                        Ok(t) => t,
+                        Err(e) => {
+                            errors.push(format!("Can't add a child to {parent_id}: {e}"));
+                            continue;
+                        }
+                    };
+                    if let Some(sound) = sound {
+                        sound.play_add();
+                    }
+                    *next_id += 1;
+                    let mut node =
+                        SceneObject::new(*next_id, &config.name, config.shape, config.color);
+                    node.rotation_speed = config.rotation_speed;
+                    node.tags = config.tags;
+                    node.node_type = node_type;
+                    p.children.push(node);
+                }
+            }
+            EditorRequest::InsertChildren { parent_id, nodes } => {
+                if let Err(e) = insert_children(v, index, parent_id, nodes, types, sound) {
+                    errors.push(e);
+                }
+            }
+            EditorRequest::Paste { parent_id, node } => {
+                if let Err(e) = insert_children(v, index, parent_id, vec![*node], types, sound) {
+                    errors.push(e);
+                }
+            }
+            EditorRequest::DeleteNode { node_id } => {
+                if detach_node(v, node_id).is_some()
+                    && let Some(sound) = sound
+                {
+                    sound.play_delete();
+                }
+            }
+            EditorRequest::ResetRotation { node_id: None } => {
+                visit_mut(v, 0, &mut |o, _| o.current_rotation = 0.0);
+            }
+            EditorRequest::ResetRotation {
+                node_id: Some(node_id),
+            } => {
+                if let Some(o) = index.get_mut(v, node_id) {
+                    o.current_rotation = 0.0;
+                }
+            }
+            EditorRequest::ToggleCollapsed { node_id } => {
+                if let Some(o) = index.get_mut(v, node_id) {
+                    o.collapsed = !o.collapsed;
+                }
+            }
+            EditorRequest::MoveChild {
+                parent_id,
+                child_id,
+                new_index,
+            } => {
+                if let Some(p) = index.get_mut(v, parent_id)
+                    && let Some(i) = p.children.iter().position(|c| c.id == child_id)
+                {
+                    let child = p.children.remove(i);
+                    let at = new_index.min(p.children.len());
+                    p.children.insert(at, child);
+                }
+            }
+            EditorRequest::Reparent {
+                node_id,
+                new_parent_id,
+            } => {
+                // refuse moves onto the node itself or into its own subtree
+                let node_type = index.get(v, node_id).and_then(|o| o.node_type.clone());
+                let refusal = new_parent_id.and_then(|p| {
+                    let parent = index.get(v, p);
+                    if p == node_id {
+                        Some("a node can't be its own parent".to_owned())
+                    } else if parent.is_none() {
+                        Some("the new parent doesn't exist".to_owned())
+                    } else if is_ancestor_of(v, node_id, p) {
+                        Some("the new parent is one of its descendants".to_owned())
+                    } else {
+                        parent.and_then(|p| child_refusal(types, p, node_type.as_deref()))
+                    }
+                });
+                if let Some(reason) = refusal {
+                    eprintln!("reparent of {node_id} refused: {reason}");
+                    errors.push(format!("Can't move {node_id}: {reason}"));
+                } else if let Some(node) = detach_node(v, node_id) {
+                    match new_parent_id.and_then(|p| index.get_mut(v, p)) {
+                        Some(parent) => parent.children.push(node),
+                        None => v.push(node),
+                    }
+                }
+            }
+            EditorRequest::PaintSubtree {
+                node_id,
+                color,
+                blend,
+            } => {
+                if let Some(o) = index.get_mut(v, node_id) {
+                    for c in &mut o.children {
+                        paint_subtree(c, color, blend);
+                    }
+                }
+            }
+            EditorRequest::BatchSetRotationSpeed { ids, value } => {
+                for_each_unlocked(v, &ids, |o| o.rotation_speed = value);
+            }
+            EditorRequest::BatchSetColor { ids, color } => {
+                for_each_unlocked(v, &ids, |o| o.color = color);
+            }
+            EditorRequest::BatchSetShape { ids, shape } => {
+                for_each_unlocked(v, &ids, |o| o.shape = shape);
+            }
+            EditorRequest::FireClickEvents {
+                targets, events, ..
+            } => {
+                for_each_unlocked(v, &targets, |o| {
+                    for &e in &events {
+                        e.apply(o);
+                    }
+                });
+            }
+            EditorRequest::MergeNodes {
+                keep_id,
+                discard_id,
+            } => {
+                let is_child = find_object_path(v, discard_id)
+                    .is_some_and(|path| path.iter().rev().nth(1) == Some(&keep_id));
+                if !is_child {
+                    errors.push(format!(
+                        "Can't merge {discard_id} into {keep_id}: it isn't a direct child"
+                    ));
+                } else if let Some(discard) = detach_node(v, discard_id)
+                    && let Some(keep) = index.get_mut(v, keep_id)
+                {
+                    keep.children.extend(discard.children);
+                    for tag in discard.tags {
+                        if !keep.tags.contains(&tag) {
+                            keep.tags.push(tag);
+                        }
+                    }
+                    // on a key clash the kept node's value wins
+                    for (key, value) in discard.properties {
+                        keep.properties.entry(key).or_insert(value);
+                    }
+                }
+            }
+            EditorRequest::ReplaceText {
+                find,
+                replace,
+                case_sensitive,
+                all,
+            } => {
+                let mut count = 0;
+                visit_mut(v, 0, &mut |o, _| {
+                    if o.locked {
+                        return;
+                    }
+                    let (text, n) = replace_text(&o.text, &find, &replace, case_sensitive, all);
+                    if n > 0 {
+                        o.text = text;
+                        o.text_buffer.clone_from(&o.text);
+                        count += n;
+                    }
+                });
+                status = Some(match count {
+                    1 => "Made 1 replacement".to_owned(),
+                    n => format!("Made {n} replacements"),
+                });
+            }
+        }
+    }
+    RequestOutcome { errors, status }
+}
+
+/// `text` with `find` swapped for `replace`, every time or just the first,
+/// and how many swaps that was. Without `case_sensitive`, letters match
+/// whatever their case. An empty `find` matches nothing.
+fn replace_text(
+    text: &str,
+    find: &str,
+    replace: &str,
+    case_sensitive: bool,
+    all: bool,
+) -> (String, usize) {
+    // how many bytes at the start of `hay` match `find`, if they do
+    let match_len = |hay: &str| {
+        let mut hay_chars = hay.char_indices();
+        for f in find.chars() {
+            let (_, h) = hay_chars.next()?;
+            let same = if case_sensitive {
+                h == f
+            } else {
+                h.to_lowercase().eq(f.to_lowercase())
+            };
+            if !same {
+                return None;
+            }
+        }
+        Some(hay_chars.next().map_or(hay.len(), |(i, _)| i))
+    };
+    if find.is_empty() {
+        return (text.to_owned(), 0);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut count = 0;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if (all || count == 0)
+            && let Some(n) = match_len(rest)
+        {
+            out.push_str(replace);
+            rest = &rest[n..];
+            count += 1;
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    (out, count)
+}
+
+/// Appends `nodes` under `parent_id`, or as roots for `None`, unless the
+/// parent's type refuses one of them.
+fn insert_children(
+    v: &mut Vec<SceneObject>,
+    index: &NodeIndex,
+    parent_id: Option<u32>,
+    nodes: Vec<SceneObject>,
+    types: &HashMap<String, NodeTypeSpec>,
+    sound: Option<&SoundPlayer>,
+) -> Result<(), String> {
+    let Some(parent_id) = parent_id else {
+        v.extend(nodes);
+        return Ok(());
+    };
+    if let Some(p) = index.get_mut(v, parent_id) {
+        if let Some(e) = nodes
+            .iter()
+            .find_map(|n| child_refusal(types, p, n.node_type.as_deref()))
+        {
+            return Err(format!("Can't insert into {parent_id}: {e}"));
+        }
+        if let Some(sound) = sound {
+            sound.play_add();
+        }
+        p.children.extend(nodes);
+    }
+    Ok(())
+}
+
+/// Why `parent` can't take a child of type `child_type`, if it can't.
+fn child_refusal(
+    types: &HashMap<String, NodeTypeSpec>,
+    parent: &SceneObject,
+    child_type: Option<&str>,
+) -> Option<String> {
+    let parent_type = parent.node_type.as_deref()?;
+    let spec = types.get(parent_type)?;
+    let allowed = child_type.is_some_and(|t| spec.allowed_child_types.iter().any(|a| a == t));
+    (!allowed).then(|| match child_type {
+        Some(t) => format!("a '{parent_type}' can't hold a '{t}'"),
+        None => format!("a '{parent_type}' only holds typed nodes"),
+    })
+}
+
+/// The type a new child of `parent` gets: the first one it allows, or none
+/// when `parent` is unconstrained.
+fn new_child_type(
+    types: &HashMap<String, NodeTypeSpec>,
+    parent: &SceneObject,
+) -> Result<Option<String>, String> {
+    let Some(parent_type) = &parent.node_type else {
+        return Ok(None);
+    };
+    let Some(spec) = types.get(parent_type) else {
+        return Ok(None);
+    };
+    match spec.allowed_child_types.first() {
+        Some(t) => Ok(Some(t.clone())),
+        None => Err(format!("a '{parent_type}' can't have children")),
+    }
+}
+
+/// Locked nodes keep their color; their descendants are still painted.
+fn paint_subtree(node: &mut SceneObject, color: Color32, blend: bool) {
+    if !node.locked {
+        node.color = if blend {
+            lerp_color(node.color, color, 0.5)
+        } else {
+            color
+        };
+    }
+    for c in &mut node.children {
+        paint_subtree(c, color, blend);
+    }
+}
+
+/// Removes a node (with its subtree) from wherever it sits and hands it back.
+pub fn detach_node(v: &mut Vec<SceneObject>, id: u32) -> Option<SceneObject> {
+    if let Some(i) = v.iter().position(|o| o.id == id) {
+        return Some(v.remove(i));
+    }
+    v.iter_mut().find_map(|o| detach_node(&mut o.children, id))
+}
+
+/// Where each node sits in the tree, as child indices from the roots, so ID
+/// lookups don't have to walk it. Built from a snapshot of the structure; an
+/// entry that has gone stale falls back to the walk rather than misleading.
+#[derive(Debug, Clone, Default)]
+pub struct NodeIndex {
+    paths: HashMap<u32, Vec<usize>>,
+}
+
+impl NodeIndex {
+    pub fn build(v: &[SceneObject]) -> Self {
+        fn walk(v: &[SceneObject], prefix: &mut Vec<usize>, out: &mut HashMap<u32, Vec<usize>>) {
+            for (i, o) in v.iter().enumerate() {
+                prefix.push(i);
+                out.insert(o.id, prefix.clone());
+                walk(&o.children, prefix, out);
+                prefix.pop();
+            }
+        }
+        let mut paths = HashMap::new();
+        walk(v, &mut vec![], &mut paths);
+        Self { paths }
+    }
+
+    pub fn get<'a>(&self, v: &'a [SceneObject], id: u32) -> Option<&'a SceneObject> {
+        self.paths
+            .get(&id)
+            .and_then(|path| object_at_path(v, path))
+            .filter(|o| o.id == id)
+            .or_else(|| find_object_by_id(v, id))
+    }
+
+    /// Unlike `get`, this checks the path before following it: the borrow
+    /// checker won't let a mutable walk that turned out stale fall back to
+    /// the search.
+    pub fn get_mut<'a>(&self, v: &'a mut [SceneObject], id: u32) -> Option<&'a mut SceneObject> {
+        match self
+            .paths
+            .get(&id)
+            .filter(|path| object_at_path(v, path).is_some_and(|o| o.id == id))
+        {
+            Some(path) => object_at_path_mut(v, path),
+            None => find_object_by_id_mut(v, id),
+        }
+    }
+}
+
+pub fn find_object_by_id(v: &[SceneObject], id: u32) -> Option<&SceneObject> {
+    v.iter().find_map(|o| {
+        if o.id == id {
+            Some(o)
+        } else {
+            find_object_by_id(&o.children, id)
+        }
+    })
+}
+pub fn find_object_by_slug<'a>(v: &'a [SceneObject], slug: &str) -> Option<&'a SceneObject> {
+    v.iter().find_map(|o| {
+        if o.slug == slug {
+            Some(o)
+        } else {
+            find_object_by_slug(&o.children, slug)
+        }
+    })
+}
+pub fn find_object_by_id_mut(v: &mut [SceneObject], id: u32) -> Option<&mut SceneObject> {
+    v.iter_mut().find_map(|o| {
+        if o.id == id {
+            Some(o)
+        } else {
+            find_object_by_id_mut(&mut o.children, id)
+        }
+    })
+}
+
+/// IDs from the root containing `target_id` down to the target, inclusive.
+pub fn find_object_path(v: &[SceneObject], target_id: u32) -> Option<Vec<u32>> {
+    v.iter().find_map(|o| {
+        if o.id == target_id {
+            return Some(vec![o.id]);
+        }
+        let mut path = find_object_path(&o.children, target_id)?;
+        path.insert(0, o.id);
+        Some(path)
+    })
+}
+
+/// Names from the root containing `target_id` down to the target, joined
+/// with "/", e.g. `"Root/Data/Mesh"`. A "/" inside a name is written `\/`
+/// and a "\" as `\\`, so the path still splits into the right names.
+pub fn node_path_string(objects: &[SceneObject], target_id: u32) -> Option<String> {
+    fn segment(o: &SceneObject) -> String {
+        o.text
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .replace('\\', "\\\\")
+            .replace('/', "\\/")
+    }
+    objects.iter().find_map(|o| {
+        if o.id == target_id {
+            return Some(segment(o));
+        }
+        let rest = node_path_string(&o.children, target_id)?;
+        Some(format!("{}/{rest}", segment(o)))
+    })
+}
+
+/// Whether `potential_ancestor` lies on the path from the root down to
+/// `node_id`, excluding the node itself.
+pub fn is_ancestor_of(objects: &[SceneObject], potential_ancestor: u32, node_id: u32) -> bool {
+    find_object_path(objects, node_id).is_some_and(|path| {
+        path.split_last()
+            .is_some_and(|(_, above)| above.contains(&potential_ancestor))
+    })
+}
+
+/// How far below its root the node sits; roots are depth 0.
+pub fn depth(nodes: &[SceneObject], target_id: u32) -> Option<usize> {
+    find_object_path(nodes, target_id).map(|path| path.len() - 1)
+}
+
+/// IDs of the other children of the node's parent, in order; for a root, the
+/// other roots. Empty when the node doesn't exist.
+pub fn sibling_ids(objects: &[SceneObject], id: u32) -> Vec<u32> {
+    let Some(path) = find_object_path(objects, id) else {
+        return vec![];
+    };
+    let level = match path.len() {
+        1 => objects,
+        n => match find_object_by_id(objects, path[n - 2]) {
+            Some(parent) => &parent.children,
+            None => return vec![],
+        },
+    };
+    level.iter().map(|o| o.id).filter(|&s| s != id).collect()
+}
+
+/// Walks from a node's parent up to its root. Built from a child → parent map
+/// of the whole scene, so each step is a lookup rather than a tree search.
+pub struct AncestorIter<'a> {
+    parents: HashMap<u32, &'a SceneObject>,
+    current: u32,
+}
+
+impl<'a> AncestorIter<'a> {
+    pub fn new(objects: &'a [SceneObject], node_id: u32) -> Self {
+        fn collect<'a>(v: &'a [SceneObject], parents: &mut HashMap<u32, &'a SceneObject>) {
+            for o in v {
+                for c in &o.children {
+                    parents.insert(c.id, o);
+                }
+                collect(&o.children, parents);
+            }
+        }
+        let mut parents = HashMap::new();
+        collect(objects, &mut parents);
+        Self {
+            parents,
+            current: node_id,
+        }
+    }
+}
+
+impl<'a> Iterator for AncestorIter<'a> {
+    type Item = &'a SceneObject;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let parent = self.parents.get(&self.current).copied()?;
+        self.current = parent.id;
+        Some(parent)
+    }
+}
+
+/// Ancestors of `node_id`, nearest first. Empty for roots and unknown IDs.
+pub fn ancestors_iter(objects: &[SceneObject], node_id: u32) -> AncestorIter<'_> {
+    AncestorIter::new(objects, node_id)
+}
+
+/// World-space box around every laid-out node in `node`'s subtree, grown by
+/// `node_radius` so the shapes fit too. `None` if none of them have a position.
+pub fn subtree_bounding_box(
+    node: &SceneObject,
+    layout: &HashMap<u32, egui::Vec2>,
+    node_radius: f32,
+) -> Option<Rect> {
+    let mut bounds: Option<Rect> = None;
+    visit(std::slice::from_ref(node), 0, &mut |o, _| {
+        if let Some(&p) = layout.get(&o.id) {
+            let r = Rect::from_center_size(Pos2::new(p.x, p.y), egui::Vec2::ZERO);
+            bounds = Some(bounds.map_or(r, |b| b.union(r)));
+        }
+    });
+    bounds.map(|b| b.expand(node_radius))
+}
+
+/// Hash of a subtree's structure: each node's shape and its children's
+/// signatures in order. IDs, names and colors don't count.
+fn subtree_signature(o: &SceneObject) -> u64 {
+    let mut h = DefaultHasher::new();
+    o.shape.hash(&mut h);
+    o.children.len().hash(&mut h);
+    for c in &o.children {
+        subtree_signature(c).hash(&mut h);
+    }
+    h.finish()
+}
+
+/// Branches with the same structure as one met earlier in pre-order, each
+/// with the number of copies the scene has. Leaves don't count, and repeats
+/// inside a repeat aren't listed since they're folded away with it.
+fn duplicate_subtrees(v: &[SceneObject]) -> HashMap<u32, usize> {
+    fn walk(v: &[SceneObject], seen: &mut HashMap<u64, Vec<u32>>) {
+        for o in v.iter().filter(|o| !o.children.is_empty()) {
+            let copies = seen.entry(subtree_signature(o)).or_default();
+            copies.push(o.id);
+            if copies.len() == 1 {
+                walk(&o.children, seen);
+            }
+        }
+    }
+    let mut seen = HashMap::new();
+    walk(v, &mut seen);
+    seen.into_values()
+        .filter(|ids| ids.len() > 1)
+        .flat_map(|ids| {
+            let n = ids.len();
+            ids.into_iter().skip(1).map(move |id| (id, n))
+        })
+        .collect()
+}
+
+/// A node's first line in quotes for messages, or `#id` if it's gone.
+fn quoted_name(objects: &[SceneObject], id: u32) -> String {
+    match find_object_by_id(objects, id) {
+        Some(o) => format!("'{}'", o.text.lines().next().unwrap_or_default()),
+        None => format!("#{id}"),
+    }
+}
+
+/// Nodes breaking any of `rules`, and how many nodes break each one.
+fn check_rules(rules: &[NodeRule], objects: &[SceneObject]) -> (HashSet<u32>, Vec<usize>) {
+    let mut warnings = HashSet::new();
+    let mut counts = vec![0; rules.len()];
+    if rules.is_empty() {
+        return (warnings, counts);
+    }
+    visit(objects, 0, &mut |o, depth| {
+        for (rule, count) in rules.iter().zip(&mut counts) {
+            if rule.condition.violated_by(o, depth) {
+                *count += 1;
+                warnings.insert(o.id);
+            }
+        }
+    });
+    (warnings, counts)
+}
+
+/// How fleshed out a node looks, 0..1: a quarter each for a name of sensible
+/// length (2–32 characters), a non-zero rotation speed, children and tags.
+fn health_score(node: &SceneObject) -> f32 {
+    let name_len = node.text.trim().chars().count();
+    [
+        (2..=32).contains(&name_len),
+        node.rotation_speed != 0.0,
+        !node.children.is_empty(),
+        !node.tags.is_empty(),
+    ]
+    .into_iter()
+    .filter(|&ok| ok)
+    .count() as f32
+        / 4.0
+}
+
+/// What a screen reader should say when `obj` is selected.
+fn node_description(obj: &SceneObject, depth: usize) -> String {
+    let shape = match obj.shape {
+        ShapeKind::Square => "square",
+        ShapeKind::Circle => "circle",
+        ShapeKind::Triangle => "triangle",
+        ShapeKind::Reference { .. } => "reference",
+    };
+    let name = obj.text.lines().next().unwrap_or_default();
+    format!("Selected: {name}, {shape}, depth {depth}")
+}
+
+/// Every node below `node`, at all levels.
+pub fn count_descendants(node: &SceneObject) -> usize {
+    node_count(&node.children)
+}
+
+/// Every node in `objects`, at all levels.
+pub fn node_count(objects: &[SceneObject]) -> usize {
+    objects.iter().map(|o| 1 + node_count(&o.children)).sum()
+}
+
+/// Depth of the deepest descendant, counting `node` itself as 0.
+pub fn max_depth_in_subtree(node: &SceneObject) -> usize {
+    node.children
+        .iter()
+        .map(|c| max_depth_in_subtree(c) + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Deepest treemap tile under `screen`.
+fn find_treemap_object(v: &[SceneObject], screen: Pos2, rects: &HashMap<u32, Rect>) -> Option<u32> {
+    v.iter().find_map(|o| {
+        let r = rects.get(&o.id)?;
+        if !r.contains(screen) {
+            return None;
+        }
+        find_treemap_object(&o.children, screen, rects).or((!o.locked).then_some(o.id))
+    })
+}
+
+fn find_pinned_object(v: &[SceneObject], screen: Pos2) -> Option<u32> {
+    v.iter().find_map(|o| {
+        if o.pinned_to_screen && !o.locked && (screen - o.screen_pos).length() < 20.0 {
+            Some(o.id)
+        } else {
+            find_pinned_object(&o.children, screen)
+        }
+    })
+}
+
+/// What hit-testing needs to turn a node's on-screen size into world units.
+struct HitCtx<'a> {
+    sizes: Option<&'a HashMap<u32, f32>>,
+    zoom: f32,
+    /// Nodes mid-delete, which can't be picked, and neither can anything under them.
+    deleting: &'a HashMap<u32, f32>,
+    snap_rotation: Option<f32>,
+    /// Scene clock in seconds, for scale animations.
+    time: f32,
+}
+
+/// Shape-accurate pick in world space. Nodes are drawn at a fixed pixel size, so
+/// their world-space extent shrinks as the camera zooms in; `scale` and any
+/// scale animation size it the way `draw_world` does. Locked nodes are passed over,
+/// and a node being deleted is skipped along with its whole subtree.
+fn find_clicked_object(
+    o: &SceneObject,
+    w: egui::Vec2,
+    m: &HashMap<u32, egui::Vec2>,
+    hit: &HitCtx,
+) -> Option<u32> {
+    if hit.deleting.contains_key(&o.id) {
+        return None;
+    }
+    if !o.locked
+        && let Some(&p) = m.get(&o.id)
+    {
+        let sz =
+            node_size(hit.sizes, o.id) * o.shown_scale() * scale_factor(o, hit.time) / hit.zoom;
+        let d = w - p;
+        let inside = match o.shape {
+            // a reference is picked by its bounding box, whatever it borrows
+            ShapeKind::Square | ShapeKind::Reference { .. } => {
+                d.x.abs() <= sz / 2.0 && d.y.abs() <= sz / 2.0
+            }
+            ShapeKind::Circle => d.length() <= sz / 2.0,
+            ShapeKind::Triangle => {
+                let rotation = snapped_rotation(o.current_rotation, hit.snap_rotation);
+                let t = triangle_points(Pos2::new(p.x, p.y), sz, rotation);
+                point_in_triangle(Pos2::new(w.x, w.y), t[0], t[1], t[2])
+            }
+        };
+        if inside {
+            return Some(o.id);
+        }
+    }
+    o.children
+        .iter()
+        .find_map(|c| find_clicked_object(c, w, m, hit))
+}
+
+/// Barycentric test; edges count as inside.
+fn point_in_triangle(p: Pos2, a: Pos2, b: Pos2, c: Pos2) -> bool {
+    let cross = |o: Pos2, u: Pos2, v: Pos2| (u - o).x * (v - o).y - (u - o).y * (v - o).x;
+    let area = cross(a, b, c);
+    if area == 0.0 {
+        return false;
+    }
+    let u = cross(p, b, c) / area;
+    let v = cross(a, p, c) / area;
+    let w = 1.0 - u - v;
+    u >= 0.0 && v >= 0.0 && w >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: u32, children: Vec<SceneObject>) -> SceneObject {
+        let mut o = SceneObject::new(id, &format!("n{id}"), ShapeKind::Square, Color32::WHITE);
+        o.children = children;
+        o
+    }
+
+    /// 1 ─┬─ 2 ─── 4
+    ///    └─ 3 ─── 5
+    /// 6
+    fn sample() -> Vec<SceneObject> {
+        vec![
+            node(
+                1,
+                vec![
+                    node(2, vec![node(4, vec![])]),
+                    node(3, vec![node(5, vec![])]),
+                ],
+            ),
+            node(6, vec![]),
+        ]
+    }
+
+    #[test]
+    fn path_to_root_is_just_the_root() {
+        assert_eq!(find_object_path(&sample(), 1), Some(vec![1]));
+        assert_eq!(find_object_path(&sample(), 6), Some(vec![6]));
+    }
+
+    #[test]
+    fn path_to_leaf_lists_every_ancestor() {
+        assert_eq!(find_object_path(&sample(), 4), Some(vec![1, 2, 4]));
+    }
+
+    #[test]
+    fn path_to_missing_node_is_none() {
+        assert_eq!(find_object_path(&sample(), 99), None);
+    }
+
+    #[test]
+    fn path_string_of_a_root_is_its_name() {
+        assert_eq!(node_path_string(&sample(), 6).as_deref(), Some("n6"));
+        assert_eq!(node_path_string(&sample(), 99), None);
+    }
+
+    #[test]
+    fn path_string_joins_names_down_to_the_node() {
+        assert_eq!(node_path_string(&sample(), 5).as_deref(), Some("n1/n3/n5"));
+    }
+
+    #[test]
+    fn scene_object_loads_without_the_optional_fields() {
+        let mut json = serde_json::to_value(node(1, vec![])).unwrap();
+        for key in [
+            "comment",
+            "fill_mode",
+            "edge_style",
+            "pinned_to_screen",
+            "screen_pos",
+        ] {
+            json.as_object_mut().unwrap().remove(key);
+        }
+        let o: SceneObject = serde_json::from_value(json).unwrap();
+        assert_eq!(o.fill_mode, FillMode::Solid);
+        assert_eq!(o.edge_style, EdgeStyle::Solid);
+        assert!(o.comment.is_empty() && !o.pinned_to_screen);
+    }
+
+    #[test]
+    fn path_string_escapes_slashes_in_names() {
+        let mut v = sample();
+        v[0].text = "Root".into();
+        v[0].children[0].text = "in/out".into();
+        assert_eq!(node_path_string(&v, 4).as_deref(), Some("Root/in\\/out/n4"));
+    }
+
+    #[test]
+    fn path_string_escapes_backslashes_before_slashes() {
+        // a trailing "\" mustn't turn the separator after it into an escaped "/"
+        let mut v = sample();
+        v[0].text = "C:\\".into();
+        assert_eq!(node_path_string(&v, 2).as_deref(), Some("C:\\\\/n2"));
+    }
+
+    #[test]
+    fn path_into_sibling_subtree() {
+        assert_eq!(find_object_path(&sample(), 5), Some(vec![1, 3, 5]));
+    }
+
+    #[test]
+    fn flatten_is_depth_first_pre_order() {
+        let v = sample();
+        let ids: Vec<u32> = flatten(&v).iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec![1, 2, 4, 3, 5, 6]);
+    }
+
+    #[test]
+    fn flatten_visits_every_node_of_a_deep_tree_once() {
+        // five levels, two children per node
+        fn build(id: &mut u32, level: u32) -> SceneObject {
+            *id += 1;
+            let me = *id;
+            let children = if level < 5 {
+                vec![build(id, level + 1), build(id, level + 1)]
+            } else {
+                vec![]
+            };
+            node(me, children)
+        }
+        let mut next = 0;
+        let v = vec![build(&mut next, 1)];
+        let mut ids: Vec<u32> = flatten(&v).iter().map(|o| o.id).collect();
+        assert_eq!(ids.len(), 31);
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids, (1..=31).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn flatten_mut_paths_follow_flatten_order_and_mutate_the_tree() {
+        let mut v = sample();
+        let paths = flatten_mut(&mut v);
+        assert_eq!(paths.len(), 6);
+        for path in &paths {
+            let o = object_at_path_mut(&mut v, path).unwrap();
+            o.text = format!("seen {}", o.id);
+        }
+        let order: Vec<u32> = flatten(&v).iter().map(|o| o.id).collect();
+        assert_eq!(order, vec![1, 2, 4, 3, 5, 6]);
+        assert!(
+            flatten(&v)
+                .iter()
+                .all(|o| o.text == format!("seen {}", o.id))
+        );
+        assert_eq!(find_object_by_id(&v, 5).unwrap().text, "seen 5");
+    }
+
+    #[test]
+    fn click_events_skip_locked_targets() {
+        let mut v = sample();
+        find_object_by_id_mut(&mut v, 5).unwrap().locked = true;
+        let mut reqs = vec![EditorRequest::FireClickEvents {
+            node_id: 1,
+            targets: vec![4, 5],
+            events: vec![NodeEvent::SetColor(Color32::RED), NodeEvent::ToggleDisabled],
+        }];
+        process_requests(
+            &mut v,
+            &NodeIndex::default(),
+            &mut reqs,
+            &mut 7,
+            &HashMap::new(),
+            None,
+        );
+        let four = find_object_by_id(&v, 4).unwrap();
+        assert_eq!(four.color, Color32::RED);
+        assert!(four.disabled);
+        let five = find_object_by_id(&v, 5).unwrap();
+        assert_eq!(five.color, Color32::WHITE);
+        assert!(!five.disabled);
+    }
+
+    #[test]
+    fn memo_underscores_inside_words_stay_literal() {
+        let style = egui::Style::default();
+        let job = memo_layout("snake_case_name", &style);
+        assert_eq!(job.text, "snake_case_name");
+        assert!(job.sections.iter().all(|s| !s.format.italics));
+
+        let job = memo_layout("_my_var_ here", &style);
+        assert_eq!(job.text, "my_var here");
+        assert!(job.sections[0].format.italics);
+        assert!(!job.sections.last().unwrap().format.italics);
+
+        let job = memo_layout("_über_ straße_name", &style);
+        assert_eq!(job.text, "über straße_name");
+        assert!(job.sections[0].format.italics);
+        assert!(!job.sections.last().unwrap().format.italics);
+    }
+
+    fn move_child(new_index: usize) -> Vec<u32> {
+        // 1's children start out as [2, 3, 7, 8]
+        let mut v = sample();
+        v[0].children.push(node(7, vec![]));
+        v[0].children.push(node(8, vec![]));
+        let mut reqs = vec![EditorRequest::MoveChild {
+            parent_id: 1,
+            child_id: 3,
+            new_index,
+        }];
+        process_requests(
+            &mut v,
+            &NodeIndex::default(),
+            &mut reqs,
+            &mut 8,
+            &HashMap::new(),
+            None,
+        );
+        v[0].children.iter().map(|c| c.id).collect()
+    }
+
+    #[test]
+    fn move_child_to_front() {
+        assert_eq!(move_child(0), vec![3, 2, 7, 8]);
+    }
+
+    #[test]
+    fn move_child_to_last() {
+        assert_eq!(move_child(3), vec![2, 7, 8, 3]);
+    }
+
+    #[test]
+    fn move_child_to_same_index_is_a_no_op() {
+        assert_eq!(move_child(1), vec![2, 3, 7, 8]);
+    }
+
+    #[test]
+    fn move_child_past_the_end_clamps_to_last() {
+        assert_eq!(move_child(99), vec![2, 7, 8, 3]);
+    }
+
+    #[test]
+    fn paste_inserts_the_whole_subtree() {
+        let mut v = sample();
+        let pasted = node(7, vec![node(8, vec![])]);
+        let mut reqs = vec![EditorRequest::Paste {
+            parent_id: Some(4),
+            node: Box::new(pasted),
+        }];
+        process_requests(
+            &mut v,
+            &NodeIndex::default(),
+            &mut reqs,
+            &mut 8,
+            &HashMap::new(),
+            None,
+        );
+        assert_eq!(find_object_path(&v, 8), Some(vec![1, 2, 4, 7, 8]));
+    }
+
+    #[test]
+    fn rules_count_the_nodes_breaking_them() {
+        // depths: 1 → 0, 2 and 3 → 1, 4 and 5 → 2, 6 → 0
+        let rule = |condition| NodeRule {
+            condition,
+            message: String::new(),
+        };
+        let rules = [
+            rule(RuleCondition::DepthGreaterThan(1)),
+            rule(RuleCondition::ChildCountGreaterThan(1)),
+        ];
+        let (warnings, counts) = check_rules(&rules, &sample());
+        assert_eq!(counts, vec![2, 1]);
+        assert_eq!(warnings, HashSet::from([1, 4, 5]));
+    }
+
+    #[test]
+    fn undoing_a_paste_removes_the_pasted_subtree() {
+        use eframe::App;
+
+        let ctx = egui::Context::default();
+        let mut app = AppState::new(&eframe::CreationContext::_new_kittest(ctx.clone())).unwrap();
+        let mut frame = eframe::Frame::_new_kittest();
+        let mut run_frame = |app: &mut AppState| {
+            let _ = ctx.run(egui::RawInput::default(), |ctx| app.update(ctx, &mut frame));
+        };
+        run_frame(&mut app);
+
+        let before = ids_in(&app.scene_objects);
+        let parent = app.scene_objects[0].id;
+        let json = serde_json::to_string(&node(900, vec![node(901, vec![])])).unwrap();
+        app.clipboard_history.push_front(json.clone());
+        app.active_settings_id = Some(parent);
+        app.paste_subtree(&json, None, &egui::InputState::default());
+        run_frame(&mut app);
+        let pasted: HashSet<u32> = ids_in(&app.scene_objects)
+            .difference(&before)
+            .copied()
+            .collect();
+        assert_eq!(pasted.len(), 2);
+
+        app.undo();
+        assert_eq!(ids_in(&app.scene_objects), before);
+        assert!(app.selected_ids.is_empty());
+        assert_eq!(app.clipboard_history, VecDeque::from([json]));
+
+        app.redo();
+        let root = app.scene_objects[0].children.last().map(|c| c.id);
+        assert!(root.is_some_and(|id| pasted.contains(&id)));
+        assert!(
+            pasted
+                .iter()
+                .all(|&id| find_object_by_id(&app.scene_objects, id).is_some())
+        );
+    }
+
+    #[test]
+    fn undo_during_a_delete_shrink_calls_the_delete_off() {
+        use eframe::App;
+
+        let ctx = egui::Context::default();
+        let mut app = AppState::new(&eframe::CreationContext::_new_kittest(ctx.clone())).unwrap();
+        let mut frame = eframe::Frame::_new_kittest();
+        let mut run_frame = |app: &mut AppState| {
+            let _ = ctx.run(egui::RawInput::default(), |ctx| app.update(ctx, &mut frame));
+        };
+        run_frame(&mut app);
+
+        let parent = app.scene_objects[0].id;
+        app.requests.push(EditorRequest::InsertChildren {
+            parent_id: Some(parent),
+            nodes: vec![node(900, vec![])],
+        });
+        run_frame(&mut app);
+        let added = app.scene_objects[0].children.last().unwrap().id;
+        let undo_depth = app.undo_stack.len();
+
+        app.requests
+            .push(EditorRequest::DeleteNode { node_id: added });
+        run_frame(&mut app);
+        assert!(app.deleting_nodes.contains_key(&added));
+
+        app.undo();
+        assert!(app.deleting_nodes.is_empty());
+        assert_eq!(app.undo_stack.len(), undo_depth);
+        assert!(find_object_by_id(&app.scene_objects, added).is_some());
+    }
+
+    #[test]
+    fn scale_track_leaves_the_saved_scale_alone() {
+        let mut v = vec![node(1, vec![])];
+        v[0].animations.push(AnimationTrack {
+            property: AnimatableProperty::Scale,
+            keyframes: vec![(0.0, 2.0), (1.0, 3.0)],
+            loop_mode: LoopMode::Once,
+        });
+        let env = vm::Env {
+            t: 0.5,
+            dt: 0.0,
+            frame: 0,
+        };
+        animate(&mut v, 1.0, &env);
+        assert_eq!(v[0].scale, 1.0);
+        assert_eq!(v[0].shown_scale(), 2.5);
+
+        v[0].animations.clear();
+        animate(&mut v, 1.0, &env);
+        assert_eq!(v[0].shown_scale(), 1.0);
+    }
+
+    fn reparent(node_id: u32, new_parent_id: u32) -> (Vec<SceneObject>, Vec<String>) {
+        let mut v = sample();
+        let mut reqs = vec![EditorRequest::Reparent {
+            node_id,
+            new_parent_id: Some(new_parent_id),
+        }];
+        let errors = process_requests(
+            &mut v,
+            &NodeIndex::default(),
+            &mut reqs,
+            &mut 6,
+            &HashMap::new(),
+            None,
+        )
+        .errors;
+        (v, errors)
+    }
+
+    #[test]
+    fn reparent_onto_itself_is_refused() {
+        let (v, errors) = reparent(2, 2);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(find_object_path(&v, 2), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn reparent_into_own_subtree_is_refused() {
+        assert!(is_ancestor_of(&sample(), 1, 4));
+        let (v, errors) = reparent(1, 4);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(find_object_path(&v, 4), Some(vec![1, 2, 4]));
+    }
+
+    #[test]
+    fn reparent_under_a_sibling_moves_the_subtree() {
+        let (v, errors) = reparent(2, 3);
+        assert!(errors.is_empty());
+        assert_eq!(find_object_path(&v, 4), Some(vec![1, 3, 2, 4]));
+    }
+
+    #[test]
+    fn reparent_under_an_unrelated_node() {
+        assert!(!is_ancestor_of(&sample(), 6, 5));
+        let (v, errors) = reparent(5, 6);
+        assert!(errors.is_empty());
+        assert_eq!(find_object_path(&v, 5), Some(vec![6, 5]));
+    }
+
+    #[test]
+    fn move_child_keeps_the_subtree() {
+        let mut v = sample();
+        let mut reqs = vec![EditorRequest::MoveChild {
+            parent_id: 1,
+            child_id: 2,
+            new_index: 1,
+        }];
+        process_requests(
+            &mut v,
+            &NodeIndex::default(),
+            &mut reqs,
+            &mut 6,
+            &HashMap::new(),
+            None,
+        );
+        assert_eq!(find_object_path(&v, 4), Some(vec![1, 2, 4]));
+        assert_eq!(v[0].children[1].id, 2);
+    }
+
+    /// 1 ─┬─ 2 ─── 3 ─── 4
+    ///    └─ 5
+    fn four_levels() -> Vec<SceneObject> {
+        vec![node(
+            1,
+            vec![
+                node(2, vec![node(3, vec![node(4, vec![])])]),
+                node(5, vec![]),
+            ],
+        )]
+    }
+
+    #[test]
+    fn depth_counts_levels_below_the_root() {
+        let v = four_levels();
+        assert_eq!(depth(&v, 1), Some(0));
+        assert_eq!(depth(&v, 2), Some(1));
+        assert_eq!(depth(&v, 5), Some(1));
+        assert_eq!(depth(&v, 4), Some(3));
+        assert_eq!(depth(&v, 99), None);
+    }
+
+    #[test]
+    fn max_depth_in_subtree_follows_the_deepest_branch() {
+        let v = four_levels();
+        assert_eq!(max_depth_in_subtree(&v[0]), 3);
+        assert_eq!(max_depth_in_subtree(&v[0].children[0]), 2);
+        assert_eq!(max_depth_in_subtree(&v[0].children[1]), 0);
+    }
+
+    #[test]
+    fn layout_places_a_lone_root_at_the_start_y() {
+        let m = tree_layout(&[node(1, vec![])]);
+        assert_eq!(m[&1], egui::Vec2::new(200.0, 100.0));
+    }
+
+    #[test]
+    fn layout_centres_a_parent_between_its_children() {
+        let m = tree_layout(&[node(1, vec![node(2, vec![]), node(3, vec![])])]);
+        assert_eq!(m[&1].y, (m[&2].y + m[&3].y) / 2.0);
+        assert!(m[&2].x > m[&1].x);
+    }
+
+    #[test]
+    fn layout_keeps_each_column_apart() {
+        let m = tree_layout(&sample());
+        let mut columns: HashMap<i32, Vec<f32>> = HashMap::new();
+        for p in m.values() {
+            columns.entry(p.x as i32).or_default().push(p.y);
+        }
+        for ys in columns.values_mut() {
+            ys.sort_by(f32::total_cmp);
+            assert!(ys.windows(2).all(|w| w[1] - w[0] >= 120.0), "{ys:?}");
+        }
+        assert_eq!(m.len(), 6);
+    }
+
+    #[test]
+    fn layout_of_an_empty_scene_is_empty() {
+        assert!(tree_layout(&[]).is_empty());
+    }
+
+    #[test]
+    fn siblings_of_a_root_are_the_other_roots() {
+        assert_eq!(sibling_ids(&sample(), 1), vec![6]);
+    }
+
+    #[test]
+    fn siblings_of_a_leaf_share_its_parent() {
+        assert_eq!(sibling_ids(&four_levels(), 5), vec![2]);
+    }
+
+    #[test]
+    fn an_only_child_has_no_siblings() {
+        assert!(sibling_ids(&sample(), 4).is_empty());
+    }
+
+    #[test]
+    fn a_missing_node_has_no_siblings() {
+        assert!(sibling_ids(&sample(), 99).is_empty());
+    }
+
+    #[test]
+    fn ancestors_run_from_parent_up_to_root() {
+        let v = four_levels();
+        let ids: Vec<u32> = ancestors_iter(&v, 4).map(|o| o.id).collect();
+        assert_eq!(ids, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn a_root_has_no_ancestors() {
+        assert_eq!(ancestors_iter(&sample(), 6).count(), 0);
+        assert_eq!(ancestors_iter(&sample(), 99).count(), 0);
+    }
+
+    #[test]
+    fn bounding_box_of_a_leaf_is_just_its_radius() {
+        let v = sample();
+        let layout = tree_layout(&v);
+        let b = subtree_bounding_box(&v[1], &layout, 10.0).unwrap();
+        let p = layout[&6];
+        assert_eq!(
+            b,
+            Rect::from_center_size(Pos2::new(p.x, p.y), egui::Vec2::splat(20.0))
+        );
+    }
+
+    #[test]
+    fn bounding_box_grows_with_deeper_children() {
+        let mut v = sample();
+        let before = subtree_bounding_box(&v[0], &tree_layout(&v), 10.0).unwrap();
+        v[0].children[0].children[0].children.push(node(7, vec![]));
+        let after = subtree_bounding_box(&v[0], &tree_layout(&v), 10.0).unwrap();
+        assert!(after.width() > before.width());
+        assert!(after.contains_rect(before));
+    }
+
+    #[test]
+    fn bounding_box_grows_with_wider_children() {
+        let mut v = sample();
+        let before = subtree_bounding_box(&v[0], &tree_layout(&v), 10.0).unwrap();
+        v[0].children.push(node(7, vec![]));
+        let after = subtree_bounding_box(&v[0], &tree_layout(&v), 10.0).unwrap();
+        assert!(after.height() > before.height());
+    }
+
+    #[test]
+    fn bounding_box_without_positions_is_none() {
+        assert_eq!(
+            subtree_bounding_box(&sample()[0], &HashMap::new(), 10.0),
+            None
+        );
+    }
+
+    /// Writes `objects` out as a scene file and reads it back, the way Save
+    /// Scene and Open Scene do, and checks nothing saved came back different.
+    fn assert_round_trips(name: &str, objects: Vec<SceneObject>) {
+        let path =
+            std::env::temp_dir().join(format!("roundtrip-{}-{name}.json", std::process::id()));
+        let scene = SceneFile {
+            objects,
+            ..SceneFile::default()
+        };
+        scene_file::save(&path, &scene).unwrap();
+        let loaded = scene_file::load(&path);
+        let _ = std::fs::remove_file(&path);
+        let loaded = loaded.unwrap().objects;
+        // `==` only compares IDs, so the contents are compared as JSON
+        assert_eq!(loaded, scene.objects);
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&scene.objects).unwrap()
+        );
+    }
+
+    #[test]
+    fn sample_scene_round_trips_through_a_scene_file() {
+        assert_round_trips("sample", sample_scene(&mut 0));
+    }
+
+    #[test]
+    fn empty_and_single_node_scenes_round_trip() {
+        assert_round_trips("empty", vec![]);
+        assert_round_trips("single", vec![node(1, vec![])]);
+    }
+
+    #[test]
+    fn every_shape_round_trips() {
+        let shapes = [
+            ShapeKind::Square,
+            ShapeKind::Circle,
+            ShapeKind::Triangle,
+            ShapeKind::Reference { target_id: 1 },
+        ];
+        let objects = (1..)
+            .zip(shapes)
+            .map(|(id, shape)| SceneObject::new(id, "shape", shape, Color32::RED))
+            .collect();
+        assert_round_trips("shapes", objects);
+    }
+
+    #[test]
+    fn properties_and_tags_round_trip() {
+        let mut o = node(1, vec![node(2, vec![])]);
+        o.tags = vec!["alpha".into(), "beta".into()];
+        o.properties.insert("owner".into(), "render team".into());
+        o.properties.insert("empty".into(), String::new());
+        o.children[0].tags.push("leaf".into());
+        assert_round_trips("properties", vec![o]);
+    }
+
+    #[test]
+    fn nodes_with_the_same_id_are_equal_whatever_their_content() {
+        let a = SceneObject::new(1, "A", ShapeKind::Square, Color32::RED);
+        let mut b = SceneObject::new(1, "B", ShapeKind::Circle, Color32::BLUE);
+        b.children.push(node(2, vec![]));
+        assert_eq!(a, b);
+        assert_ne!(a, SceneObject::new(2, "A", ShapeKind::Square, Color32::RED));
+        assert_eq!(HashSet::from([a, b]).len(), 1);
     }
-    o.children.iter().find_map(|c| find_clicked_object(c, w, m))
 }
@@ -0,0 +1,53 @@
+//! Editor sound effects. Playback needs the `sound` cargo feature; without it
+//! `SoundPlayer` is a silent stand-in so callers don't need their own cfgs.
+
+use crate::error::AppError;
+
+/// Raw mono 16-bit little-endian PCM at `SAMPLE_RATE`.
+#[cfg(feature = "sound")]
+const POP: &[u8] = include_bytes!("../assets/pop.pcm");
+#[cfg(feature = "sound")]
+const WHOOSH: &[u8] = include_bytes!("../assets/whoosh.pcm");
+#[cfg(feature = "sound")]
+const SAMPLE_RATE: u32 = 22_050;
+
+pub struct SoundPlayer {
+    #[cfg(feature = "sound")]
+    stream: rodio::OutputStream,
+}
+
+impl SoundPlayer {
+    #[cfg(feature = "sound")]
+    pub fn new() -> Result<Self, AppError> {
+        let mut stream = rodio::OutputStreamBuilder::open_default_stream()
+            .map_err(|e| AppError::AudioError(e.to_string()))?;
+        stream.log_on_drop(false);
+        Ok(Self { stream })
+    }
+
+    #[cfg(not(feature = "sound"))]
+    pub fn new() -> Result<Self, AppError> {
+        Ok(Self {})
+    }
+
+    pub fn play_add(&self) {
+        #[cfg(feature = "sound")]
+        self.play(POP);
+    }
+
+    pub fn play_delete(&self) {
+        #[cfg(feature = "sound")]
+        self.play(WHOOSH);
+    }
+
+    #[cfg(feature = "sound")]
+    fn play(&self, pcm: &[u8]) {
+        let samples: Vec<f32> = pcm
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / i16::MAX as f32)
+            .collect();
+        self.stream
+            .mixer()
+            .add(rodio::buffer::SamplesBuffer::new(1, SAMPLE_RATE, samples));
+    }
+}
@@ -0,0 +1,76 @@
+//! Text files by path. Native builds use the filesystem; the web build keeps each
+//! "file" in the browser's `localStorage` under its path.
+
+use std::io;
+use std::path::Path;
+
+use eframe::egui::ColorImage;
+#[cfg(target_arch = "wasm32")]
+use eframe::web_sys;
+
+use crate::error::AppError;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(path: &Path) -> io::Result<String> {
+    std::fs::read_to_string(path)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(path: &Path, text: &str) -> io::Result<()> {
+    std::fs::write(path, text)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> io::Result<web_sys::Storage> {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .ok_or_else(|| io::Error::other("localStorage is not available"))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read(path: &Path) -> io::Result<String> {
+    local_storage()?
+        .get_item(&path.to_string_lossy())
+        .map_err(|_| io::Error::other("localStorage read failed"))?
+        .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write(path: &Path, text: &str) -> io::Result<()> {
+    local_storage()?
+        .set_item(&path.to_string_lossy(), text)
+        .map_err(|_| io::Error::other("localStorage write failed (quota exceeded?)"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn exists(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn exists(path: &Path) -> bool {
+    local_storage()
+        .ok()
+        .and_then(|s| s.get_item(&path.to_string_lossy()).ok().flatten())
+        .is_some()
+}
+
+/// A PNG or JPEG decoded into RGBA.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_image(path: &Path) -> Result<ColorImage, AppError> {
+    let image = image::ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()?
+        .to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    Ok(ColorImage::from_rgba_unmultiplied(size, image.as_raw()))
+}
+
+/// Images aren't kept in `localStorage`, so there is nothing to read.
+#[cfg(target_arch = "wasm32")]
+pub fn read_image(path: &Path) -> Result<ColorImage, AppError> {
+    Err(AppError::IoError(io::Error::other(format!(
+        "{} can't be read in the web build",
+        path.display()
+    ))))
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use eframe::egui::{self, Color32, Pos2};
+
+use crate::sketch::{
+    NODE_SIZE, NodeIndex, SceneObject, ShapeKind, resolve_appearance, triangle_points,
+};
+
+/// Room around the outermost nodes for their shapes and labels.
+const MARGIN: f32 = 60.0;
+
+fn hex(c: Color32) -> String {
+    let [r, g, b, _] = c.to_array();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn write_edges(out: &mut String, o: &SceneObject, layout: &HashMap<u32, egui::Vec2>) {
+    for c in &o.children {
+        if let (Some(a), Some(b)) = (layout.get(&o.id), layout.get(&c.id)) {
+            let _ = writeln!(
+                out,
+                "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" \
+                 stroke=\"#808080\" stroke-width=\"{}\"/>",
+                a.x,
+                a.y,
+                b.x,
+                b.y,
+                1.0 + c.edge_weight * 0.5,
+            );
+        }
+        write_edges(out, c, layout);
+    }
+}
+
+/// `scene` and `index` are the whole scene, for drawing references as their targets.
+fn write_node(
+    out: &mut String,
+    o: &SceneObject,
+    layout: &HashMap<u32, egui::Vec2>,
+    scene: &[SceneObject],
+    index: &NodeIndex,
+) {
+    if let Some(&p) = layout.get(&o.id) {
+        let (shape, color) = resolve_appearance(scene, index, o);
+        let fill = hex(color);
+        let half = NODE_SIZE / 2.0;
+        let _ = match shape {
+            ShapeKind::Square | ShapeKind::Reference { .. } => writeln!(
+                out,
+                "  <rect x=\"{}\" y=\"{}\" width=\"{NODE_SIZE}\" height=\"{NODE_SIZE}\" \
+                 rx=\"{}\" fill=\"{fill}\"/>",
+                p.x - half,
+                p.y - half,
+                o.corner_radius,
+            ),
+            ShapeKind::Circle => writeln!(
+                out,
+                "  <circle cx=\"{}\" cy=\"{}\" r=\"{half}\" fill=\"{fill}\"/>",
+                p.x, p.y,
+            ),
+            ShapeKind::Triangle => {
+                let points = triangle_points(Pos2::new(p.x, p.y), NODE_SIZE, o.current_rotation)
+                    .iter()
+                    .map(|q| format!("{},{}", q.x, q.y))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                writeln!(out, "  <polygon points=\"{points}\" fill=\"{fill}\"/>")
+            }
+        };
+        // one tspan per line, hanging under the shape like the canvas label
+        let text_fill = o.text_color.map_or_else(|| "#dcdcdc".to_owned(), hex);
+        let _ = write!(
+            out,
+            "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-size=\"{}\" \
+             fill=\"{text_fill}\">",
+            p.x,
+            p.y + half + o.text_size,
+            o.text_size,
+        );
+        for (i, line) in o.text.lines().enumerate() {
+            let dy = if i == 0 { "0" } else { "1.2em" };
+            let _ = write!(
+                out,
+                "<tspan x=\"{}\" dy=\"{dy}\">{}</tspan>",
+                p.x,
+                escape(line)
+            );
+        }
+        out.push_str("</text>\n");
+    }
+    for c in &o.children {
+        write_node(out, c, layout, scene, index);
+    }
+}
+
+/// Renders the laid-out part of the scene as an SVG document of `canvas_size`
+/// pixels, with the viewBox fitted to the nodes. Nodes missing from `layout`
+/// (pinned, hidden or collapsed away) are left out.
+pub fn export_svg(
+    objects: &[SceneObject],
+    layout: &HashMap<u32, egui::Vec2>,
+    canvas_size: egui::Vec2,
+) -> String {
+    let (min, max) = layout.values().fold(
+        (egui::Vec2::splat(f32::MAX), egui::Vec2::splat(f32::MIN)),
+        |(lo, hi), &p| (lo.min(p), hi.max(p)),
+    );
+    let (min, size) = if layout.is_empty() {
+        (egui::Vec2::ZERO, canvas_size)
+    } else {
+        (
+            min - egui::Vec2::splat(MARGIN),
+            max - min + egui::Vec2::splat(MARGIN * 2.0),
+        )
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         viewBox=\"{} {} {} {}\">",
+        canvas_size.x, canvas_size.y, min.x, min.y, size.x, size.y,
+    );
+    let _ = writeln!(
+        out,
+        "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#1b1b1b\"/>",
+        min.x, min.y, size.x, size.y,
+    );
+    for o in objects {
+        write_edges(&mut out, o, layout);
+    }
+    let index = NodeIndex::build(objects);
+    for o in objects {
+        write_node(&mut out, o, layout, objects, &index);
+    }
+    out.push_str("</svg>\n");
+    out
+}
@@ -0,0 +1,158 @@
+//! Ready-made example scenes for File → New from Template. Each builder takes
+//! the scene's ID counter and bumps it once per node, like `AppState::new_id`.
+
+use eframe::egui::Color32;
+use rand::Rng;
+
+use crate::sketch::{SceneObject, ShapeKind};
+
+/// A gallery entry: its name and the function that builds it.
+pub struct Template {
+    pub name: &'static str,
+    pub build: fn(&mut u32) -> Vec<SceneObject>,
+}
+
+pub const TEMPLATES: &[Template] = &[
+    Template {
+        name: "Binary Tree (depth 4)",
+        build: build_binary_tree_scene,
+    },
+    Template {
+        name: "Linear Chain (10 nodes)",
+        build: build_linear_chain_scene,
+    },
+    Template {
+        name: "Star (root + 8 leaves)",
+        build: build_star_scene,
+    },
+    Template {
+        name: "Grid (4×4)",
+        build: build_grid_scene,
+    },
+    Template {
+        name: "Random Tree (20 nodes)",
+        build: build_random_tree_scene,
+    },
+];
+
+const SHAPES: [ShapeKind; 3] = [ShapeKind::Square, ShapeKind::Circle, ShapeKind::Triangle];
+
+/// Colors the builders cycle through.
+const PALETTE: [Color32; 6] = [
+    Color32::RED,
+    Color32::BLUE,
+    Color32::GREEN,
+    Color32::YELLOW,
+    Color32::from_rgb(255, 128, 0),
+    Color32::from_rgb(128, 0, 255),
+];
+
+fn node(next_id: &mut u32, name: &str, shape: ShapeKind, color: Color32) -> SceneObject {
+    *next_id += 1;
+    SceneObject::new(*next_id, name, shape, color)
+}
+
+/// Nests `nodes` so each is the only child of the one before it.
+fn chain(nodes: Vec<SceneObject>) -> Option<SceneObject> {
+    nodes.into_iter().rev().reduce(|child, mut parent| {
+        parent.children.push(child);
+        parent
+    })
+}
+
+/// A single root, for starting from nothing.
+pub fn build_blank_scene(next_id: &mut u32) -> Vec<SceneObject> {
+    vec![node(next_id, "Root", ShapeKind::Square, Color32::RED)]
+}
+
+/// Full binary tree, four levels including the root (15 nodes).
+pub fn build_binary_tree_scene(next_id: &mut u32) -> Vec<SceneObject> {
+    fn level(next_id: &mut u32, name: String, depth: usize) -> SceneObject {
+        let mut o = node(
+            next_id,
+            &name,
+            SHAPES[depth % 3],
+            PALETTE[depth % PALETTE.len()],
+        );
+        if depth < 3 {
+            for side in ["L", "R"] {
+                o.children
+                    .push(level(next_id, format!("{name}{side}"), depth + 1));
+            }
+        }
+        o
+    }
+    vec![level(next_id, "N".to_owned(), 0)]
+}
+
+/// Ten nodes, each the only child of the one before.
+pub fn build_linear_chain_scene(next_id: &mut u32) -> Vec<SceneObject> {
+    let nodes: Vec<SceneObject> = (0..10)
+        .map(|i| {
+            let color = PALETTE[i % PALETTE.len()];
+            node(
+                next_id,
+                &format!("Step {}", i + 1),
+                ShapeKind::Circle,
+                color,
+            )
+        })
+        .collect();
+    chain(nodes).into_iter().collect()
+}
+
+/// A root with eight leaves directly under it.
+pub fn build_star_scene(next_id: &mut u32) -> Vec<SceneObject> {
+    let mut root = node(next_id, "Hub", ShapeKind::Square, Color32::RED);
+    for i in 0..8 {
+        let color = PALETTE[1 + i % (PALETTE.len() - 1)];
+        root.children.push(node(
+            next_id,
+            &format!("Leaf {}", i + 1),
+            SHAPES[i % 3],
+            color,
+        ));
+    }
+    vec![root]
+}
+
+/// Four roots, each heading a chain of four, which the tree layout draws as
+/// rows and columns.
+pub fn build_grid_scene(next_id: &mut u32) -> Vec<SceneObject> {
+    (0..4)
+        .filter_map(|row| {
+            let cells: Vec<SceneObject> = (0..4)
+                .map(|col| {
+                    let name = format!("{}{}", (b'A' + row as u8) as char, col + 1);
+                    node(
+                        next_id,
+                        &name,
+                        ShapeKind::Square,
+                        PALETTE[col % PALETTE.len()],
+                    )
+                })
+                .collect();
+            chain(cells)
+        })
+        .collect()
+}
+
+/// Twenty nodes, each hung under a random earlier one.
+pub fn build_random_tree_scene(next_id: &mut u32) -> Vec<SceneObject> {
+    let mut rng = rand::rng();
+    // children are collected by parent index first, then nested bottom-up,
+    // since later nodes only ever hang under earlier ones
+    let mut nodes: Vec<SceneObject> = (0..20)
+        .map(|i| {
+            let shape = SHAPES[rng.random_range(0..SHAPES.len())];
+            let color = PALETTE[rng.random_range(0..PALETTE.len())];
+            node(next_id, &format!("Node {}", i + 1), shape, color)
+        })
+        .collect();
+    let parents: Vec<usize> = (1..nodes.len()).map(|i| rng.random_range(0..i)).collect();
+    for i in (1..nodes.len()).rev() {
+        let child = nodes.remove(i);
+        nodes[parents[i - 1]].children.insert(0, child);
+    }
+    nodes
+}
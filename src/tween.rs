@@ -0,0 +1,97 @@
+//! Timed interpolation of a single node property, started through
+//! `AppState::tween` and advanced once per frame.
+
+use eframe::egui::Color32;
+
+use crate::sketch::SceneObject;
+
+/// Which property of a node a tween drives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TweenProperty {
+    RotationSpeed,
+    Opacity,
+    Scale,
+    /// One color channel, 0–255.
+    ColorR,
+    ColorG,
+    ColorB,
+}
+
+impl TweenProperty {
+    pub fn get(self, o: &SceneObject) -> f32 {
+        let [r, g, b, _] = o.color.to_array();
+        match self {
+            Self::RotationSpeed => o.rotation_speed,
+            Self::Opacity => o.opacity,
+            Self::Scale => o.scale,
+            Self::ColorR => r as f32,
+            Self::ColorG => g as f32,
+            Self::ColorB => b as f32,
+        }
+    }
+
+    pub fn set(self, o: &mut SceneObject, value: f32) {
+        let [mut r, mut g, mut b, a] = o.color.to_array();
+        let channel = value.round().clamp(0.0, 255.0) as u8;
+        match self {
+            Self::RotationSpeed => o.rotation_speed = value,
+            Self::Opacity => o.opacity = value.clamp(0.0, 1.0),
+            Self::Scale => o.scale = value.max(0.0),
+            Self::ColorR => r = channel,
+            Self::ColorG => g = channel,
+            Self::ColorB => b = channel,
+        }
+        o.color = Color32::from_rgba_premultiplied(r, g, b, a);
+    }
+}
+
+/// Maps linear progress 0..1 onto the curve a tween follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EasingKind {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl EasingKind {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => t * (2.0 - t),
+            Self::EaseInOut if t < 0.5 => 2.0 * t * t,
+            Self::EaseInOut => 1.0 - 2.0 * (1.0 - t) * (1.0 - t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tween {
+    pub node_id: u32,
+    pub property: TweenProperty,
+    pub from: f32,
+    pub to: f32,
+    /// Seconds from `from` to `to`.
+    pub duration: f32,
+    pub elapsed: f32,
+    pub easing: EasingKind,
+}
+
+impl Tween {
+    /// Advances by `dt` and writes the eased value into `o`. Returns whether
+    /// the tween has reached `to`.
+    pub fn step(&mut self, o: &mut SceneObject, dt: f32) -> bool {
+        self.elapsed += dt;
+        let progress = if self.duration > 0.0 {
+            self.elapsed / self.duration
+        } else {
+            1.0
+        };
+        let t = self.easing.apply(progress);
+        self.property.set(o, self.from + (self.to - self.from) * t);
+        progress >= 1.0
+    }
+}
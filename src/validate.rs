@@ -0,0 +1,99 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::sketch::{SceneObject, flatten};
+
+/// A structural problem found by [`validate_scene`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// More than one node carries this ID.
+    DuplicateId(u32),
+    /// A node whose name is empty or only whitespace.
+    EmptyName(u32),
+    /// Links that lead back to where they started, in order.
+    CycleDetected(Vec<u32>),
+    /// A `source → target` link where one end isn't in the scene.
+    OrphanedLink(u32, u32),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateId(id) => write!(f, "ID {id} is used by more than one node"),
+            Self::EmptyName(id) => write!(f, "node {id} has no name"),
+            Self::CycleDetected(ids) => {
+                let path: Vec<String> = ids.iter().map(u32::to_string).collect();
+                write!(f, "links form a cycle: {}", path.join(" → "))
+            }
+            Self::OrphanedLink(a, b) => write!(f, "link {a} → {b} points at a missing node"),
+        }
+    }
+}
+
+/// Everything wrong with the scene, in a stable order: duplicate IDs, empty
+/// names, orphaned links, then link cycles. The parent-child tree itself can't
+/// loop since nodes own their children, so cycles are only looked for in `links`.
+pub fn validate_scene(objects: &[SceneObject], links: &[(u32, u32)]) -> Vec<ValidationError> {
+    let mut errors = vec![];
+    let nodes = flatten(objects);
+
+    let mut seen = HashSet::new();
+    let mut reported = HashSet::new();
+    for o in &nodes {
+        if !seen.insert(o.id) && reported.insert(o.id) {
+            errors.push(ValidationError::DuplicateId(o.id));
+        }
+    }
+    for o in &nodes {
+        if o.text.trim().is_empty() {
+            errors.push(ValidationError::EmptyName(o.id));
+        }
+    }
+    for &(a, b) in links {
+        if !seen.contains(&a) || !seen.contains(&b) {
+            errors.push(ValidationError::OrphanedLink(a, b));
+        }
+    }
+
+    let mut out: HashMap<u32, Vec<u32>> = HashMap::new();
+    for &(a, b) in links {
+        out.entry(a).or_default().push(b);
+    }
+    let mut done = HashSet::new();
+    for &(start, _) in links {
+        let mut path = vec![];
+        if let Some(cycle) = find_cycle(start, &out, &mut path, &mut done) {
+            // so later starts on the same loop don't report it again
+            done.extend(&cycle);
+            errors.push(ValidationError::CycleDetected(cycle));
+        }
+    }
+    errors
+}
+
+/// Depth-first walk from `id`; returns the first cycle found, closed with its
+/// first node repeated. Nodes fully explored go into `done` and aren't revisited.
+fn find_cycle(
+    id: u32,
+    out: &HashMap<u32, Vec<u32>>,
+    path: &mut Vec<u32>,
+    done: &mut HashSet<u32>,
+) -> Option<Vec<u32>> {
+    if let Some(i) = path.iter().position(|&p| p == id) {
+        let mut cycle = path[i..].to_vec();
+        cycle.push(id);
+        return Some(cycle);
+    }
+    if done.contains(&id) {
+        return None;
+    }
+    path.push(id);
+    for &next in out.get(&id).into_iter().flatten() {
+        if let Some(cycle) = find_cycle(next, out, path, done) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    done.insert(id);
+    None
+}
@@ -0,0 +1,353 @@
+//! Per-node frame scripts. A script is a list of assignments, one per line or
+//! separated by `;`, with `--` comments:
+//!
+//! ```text
+//! rotation_speed = sin(t) * 90
+//! color.r = abs(sin(t * 2)) * 255; scale = 1 + floor(frame / 30) * 0.1
+//! ```
+//!
+//! It is compiled once to stack bytecode and run every frame. Readable: `t`,
+//! `dt`, `frame`. Writable: `rotation_speed`, `color.r`, `color.g`, `color.b`,
+//! `scale`. Functions are the ones `expr` knows.
+
+use std::fmt;
+
+use crate::expr::Func;
+
+/* ---------------- errors ---------------- */
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    /// 1-based.
+    pub line: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.msg)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/* ---------------- bytecode ---------------- */
+
+/// What a script can read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Env {
+    pub t: f32,
+    pub dt: f32,
+    pub frame: u64,
+}
+
+/// What a script can write. Loaded from the node before a run, stored back after.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Registers {
+    pub rotation_speed: f32,
+    pub color: [f32; 3],
+    pub scale: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Var {
+    T,
+    Dt,
+    Frame,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    RotationSpeed,
+    Color(usize),
+    Scale,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Push(f32),
+    Load(Var),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Neg,
+    Call(Func),
+    Store(Target),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Program {
+    ops: Vec<Op>,
+}
+
+impl Program {
+    pub fn run(&self, env: &Env, regs: &mut Registers) {
+        let mut stack: Vec<f32> = Vec::with_capacity(8);
+        let pop = |stack: &mut Vec<f32>| stack.pop().unwrap_or(0.0);
+        for &op in &self.ops {
+            match op {
+                Op::Push(n) => stack.push(n),
+                Op::Load(Var::T) => stack.push(env.t),
+                Op::Load(Var::Dt) => stack.push(env.dt),
+                Op::Load(Var::Frame) => stack.push(env.frame as f32),
+                Op::Neg => {
+                    let a = pop(&mut stack);
+                    stack.push(-a);
+                }
+                Op::Call(f) => {
+                    let a = pop(&mut stack);
+                    stack.push(match f {
+                        Func::Sin => a.sin(),
+                        Func::Cos => a.cos(),
+                        Func::Abs => a.abs(),
+                        Func::Floor => a.floor(),
+                    });
+                }
+                Op::Add | Op::Sub | Op::Mul | Op::Div => {
+                    let b = pop(&mut stack);
+                    let a = pop(&mut stack);
+                    stack.push(match op {
+                        Op::Add => a + b,
+                        Op::Sub => a - b,
+                        Op::Mul => a * b,
+                        _ => a / b,
+                    });
+                }
+                Op::Store(target) => {
+                    let v = pop(&mut stack);
+                    match target {
+                        Target::RotationSpeed => regs.rotation_speed = v,
+                        Target::Color(i) => regs.color[i] = v,
+                        Target::Scale => regs.scale = v,
+                    }
+                }
+            }
+        }
+    }
+}
+
+/* ---------------- compiler ---------------- */
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Num(f32),
+    Name(String),
+    Sym(char),
+    /// Newline or `;`.
+    End,
+}
+
+fn tokenize(src: &str) -> Result<Vec<(Tok, usize)>, CompileError> {
+    let mut out = vec![];
+    for (n, line) in src.lines().enumerate() {
+        let line_no = n + 1;
+        let code = line.split("--").next().unwrap_or("");
+        let chars: Vec<char> = code.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let start = i;
+            if c.is_whitespace() {
+                i += 1;
+            } else if c.is_ascii_digit() || c == '.' {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let n = text.parse().map_err(|_| CompileError {
+                    line: line_no,
+                    msg: format!("bad number `{text}`"),
+                })?;
+                out.push((Tok::Num(n), line_no));
+            } else if c.is_alphabetic() || c == '_' {
+                // `.` joins field names, as in `color.r`
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+                {
+                    i += 1;
+                }
+                out.push((Tok::Name(chars[start..i].iter().collect()), line_no));
+            } else if c == ';' {
+                out.push((Tok::End, line_no));
+                i += 1;
+            } else if "+-*/()=".contains(c) {
+                out.push((Tok::Sym(c), line_no));
+                i += 1;
+            } else {
+                return Err(CompileError {
+                    line: line_no,
+                    msg: format!("unexpected `{c}`"),
+                });
+            }
+        }
+        out.push((Tok::End, line_no));
+    }
+    Ok(out)
+}
+
+struct Compiler {
+    toks: Vec<(Tok, usize)>,
+    pos: usize,
+    ops: Vec<Op>,
+}
+
+impl Compiler {
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn line(&self) -> usize {
+        self.toks
+            .get(self.pos)
+            .or(self.toks.last())
+            .map_or(1, |(_, l)| *l)
+    }
+
+    fn error(&self, msg: impl Into<String>) -> CompileError {
+        CompileError {
+            line: self.line(),
+            msg: msg.into(),
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(&Tok::Sym(c));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), CompileError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{c}`")))
+        }
+    }
+
+    // statement := target '=' sum
+    fn statement(&mut self) -> Result<(), CompileError> {
+        let target = match self.peek() {
+            Some(Tok::Name(n)) => match n.as_str() {
+                "rotation_speed" => Target::RotationSpeed,
+                "color.r" => Target::Color(0),
+                "color.g" => Target::Color(1),
+                "color.b" => Target::Color(2),
+                "scale" => Target::Scale,
+                _ => return Err(self.error(format!("can't assign to `{n}`"))),
+            },
+            _ => return Err(self.error("expected a name to assign to")),
+        };
+        self.pos += 1;
+        self.expect('=')?;
+        self.sum()?;
+        self.ops.push(Op::Store(target));
+        match self.peek() {
+            Some(Tok::End) | None => Ok(()),
+            _ => Err(self.error("expected end of statement")),
+        }
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<(), CompileError> {
+        self.product()?;
+        loop {
+            let op = if self.eat('+') {
+                Op::Add
+            } else if self.eat('-') {
+                Op::Sub
+            } else {
+                return Ok(());
+            };
+            self.product()?;
+            self.ops.push(op);
+        }
+    }
+
+    // product := unary (('*' | '/') unary)*
+    fn product(&mut self) -> Result<(), CompileError> {
+        self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            } else if self.eat('/') {
+                Op::Div
+            } else {
+                return Ok(());
+            };
+            self.unary()?;
+            self.ops.push(op);
+        }
+    }
+
+    // unary := '-' unary | atom
+    fn unary(&mut self) -> Result<(), CompileError> {
+        if self.eat('-') {
+            self.unary()?;
+            self.ops.push(Op::Neg);
+            return Ok(());
+        }
+        self.atom()
+    }
+
+    // atom := number | var | func '(' sum ')' | '(' sum ')'
+    fn atom(&mut self) -> Result<(), CompileError> {
+        match self.peek().cloned() {
+            Some(Tok::Num(n)) => {
+                self.pos += 1;
+                self.ops.push(Op::Push(n));
+            }
+            Some(Tok::Sym('(')) => {
+                self.pos += 1;
+                self.sum()?;
+                self.expect(')')?;
+            }
+            Some(Tok::Name(name)) => {
+                let var = match name.as_str() {
+                    "t" => Some(Var::T),
+                    "dt" => Some(Var::Dt),
+                    "frame" => Some(Var::Frame),
+                    _ => None,
+                };
+                if let Some(var) = var {
+                    self.pos += 1;
+                    self.ops.push(Op::Load(var));
+                    return Ok(());
+                }
+                let func = match name.as_str() {
+                    "sin" => Func::Sin,
+                    "cos" => Func::Cos,
+                    "abs" => Func::Abs,
+                    "floor" => Func::Floor,
+                    _ => return Err(self.error(format!("unknown name `{name}`"))),
+                };
+                self.pos += 1;
+                self.expect('(')?;
+                self.sum()?;
+                self.expect(')')?;
+                self.ops.push(Op::Call(func));
+            }
+            Some(Tok::Sym(c)) => return Err(self.error(format!("unexpected `{c}`"))),
+            Some(Tok::End) | None => return Err(self.error("unexpected end of statement")),
+        }
+        Ok(())
+    }
+}
+
+pub fn compile(src: &str) -> Result<Program, CompileError> {
+    let mut c = Compiler {
+        toks: tokenize(src)?,
+        pos: 0,
+        ops: vec![],
+    };
+    while c.pos < c.toks.len() {
+        if c.peek() == Some(&Tok::End) {
+            c.pos += 1;
+            continue;
+        }
+        c.statement()?;
+    }
+    Ok(Program { ops: c.ops })
+}
@@ -0,0 +1,23 @@
+//! Browser entry point. Build with `trunk serve` (see `index.html`).
+
+use eframe::wasm_bindgen::{self, JsCast, prelude::*};
+use eframe::web_sys;
+
+use crate::sketch::AppState;
+
+/// Called from the page once the wasm module is loaded.
+#[wasm_bindgen]
+pub async fn start(canvas_id: &str) -> Result<(), JsValue> {
+    let canvas = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.get_element_by_id(canvas_id))
+        .ok_or_else(|| JsValue::from_str(&format!("no element with id `{canvas_id}`")))?
+        .dyn_into::<web_sys::HtmlCanvasElement>()?;
+    eframe::WebRunner::new()
+        .start(
+            canvas,
+            eframe::WebOptions::default(),
+            Box::new(|cc| Ok(Box::new(AppState::new(cc)?))),
+        )
+        .await
+}
@@ -0,0 +1,310 @@
+//! Additive glow drawn behind the nodes for high-priority ones, as a wgpu
+//! paint callback. `init` sets up the pipeline once per renderer; after that a
+//! frame's glows are handed over through `callback`, into a shape slot the
+//! canvas reserves before drawing the nodes.
+
+use eframe::egui::{self, Color32, Pos2, Rect};
+use eframe::egui_wgpu::{self, CallbackResources, CallbackTrait, RenderState, ScreenDescriptor};
+use eframe::wgpu;
+
+/// How far the glow reaches past the shape's edge, as a fraction of its size.
+const GLOW_REACH: f32 = 0.35;
+/// Peak alpha of the glow, relative to the node's own.
+const GLOW_ALPHA: f32 = 0.6;
+/// Floats per instance: center (2), size, rotation, color (4), shape, padding (3).
+const INSTANCE_FLOATS: usize = 12;
+
+const SHADER: &str = r#"
+struct Viewport {
+    size: vec2<f32>,
+    reach: f32,
+    alpha: f32,
+};
+@group(0) @binding(0) var<uniform> viewport: Viewport;
+
+struct Instance {
+    @location(0) center: vec2<f32>,
+    @location(1) size_rot: vec2<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) shape: vec4<f32>,
+};
+
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    // unrotated offset from the center, in points
+    @location(0) local: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) size: f32,
+    @location(3) shape: f32,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vi: u32, inst: Instance) -> VsOut {
+    var corners = array<vec2<f32>, 6>(
+        vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(1.0, 1.0),
+        vec2(-1.0, -1.0), vec2(1.0, 1.0), vec2(-1.0, 1.0),
+    );
+    let size = inst.size_rot.x;
+    let local = corners[vi] * size * (0.5 + viewport.reach);
+    let c = cos(inst.size_rot.y);
+    let s = sin(inst.size_rot.y);
+    let p = inst.center + vec2(local.x * c - local.y * s, local.x * s + local.y * c);
+    var out: VsOut;
+    out.pos = vec4(p.x / viewport.size.x * 2.0 - 1.0, 1.0 - p.y / viewport.size.y * 2.0, 0.0, 1.0);
+    out.local = local;
+    out.color = inst.color;
+    out.size = size;
+    out.shape = inst.shape.x;
+    return out;
+}
+
+fn sd_box(p: vec2<f32>, r: f32) -> f32 {
+    let d = abs(p) - vec2(r);
+    return length(max(d, vec2(0.0))) + min(max(d.x, d.y), 0.0);
+}
+
+// Signed distance to the triangle `triangle_points` draws.
+fn sd_triangle(p: vec2<f32>, h: f32) -> f32 {
+    let p0 = vec2(0.0, -h);
+    let p1 = vec2(-h, h);
+    let p2 = vec2(h, h);
+    let e0 = p1 - p0;
+    let e1 = p2 - p1;
+    let e2 = p0 - p2;
+    let v0 = p - p0;
+    let v1 = p - p1;
+    let v2 = p - p2;
+    let pq0 = v0 - e0 * clamp(dot(v0, e0) / dot(e0, e0), 0.0, 1.0);
+    let pq1 = v1 - e1 * clamp(dot(v1, e1) / dot(e1, e1), 0.0, 1.0);
+    let pq2 = v2 - e2 * clamp(dot(v2, e2) / dot(e2, e2), 0.0, 1.0);
+    let s = sign(e0.x * e2.y - e0.y * e2.x);
+    let d = min(min(
+        vec2(dot(pq0, pq0), s * (v0.x * e0.y - v0.y * e0.x)),
+        vec2(dot(pq1, pq1), s * (v1.x * e1.y - v1.y * e1.x))),
+        vec2(dot(pq2, pq2), s * (v2.x * e2.y - v2.y * e2.x)));
+    return -sqrt(d.x) * sign(d.y);
+}
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+    let h = in.size * 0.5;
+    var d: f32;
+    if in.shape < 0.5 {
+        d = sd_box(in.local, h);
+    } else if in.shape < 1.5 {
+        d = length(in.local) - h;
+    } else {
+        d = sd_triangle(in.local, h);
+    }
+    let falloff = 1.0 - clamp(max(d, 0.0) / (in.size * viewport.reach), 0.0, 1.0);
+    return vec4(in.color.rgb, in.color.a * viewport.alpha * falloff * falloff);
+}
+"#;
+
+/// One node to glow, in screen points.
+#[derive(Debug, Clone, Copy)]
+pub struct Glow {
+    pub center: Pos2,
+    pub size: f32,
+    pub rotation_deg: f32,
+    pub color: Color32,
+    /// 0 = square, 1 = circle, 2 = triangle.
+    pub shape: u8,
+}
+
+struct GlowResources {
+    pipeline: wgpu::RenderPipeline,
+    uniform: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    instances: wgpu::Buffer,
+    /// Instances written by the last `prepare`.
+    count: u32,
+}
+
+/// Builds the pipeline and parks it in the renderer's callback resources.
+pub fn init(render_state: &RenderState) {
+    let device = &render_state.device;
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("glow"),
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("glow"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("glow"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let stride = (INSTANCE_FLOATS * size_of::<f32>()) as wgpu::BufferAddress;
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("glow"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: stride,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &wgpu::vertex_attr_array![
+                    0 => Float32x2,
+                    1 => Float32x2,
+                    2 => Float32x4,
+                    3 => Float32x4,
+                ],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: render_state.target_format,
+                // additive, so overlapping glows brighten instead of covering each other
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent::OVER,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    });
+    let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("glow viewport"),
+        size: 16,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("glow"),
+        layout: &bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: uniform.as_entire_binding(),
+        }],
+    });
+    let instances = instance_buffer(device, 64);
+    render_state
+        .renderer
+        .write()
+        .callback_resources
+        .insert(GlowResources {
+            pipeline,
+            uniform,
+            bind_group,
+            instances,
+            count: 0,
+        });
+}
+
+fn instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("glow instances"),
+        size: (capacity * INSTANCE_FLOATS * size_of::<f32>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+struct GlowCallback {
+    /// The callback's viewport; glows are uploaded relative to its corner.
+    rect: Rect,
+    glows: Vec<Glow>,
+}
+
+/// A paint callback over `rect` that draws `glows`. Needs `init` to have run.
+pub fn callback(rect: Rect, glows: Vec<Glow>) -> egui::PaintCallback {
+    egui_wgpu::Callback::new_paint_callback(rect, GlowCallback { rect, glows })
+}
+
+impl CallbackTrait for GlowCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen: &ScreenDescriptor,
+        _encoder: &mut wgpu::CommandEncoder,
+        resources: &mut CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let Some(res) = resources.get_mut::<GlowResources>() else {
+            return vec![];
+        };
+        let floats: Vec<f32> = self
+            .glows
+            .iter()
+            .flat_map(|g| {
+                let [r, gr, b, a] = g.color.to_array().map(|c| c as f32 / 255.0);
+                let p = g.center - self.rect.min;
+                [
+                    p.x,
+                    p.y,
+                    g.size,
+                    g.rotation_deg.to_radians(),
+                    r,
+                    gr,
+                    b,
+                    a,
+                    g.shape as f32,
+                    0.0,
+                    0.0,
+                    0.0,
+                ]
+            })
+            .collect();
+        let size = floats.len() * size_of::<f32>();
+        if size as wgpu::BufferAddress > res.instances.size() {
+            res.instances = instance_buffer(device, self.glows.len().next_power_of_two());
+        }
+        let bytes: Vec<u8> = floats.iter().flat_map(|f| f.to_ne_bytes()).collect();
+        queue.write_buffer(&res.instances, 0, &bytes);
+        // egui sets the viewport to the callback rect, so that's what NDC spans
+        let viewport = [
+            self.rect.width(),
+            self.rect.height(),
+            GLOW_REACH,
+            GLOW_ALPHA,
+        ];
+        let bytes: Vec<u8> = viewport.iter().flat_map(|f| f.to_ne_bytes()).collect();
+        queue.write_buffer(&res.uniform, 0, &bytes);
+        res.count = self.glows.len() as u32;
+        vec![]
+    }
+
+    fn paint(
+        &self,
+        _info: egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'static>,
+        resources: &CallbackResources,
+    ) {
+        let Some(res) = resources.get::<GlowResources>() else {
+            return;
+        };
+        if res.count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&res.pipeline);
+        render_pass.set_bind_group(0, &res.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, res.instances.slice(..));
+        render_pass.draw(0..6, 0..res.count);
+    }
+}